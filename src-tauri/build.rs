@@ -1,3 +1,54 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
 fn main() {
+  embed_migrations();
   tauri_build::build()
 }
+
+/// 扫描 migrations/NNN_描述.sql，按版本号生成迁移列表（由 src/schema.rs 引入）
+fn embed_migrations() {
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+  println!("cargo:rerun-if-changed={}", dir.display());
+
+  let mut migrations = Vec::new();
+  for entry in fs::read_dir(&dir).expect("无法读取 migrations 目录") {
+    let path = entry.expect("无法读取 migrations 目录").path();
+    if path.extension().and_then(|e| e.to_str()) != Some("sql") {
+      continue;
+    }
+    let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+    let (version, description) = stem
+      .split_once('_')
+      .unwrap_or_else(|| panic!("迁移文件名应为 NNN_描述.sql: {}", stem));
+    let version: i64 = version
+      .parse()
+      .unwrap_or_else(|_| panic!("迁移文件名应以版本号开头: {}", stem));
+    migrations.push((version, description.replace('_', " "), path));
+  }
+
+  migrations.sort_by_key(|(version, _, _)| *version);
+  for (index, (version, _, path)) in migrations.iter().enumerate() {
+    assert_eq!(
+      *version,
+      index as i64 + 1,
+      "迁移版本号必须从 1 开始连续编号: {}",
+      path.display()
+    );
+  }
+
+  let mut code = String::from("&[\n");
+  for (version, description, path) in &migrations {
+    code.push_str(&format!(
+      "    SchemaMigration {{ version: {}, description: {:?}, sql: include_str!({:?}) }},\n",
+      version,
+      description,
+      path.to_string_lossy()
+    ));
+  }
+  code.push_str("]\n");
+
+  let out = Path::new(&env::var("OUT_DIR").unwrap()).join("migrations.rs");
+  fs::write(out, code).expect("无法写入迁移列表");
+}
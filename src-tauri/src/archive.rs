@@ -0,0 +1,524 @@
+//! ZIP 归档导入导出
+//!
+//! 说明：粘贴的图片以 data URL 的形式内嵌在 notes.content 中，导出文件体积大且无法 diff。
+//! 导出 ZIP 时将图片提取为 `assets/<SHA-256>.<扩展名>`，正文中的引用改写为相对路径，
+//! 相同图片只保存一份；导入时再将相对路径还原为 data URL
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use regex::{Captures, Regex};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::export;
+use crate::export_format;
+use crate::markdown::{self, MarkdownSource};
+use crate::models::{
+    ArchiveExportOptions, ArchiveExportReport, ArchiveFormat, ExportData, ExportFilter,
+    ImportOptions, ImportReport, MarkdownExportOptions,
+};
+use crate::storage;
+
+/// JSON 格式归档中的笔记文件
+const NOTES_FILE: &str = "notes.json";
+
+/// 图片所在目录
+const ASSETS_DIR: &str = "assets";
+
+/// 导入时单个文件解压后的最大字节数
+pub const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// 导入时全部文件解压后的最大总字节数（导入的文件都读入内存）
+pub const MAX_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// 图片 MIME 子类型与文件扩展名的对应关系
+const IMAGE_TYPES: [(&str, &str); 8] = [
+    ("png", "png"),
+    ("jpeg", "jpg"),
+    ("gif", "gif"),
+    ("webp", "webp"),
+    ("svg+xml", "svg"),
+    ("bmp", "bmp"),
+    ("x-icon", "ico"),
+    ("avif", "avif"),
+];
+
+fn data_url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"data:image/([A-Za-z0-9.+-]+);base64,([A-Za-z0-9+/]+=*)").unwrap()
+    })
+}
+
+fn asset_ref_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:\./)?assets/[0-9a-f]{64}\.[A-Za-z0-9]+").unwrap())
+}
+
+fn zip_err(e: ZipError) -> String {
+    format!("读写 ZIP 归档失败: {}", e)
+}
+
+/// 根据 MIME 子类型确定扩展名，未知类型保留子类型中的字母数字
+fn extension_for(subtype: &str) -> String {
+    let subtype = subtype.to_lowercase();
+    if let Some((_, ext)) = IMAGE_TYPES.iter().find(|(s, _)| *s == subtype) {
+        return ext.to_string();
+    }
+    let ext: String = subtype
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if ext.is_empty() {
+        "bin".to_string()
+    } else {
+        ext
+    }
+}
+
+/// 根据扩展名还原 MIME 类型
+fn mime_for(ext: &str) -> String {
+    let ext = match ext.to_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        ext => ext.to_string(),
+    };
+    let subtype = IMAGE_TYPES
+        .iter()
+        .find(|(_, e)| *e == ext)
+        .map(|(s, _)| s.to_string())
+        .unwrap_or(ext);
+    format!("image/{}", subtype)
+}
+
+/// 读取导入文件时的大小限制，防止压缩炸弹耗尽内存
+///
+/// 先按文件头中声明的大小检查，读取时再按实际读出的字节数检查（声明的大小可能是伪造的）
+pub struct SizeLimit {
+    entry_limit: u64,
+    total_limit: u64,
+    total: u64,
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self {
+            entry_limit: MAX_ENTRY_SIZE,
+            total_limit: MAX_TOTAL_SIZE,
+            total: 0,
+        }
+    }
+}
+
+impl SizeLimit {
+    #[cfg(test)]
+    fn with_limits(entry_limit: u64, total_limit: u64) -> Self {
+        Self {
+            entry_limit,
+            total_limit,
+            total: 0,
+        }
+    }
+
+    fn too_large(&self, name: &str) -> String {
+        format!(
+            "{} 解压后超过 {} MB，可能是损坏或恶意构造的压缩文件",
+            name,
+            self.entry_limit / 1024 / 1024
+        )
+    }
+
+    fn total_too_large(&self) -> String {
+        format!(
+            "导入文件解压后的总大小超过 {} MB，请拆分后再导入",
+            self.total_limit / 1024 / 1024
+        )
+    }
+
+    /// 读取一个文件的全部内容，declared 为文件头中声明的解压后大小
+    pub fn read(
+        &mut self,
+        name: &str,
+        declared: u64,
+        reader: impl Read,
+    ) -> Result<Vec<u8>, String> {
+        if declared > self.entry_limit {
+            return Err(self.too_large(name));
+        }
+        let remaining = self.total_limit - self.total;
+        if declared > remaining {
+            return Err(self.total_too_large());
+        }
+
+        let limit = self.entry_limit.min(remaining);
+        let mut bytes = Vec::new();
+        reader
+            .take(limit + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+        let size = bytes.len() as u64;
+        if size > self.entry_limit {
+            return Err(self.too_large(name));
+        }
+        if size > remaining {
+            return Err(self.total_too_large());
+        }
+        self.total += size;
+        Ok(bytes)
+    }
+}
+
+/// 是否为常见图片扩展名
+pub fn is_image_ext(ext: &str) -> bool {
+    let ext = ext.to_lowercase();
+    ext == "jpeg" || IMAGE_TYPES.iter().any(|(_, e)| *e == ext)
+}
+
+/// 将图片文件内容转换为 data URL
+pub fn to_data_url(ext: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_for(ext), STANDARD.encode(bytes))
+}
+
+/// 将正文中的 data URL 图片提取到 assets，返回改写后的正文
+/// assets 的键为归档内路径（如 `assets/<hash>.png`），无法解码的 data URL 保持原样
+pub fn extract_assets(content: &str, assets: &mut BTreeMap<String, Vec<u8>>) -> String {
+    data_url_regex()
+        .replace_all(content, |caps: &Captures| {
+            let Ok(bytes) = STANDARD.decode(&caps[2]) else {
+                return caps[0].to_string();
+            };
+            let name = format!(
+                "{}/{:x}.{}",
+                ASSETS_DIR,
+                Sha256::digest(&bytes),
+                extension_for(&caps[1])
+            );
+            assets.entry(name.clone()).or_insert(bytes);
+            name
+        })
+        .into_owned()
+}
+
+/// 将正文中指向归档内图片的相对路径还原为 data URL，归档中不存在的引用保持原样
+pub fn inline_assets(content: &str, assets: &HashMap<String, Vec<u8>>) -> String {
+    asset_ref_regex()
+        .replace_all(content, |caps: &Captures| {
+            let name = caps[0].trim_start_matches("./");
+            let Some(bytes) = assets.get(name) else {
+                return caps[0].to_string();
+            };
+            let ext = name.rsplit('.').next().unwrap_or_default();
+            to_data_url(ext, bytes)
+        })
+        .into_owned()
+}
+
+/// 将数据库导出为 ZIP 归档，提供筛选条件时只导出满足条件的笔记
+pub fn export_archive(
+    conn: &Connection,
+    path: &Path,
+    options: &ArchiveExportOptions,
+    filter: Option<&ExportFilter>,
+) -> Result<ArchiveExportReport, String> {
+    let mut data = storage::build_filtered_export(conn, filter)?;
+    if options.format == ArchiveFormat::Markdown && filter.is_none() {
+        data.notes.retain(|note| note.is_deleted == 0);
+    }
+
+    let mut assets = BTreeMap::new();
+    for note in &mut data.notes {
+        note.content = extract_assets(&note.content, &mut assets);
+    }
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // 图片本身已压缩，直接存储
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let bytes_written = export::write_atomically(path, |writer| {
+        let mut zip = ZipWriter::new(writer);
+        match options.format {
+            ArchiveFormat::Json => {
+                zip.start_file(NOTES_FILE, deflated).map_err(zip_err)?;
+                serde_json::to_writer_pretty(&mut zip, &data)
+                    .map_err(|e| format!("序列化失败: {}", e))?;
+            }
+            ArchiveFormat::Markdown => {
+                let md_options = MarkdownExportOptions {
+                    include_chat: options.include_chat,
+                };
+                for file in markdown::render_files(&data.notes, &data.chat_messages, &md_options)? {
+                    zip.start_file(file.name, deflated).map_err(zip_err)?;
+                    zip.write_all(file.content.as_bytes())
+                        .map_err(|e| format!("写入 ZIP 归档失败: {}", e))?;
+                }
+            }
+        }
+        for (name, bytes) in &assets {
+            zip.start_file(name, stored).map_err(zip_err)?;
+            zip.write_all(bytes)
+                .map_err(|e| format!("写入 ZIP 归档失败: {}", e))?;
+        }
+        zip.finish().map_err(zip_err)?;
+        Ok(())
+    })?;
+
+    Ok(ArchiveExportReport {
+        path: path.to_string_lossy().to_string(),
+        notes_exported: data.notes.len(),
+        assets_exported: assets.len(),
+        bytes_written,
+    })
+}
+
+/// 读取归档中的全部文件
+fn read_entries<R: Read + Seek>(reader: R) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut archive = ZipArchive::new(reader).map_err(zip_err)?;
+    let mut entries = BTreeMap::new();
+    let mut limit = SizeLimit::default();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(zip_err)?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = file.name().replace('\\', "/");
+        let bytes = limit.read(&name, file.size(), file)?;
+        entries.insert(name, bytes);
+    }
+    Ok(entries)
+}
+
+/// 导入 ZIP 归档（JSON 或 Markdown 格式），图片还原为 data URL 后按冲突策略写入
+/// JSON 格式会先升级并校验 notes.json，存在校验错误时不写入任何数据
+pub fn import_archive<R: Read + Seek>(
+    conn: &mut Connection,
+    reader: R,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let assets_prefix = format!("{}/", ASSETS_DIR);
+    let mut assets = HashMap::new();
+    let mut entries = BTreeMap::new();
+    for (name, bytes) in read_entries(reader)? {
+        if name.starts_with(&assets_prefix) {
+            assets.insert(name, bytes);
+        } else {
+            entries.insert(name, bytes);
+        }
+    }
+
+    let mut skipped = Vec::new();
+    let mut validation = None;
+    let mut data = if let Some(json) = entries.remove(NOTES_FILE) {
+        let json =
+            String::from_utf8(json).map_err(|_| "notes.json 不是有效的 UTF-8".to_string())?;
+        let (data, report) = export_format::parse_export(&json);
+        let Some(data) = data else {
+            return Ok(ImportReport {
+                validation: Some(report),
+                ..Default::default()
+            });
+        };
+        validation = Some(report);
+        data
+    } else {
+        let now = SystemTime::now();
+        let mut notes = Vec::new();
+        for (index, (name, bytes)) in entries
+            .iter()
+            .filter(|(name, _)| markdown::is_markdown_file(Path::new(name.as_str())))
+            .enumerate()
+        {
+            let source = MarkdownSource {
+                rel_path: Path::new(name.as_str()),
+                created: now,
+                modified: now,
+                folder_tags: Vec::new(),
+            };
+            let parsed = std::str::from_utf8(bytes)
+                .map_err(|_| "不是有效的 UTF-8".to_string())
+                .and_then(|text| markdown::parse_markdown(text, &source));
+            match parsed {
+                Ok(note) => notes.push(note),
+                Err(reason) => skipped.push(storage::import_issue(
+                    "note",
+                    index,
+                    None,
+                    format!("{}: {}", name, reason),
+                )),
+            }
+        }
+        if notes.is_empty() && skipped.is_empty() {
+            return Err("归档中没有 notes.json 或 Markdown 文件".to_string());
+        }
+        ExportData {
+            version: storage::EXPORT_VERSION.to_string(),
+            exported_at: String::new(),
+            notes,
+            chat_messages: Vec::new(),
+        }
+    };
+
+    for note in &mut data.notes {
+        note.content = inline_assets(&note.content, &assets);
+    }
+
+    let mut report = storage::import_export_data(conn, &data, options)?;
+    report.skipped.extend(skipped);
+    report.validation = validation;
+    Ok(report)
+}
+
+/// 从文件导入 ZIP 归档
+pub fn import_archive_file(
+    conn: &mut Connection,
+    path: &Path,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let file = File::open(path).map_err(|e| format!("打开归档文件失败: {}", e))?;
+    import_archive(conn, file, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const PNG: &str = "iVBORw0KGgo=";
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        let content = format!(
+            "# 截图\n\n![](data:image/png;base64,{png})\n\n<img src=\"data:image/png;base64,{png}\">",
+            png = PNG
+        );
+        conn.execute(
+            "INSERT INTO notes (title, content, tags, created_at, updated_at) VALUES ('图片', ?1, '[\"a\"]', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z')",
+            [content],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at, is_deleted) VALUES ('已删除', '', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z', 1);
+             INSERT INTO chat_messages (note_id, role, content, timestamp) VALUES (1, 'user', 'hi', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn extracts_and_inlines_assets() {
+        let mut assets = BTreeMap::new();
+        let content = format!(
+            "![a](data:image/png;base64,{0}) ![b](data:image/png;base64,{0}) data:image/png;base64,@@",
+            PNG
+        );
+        let rewritten = extract_assets(&content, &mut assets);
+        assert_eq!(assets.len(), 1);
+        let name = assets.keys().next().unwrap().clone();
+        assert!(name.starts_with("assets/") && name.ends_with(".png"));
+        assert_eq!(rewritten.matches(&name).count(), 2);
+        assert!(rewritten.ends_with("data:image/png;base64,@@"));
+
+        let assets: HashMap<_, _> = assets.into_iter().collect();
+        assert_eq!(inline_assets(&rewritten, &assets), content);
+        assert_eq!(extension_for("jpeg"), "jpg");
+        assert_eq!(mime_for("svg"), "image/svg+xml");
+    }
+
+    fn round_trip(format: ArchiveFormat) -> (ArchiveExportReport, Connection) {
+        let conn = setup();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.zip");
+        let options = ArchiveExportOptions {
+            format,
+            include_chat: true,
+        };
+        let report = export_archive(&conn, &path, &options, None).unwrap();
+        assert_eq!(report.assets_exported, 1);
+
+        let entries = read_entries(File::open(&path).unwrap()).unwrap();
+        assert!(entries
+            .values()
+            .all(|bytes| !String::from_utf8_lossy(bytes).contains("data:image")));
+
+        let mut target = Connection::open_in_memory().unwrap();
+        target
+            .execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        let imported = import_archive_file(&mut target, &path, &ImportOptions::default()).unwrap();
+        assert!(imported.committed);
+
+        let original = storage::read_notes(&conn).unwrap();
+        let notes = storage::read_notes(&target).unwrap();
+        assert_eq!(notes[0].content.trim_end(), original[0].content);
+        assert_eq!(notes[0].tags, original[0].tags);
+        (report, target)
+    }
+
+    #[test]
+    fn round_trips_json_archive() {
+        let (report, target) = round_trip(ArchiveFormat::Json);
+        assert_eq!(report.notes_exported, 2);
+        assert_eq!(storage::read_chat_messages(&target).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn round_trips_markdown_archive() {
+        let (report, target) = round_trip(ArchiveFormat::Markdown);
+        assert_eq!(report.notes_exported, 1);
+        assert_eq!(storage::read_notes(&target).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_archives_without_notes() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut zip = ZipWriter::new(&mut buffer);
+        zip.start_file("readme.txt", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"x").unwrap();
+        zip.finish().unwrap();
+
+        let mut conn = setup();
+        buffer.set_position(0);
+        let err = import_archive(&mut conn, buffer, &ImportOptions::default()).unwrap_err();
+        assert!(err.contains("notes.json"));
+    }
+
+    #[test]
+    fn limits_uncompressed_size() {
+        // 高度压缩的文件：声明的大小超过限制时不读取
+        let mut buffer = Cursor::new(Vec::new());
+        let mut zip = ZipWriter::new(&mut buffer);
+        zip.start_file("bomb.txt", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&[0u8; 4096]).unwrap();
+        zip.finish().unwrap();
+        buffer.set_position(0);
+        let mut archive = ZipArchive::new(buffer).unwrap();
+        let file = archive.by_index(0).unwrap();
+        let size = file.size();
+        let err = SizeLimit::with_limits(1000, 10_000)
+            .read("bomb.txt", size, file)
+            .unwrap_err();
+        assert!(err.contains("bomb.txt"));
+
+        // 声明的大小是伪造的：按实际读出的字节数检查
+        let mut limit = SizeLimit::with_limits(1000, 1500);
+        let err = limit.read("fake", 10, &[0u8; 1001][..]).unwrap_err();
+        assert!(err.contains("fake"));
+
+        // 总大小超过限制
+        assert_eq!(limit.read("a", 800, &[1u8; 800][..]).unwrap().len(), 800);
+        let err = limit.read("b", 0, &[1u8; 800][..]).unwrap_err();
+        assert!(err.contains("总大小"));
+    }
+}
@@ -0,0 +1,707 @@
+//! 数据库自动备份
+//!
+//! 说明：备份文件名为 `jdnotes-YYYYMMDD-HHMMSS.db`（UTC 时间），
+//! 通过 `VACUUM INTO` 在线生成，不影响前端正在使用的数据库。
+//! 每次备份后按「每小时 / 每天 / 每周各保留最新一份」的策略清理旧备份。
+//! 恢复、修复前生成的快照命名为 `jdnotes-<标记>-YYYYMMDD-HHMMSS.db`（如 `pre-restore`），
+//! 会列在备份列表中，但不参与保留策略清理，需要用户手动删除。
+//! 恢复时通过 SQLite 在线备份 API 把备份内容写回当前数据库文件，
+//! 前端已打开的连接无需关闭即可看到恢复后的数据
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::backup::{Backup, StepResult};
+
+use crate::encryption;
+use crate::models::{BackupInfo, BackupSettings, RestoreReport};
+use crate::schema;
+use crate::storage::{self, to_iso};
+
+const FILE_PREFIX: &str = "jdnotes-";
+const FILE_SUFFIX: &str = ".db";
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// 文件名中时间部分的长度（YYYYMMDD-HHMMSS）
+const TIME_LEN: usize = 15;
+
+/// 从备份恢复前生成的快照标记
+pub const PRE_RESTORE: &str = "pre-restore";
+
+/// 修复数据库前生成的快照标记
+pub const PRE_REPAIR: &str = "pre-repair";
+
+/// 启用加密前生成的快照标记
+pub const PRE_ENCRYPT: &str = "pre-encrypt";
+
+/// SQLite 数据库文件头
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// 恢复时必须存在的表和列
+const REQUIRED_COLUMNS: [(&str, &[&str]); 2] = [
+    (
+        "notes",
+        &[
+            "id",
+            "title",
+            "content",
+            "tags",
+            "is_favorite",
+            "is_deleted",
+            "created_at",
+            "updated_at",
+            "reminder_date",
+            "reminder_enabled",
+        ],
+    ),
+    (
+        "chat_messages",
+        &["id", "note_id", "role", "content", "timestamp"],
+    ),
+];
+
+/// 恢复时等待数据库解锁的间隔
+const RESTORE_BUSY_PAUSE: Duration = Duration::from_millis(100);
+
+/// 同一时间只执行一个备份（定时备份与退出备份可能同时触发）
+static BACKUP_LOCK: Mutex<()> = Mutex::new(());
+
+/// 从备份文件名解析备份时间（快照返回 None，不参与保留策略）
+fn parse_file_time(file_name: &str) -> Option<DateTime<Utc>> {
+    let stamp = file_name
+        .strip_prefix(FILE_PREFIX)?
+        .strip_suffix(FILE_SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, TIME_FORMAT)
+        .ok()
+        .map(|dt| dt.and_utc())
+}
+
+/// 从快照文件名解析快照标记和时间
+fn parse_snapshot_name(file_name: &str) -> Option<(String, DateTime<Utc>)> {
+    let rest = file_name
+        .strip_prefix(FILE_PREFIX)?
+        .strip_suffix(FILE_SUFFIX)?;
+    let split = rest.len().checked_sub(TIME_LEN + 1)?;
+    let (label, stamp) = (rest.get(..split)?, rest.get(split + 1..)?);
+    if label.is_empty() || !rest[split..].starts_with('-') {
+        return None;
+    }
+    let time = NaiveDateTime::parse_from_str(stamp, TIME_FORMAT).ok()?;
+    Some((label.to_string(), time.and_utc()))
+}
+
+/// 列出备份目录中的备份，按时间从新到旧排序（目录不存在时返回空列表）
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取备份目录失败: {}", e))?;
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取备份目录失败: {}", e))?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let (label, time) = match parse_file_time(&file_name) {
+            Some(time) => (None, time),
+            None => match parse_snapshot_name(&file_name) {
+                Some((label, time)) => (Some(label), time),
+                None => continue,
+            },
+        };
+        let metadata = entry
+            .metadata()
+            .map_err(|e| format!("获取备份文件信息失败: {}", e))?;
+        if !metadata.is_file() {
+            continue;
+        }
+        backups.push(BackupInfo {
+            path: entry.path().to_string_lossy().to_string(),
+            file_name,
+            created_at: to_iso(time),
+            size: metadata.len(),
+            label,
+        });
+    }
+    // 按时间倒序，同一时间的快照排在备份之后
+    backups.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then(a.label.is_some().cmp(&b.label.is_some()))
+            .then(b.file_name.cmp(&a.file_name))
+    });
+    Ok(backups)
+}
+
+fn write_backup(
+    db_path: &Path,
+    dir: &Path,
+    label: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<BackupInfo, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+    let label_part = label.map(|l| format!("{}-", l)).unwrap_or_default();
+    let file_name = format!(
+        "{}{}{}{}",
+        FILE_PREFIX,
+        label_part,
+        now.format(TIME_FORMAT),
+        FILE_SUFFIX
+    );
+    let path = dir.join(&file_name);
+    let size = storage::copy_database(db_path, &path)?;
+    Ok(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        file_name,
+        created_at: to_iso(now),
+        size,
+        label: label.map(str::to_string),
+    })
+}
+
+/// 生成一份备份
+pub fn create_backup(db_path: &Path, dir: &Path, now: DateTime<Utc>) -> Result<BackupInfo, String> {
+    write_backup(db_path, dir, None, now)
+}
+
+/// 生成一份快照（恢复、修复前调用），快照不会被保留策略清理
+pub fn create_snapshot(
+    db_path: &Path,
+    dir: &Path,
+    label: &str,
+    now: DateTime<Utc>,
+) -> Result<BackupInfo, String> {
+    write_backup(db_path, dir, Some(label), now)
+}
+
+/// 按保留策略选出需要删除的备份（`backups` 需按时间从新到旧排序）
+/// 每个小时、每天、每周各保留最新的一份，分别保留最近 N 个时间段；最新的一份始终保留
+/// 快照不参与清理
+pub fn select_expired<'a>(
+    backups: &'a [BackupInfo],
+    settings: &BackupSettings,
+) -> Vec<&'a BackupInfo> {
+    let backups: Vec<&BackupInfo> = backups.iter().filter(|b| b.label.is_none()).collect();
+    let mut keep: HashSet<&str> = HashSet::new();
+    if let Some(newest) = backups.first() {
+        keep.insert(newest.file_name.as_str());
+    }
+
+    let buckets: [(&str, usize); 3] = [
+        ("%Y%m%d%H", settings.keep_hourly),
+        ("%Y%m%d", settings.keep_daily),
+        ("%G%V", settings.keep_weekly),
+    ];
+    for (format, limit) in buckets {
+        let mut seen = HashSet::new();
+        for backup in &backups {
+            if seen.len() >= limit {
+                break;
+            }
+            let Some(time) = parse_file_time(&backup.file_name) else {
+                continue;
+            };
+            if seen.insert(time.format(format).to_string()) {
+                keep.insert(backup.file_name.as_str());
+            }
+        }
+    }
+
+    backups
+        .into_iter()
+        .filter(|b| !keep.contains(b.file_name.as_str()))
+        .collect()
+}
+
+/// 删除超出保留策略的备份，返回被删除的文件名
+pub fn prune_backups(dir: &Path, settings: &BackupSettings) -> Result<Vec<String>, String> {
+    let backups = list_backups(dir)?;
+    let mut removed = Vec::new();
+    for backup in select_expired(&backups, settings) {
+        match fs::remove_file(&backup.path) {
+            Ok(()) => removed.push(backup.file_name.clone()),
+            Err(e) => log::warn!("删除旧备份 {} 失败: {}", backup.file_name, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// 备份数据库并清理旧备份
+pub fn run_backup(
+    db_path: &Path,
+    dir: &Path,
+    settings: &BackupSettings,
+) -> Result<BackupInfo, String> {
+    let _guard = BACKUP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let backup = create_backup(db_path, dir, Utc::now())?;
+    let removed = prune_backups(dir, settings)?;
+    log::info!(
+        "数据库已备份到 {}（{} 字节），清理旧备份 {} 个",
+        backup.path,
+        backup.size,
+        removed.len()
+    );
+    Ok(backup)
+}
+
+/// 列出目录中的明文备份（启用加密后仍是明文的备份会泄露内容）
+pub fn plaintext_backups(dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    Ok(list_backups(dir)?
+        .into_iter()
+        .filter(|b| !encryption::is_encrypted(Path::new(&b.path)))
+        .collect())
+}
+
+/// 用密码就地加密目录中的明文备份（启用加密后调用），返回加密失败、仍是明文的备份路径
+pub fn encrypt_backups(dir: &Path, passphrase: &str) -> Result<Vec<String>, String> {
+    let _guard = BACKUP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut remaining = Vec::new();
+    for backup in plaintext_backups(dir)? {
+        if let Err(e) = encryption::encrypt_database(Path::new(&backup.path), passphrase) {
+            log::warn!("加密备份 {} 失败: {}", backup.file_name, e);
+            remaining.push(backup.path);
+        }
+    }
+    Ok(remaining)
+}
+
+/// 把目录中用原密码加密的备份改用新密码加密（修改密码后调用），`new_passphrase` 为 None 时
+/// 解密为明文（关闭加密后调用），使备份始终能用数据库当前的密码恢复
+///
+/// 返回转换失败、仍需原密码才能打开的备份路径；无法用原密码打开的备份（更早的密码）保持不变
+pub fn rekey_backups(
+    dir: &Path,
+    old_passphrase: &str,
+    new_passphrase: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let _guard = BACKUP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut remaining = Vec::new();
+    for backup in list_backups(dir)? {
+        let path = Path::new(&backup.path);
+        if !encryption::is_encrypted(path)
+            || encryption::open_read_only_with_passphrase(path, Some(old_passphrase)).is_err()
+        {
+            continue;
+        }
+        let result = match new_passphrase {
+            Some(new) => encryption::change_passphrase(path, old_passphrase, new),
+            None => encryption::decrypt_database(path, old_passphrase),
+        };
+        if let Err(e) = result {
+            log::warn!("转换备份 {} 的加密方式失败: {}", backup.file_name, e);
+            remaining.push(backup.path);
+        }
+    }
+    Ok(remaining)
+}
+
+/// 校验备份文件：必须是 SQLite 数据库、通过完整性检查、包含笔记和聊天记录表，
+/// 且结构版本不高于当前应用支持的版本
+///
+/// `passphrase` 为当前数据库的密码：没有 SQLite 文件头的文件只有用该密码能打开时才视为加密备份
+pub fn validate_backup(path: &Path, passphrase: Option<&str>) -> Result<RestoreReport, String> {
+    let invalid = || format!("{} 不是有效的 SQLite 数据库文件", path.to_string_lossy());
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|_| invalid())?;
+    let conn = if &header == SQLITE_HEADER {
+        encryption::open_read_only_with_passphrase(path, None)?
+    } else {
+        let passphrase = passphrase.ok_or_else(invalid)?;
+        encryption::open_read_only_with_passphrase(path, Some(passphrase)).map_err(|_| invalid())?
+    };
+    let problems = storage::integrity_check(&conn)?;
+    if !problems.is_empty() {
+        return Err(format!("备份文件已损坏: {}", problems.join("; ")));
+    }
+
+    for (table, columns) in REQUIRED_COLUMNS {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .map_err(|e| format!("读取表结构失败: {}", e))?;
+        let existing: HashSet<String> = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| format!("读取表结构失败: {}", e))?;
+        if existing.is_empty() {
+            return Err(format!("备份文件中缺少 {} 表", table));
+        }
+        let missing: Vec<&str> = columns
+            .iter()
+            .copied()
+            .filter(|c| !existing.contains(*c))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "备份文件中的 {} 表缺少列: {}",
+                table,
+                missing.join(", ")
+            ));
+        }
+    }
+
+    let version = schema::current_version(&conn)?;
+    if version > schema::latest_version() {
+        return Err(format!(
+            "备份文件的数据库结构版本为 {}，高于当前应用支持的版本 {}，请先升级应用",
+            version,
+            schema::latest_version()
+        ));
+    }
+
+    Ok(RestoreReport {
+        backup_path: path.to_string_lossy().to_string(),
+        snapshot_path: None,
+        schema_version: version,
+        notes: storage::count_rows(&conn, "notes")?,
+        chat_messages: storage::count_rows(&conn, "chat_messages")?,
+    })
+}
+
+/// 用备份内容替换数据库（调用前应先通过 `validate_backup` 校验）
+/// `passphrase` 为当前数据库的密码，加密的备份和数据库都使用该密码打开
+///
+/// 通过在线备份 API 一次性写入全部页面，其他连接（如前端 SQL 插件）无需重新打开文件。
+/// 复制页面的过程中失败时数据库保持原样；复制完成后的完整性检查失败时数据库已被替换，
+/// 调用方应从恢复前的快照还原（见 `db::restore_database`）
+pub fn restore_backup(
+    backup_path: &Path,
+    db_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    // 在线备份 API 不能在加密和明文数据库之间复制页面
+    let encrypted = encryption::is_encrypted(backup_path);
+    if encrypted != encryption::is_encrypted(db_path) {
+        return Err(
+            "备份文件与当前数据库的加密状态不同，请先启用或关闭数据库加密后再恢复".to_string(),
+        );
+    }
+    let key = if encrypted {
+        Some(passphrase.ok_or_else(|| "数据库已加密，请先输入密码解锁".to_string())?)
+    } else {
+        None
+    };
+
+    let _guard = BACKUP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let src = encryption::open_read_only_with_passphrase(backup_path, key)?;
+    let mut dst = encryption::open_with_passphrase(db_path, key)?;
+    {
+        let backup = Backup::new(&src, &mut dst).map_err(|e| format!("恢复数据库失败: {}", e))?;
+        loop {
+            match backup.step(-1) {
+                Ok(StepResult::Done) => break,
+                Ok(StepResult::Busy | StepResult::Locked) => std::thread::sleep(RESTORE_BUSY_PAUSE),
+                Ok(_) => {}
+                Err(e) => return Err(format!("恢复数据库失败: {}", e)),
+            }
+        }
+    }
+
+    let problems = storage::integrity_check(&dst)?;
+    if !problems.is_empty() {
+        return Err(format!("恢复后的数据库校验失败: {}", problems.join("; ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rusqlite::Connection;
+
+    fn create_db(path: &Path, title: &str) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES (?1, '', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z')",
+            [title],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn backup_at(time: DateTime<Utc>) -> BackupInfo {
+        let file_name = format!("{}{}{}", FILE_PREFIX, time.format(TIME_FORMAT), FILE_SUFFIX);
+        BackupInfo {
+            path: file_name.clone(),
+            file_name,
+            created_at: to_iso(time),
+            size: 0,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn retention_keeps_newest_per_hour_day_and_week() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 31, 23, 0, 0).unwrap();
+        // 每 30 分钟一份，共 30 天
+        let mut backups: Vec<BackupInfo> = (0..30 * 48)
+            .map(|i| backup_at(start - chrono::Duration::minutes(30 * i)))
+            .collect();
+        backups.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+
+        let settings = BackupSettings {
+            keep_hourly: 3,
+            keep_daily: 2,
+            keep_weekly: 2,
+            ..Default::default()
+        };
+        let expired: HashSet<&str> = select_expired(&backups, &settings)
+            .into_iter()
+            .map(|b| b.file_name.as_str())
+            .collect();
+        let kept: Vec<&str> = backups
+            .iter()
+            .map(|b| b.file_name.as_str())
+            .filter(|name| !expired.contains(name))
+            .collect();
+
+        assert_eq!(
+            kept,
+            vec![
+                // 最近三个小时（同时是 3 月 31 日和第 13 周最新的一份）
+                "jdnotes-20240331-230000.db",
+                "jdnotes-20240331-223000.db",
+                "jdnotes-20240331-213000.db",
+                // 3 月 30 日
+                "jdnotes-20240330-233000.db",
+                // 第 12 周（截至 3 月 24 日周日）
+                "jdnotes-20240324-233000.db",
+            ]
+        );
+
+        let none = BackupSettings {
+            keep_hourly: 0,
+            keep_daily: 0,
+            keep_weekly: 0,
+            ..Default::default()
+        };
+        assert_eq!(select_expired(&backups, &none).len(), backups.len() - 1);
+    }
+
+    #[test]
+    fn creates_lists_and_prunes_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        drop(conn);
+
+        let backup_dir = dir.path().join("backups");
+        assert!(list_backups(&backup_dir).unwrap().is_empty());
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        for minutes in [0, 10, 70] {
+            create_backup(
+                &db_path,
+                &backup_dir,
+                start + chrono::Duration::minutes(minutes),
+            )
+            .unwrap();
+        }
+        fs::write(backup_dir.join("notes.txt"), "其他文件").unwrap();
+
+        let backups = list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[0].created_at, "2024-01-01T09:10:00.000Z");
+        assert!(backups[0].size > 0);
+
+        let settings = BackupSettings {
+            keep_hourly: 2,
+            keep_daily: 0,
+            keep_weekly: 0,
+            ..Default::default()
+        };
+        let removed = prune_backups(&backup_dir, &settings).unwrap();
+        assert_eq!(removed, vec!["jdnotes-20240101-080000.db"]);
+        assert_eq!(list_backups(&backup_dir).unwrap().len(), 2);
+        assert!(backup_dir.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn snapshots_are_listed_but_never_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        drop(create_db(&db_path, "快照"));
+        let backup_dir = dir.path().join("backups");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+
+        let snapshot = create_snapshot(&db_path, &backup_dir, PRE_RESTORE, start).unwrap();
+        assert_eq!(snapshot.file_name, "jdnotes-pre-restore-20240101-080000.db");
+        create_snapshot(
+            &db_path,
+            &backup_dir,
+            PRE_REPAIR,
+            start + chrono::Duration::minutes(5),
+        )
+        .unwrap();
+        for minutes in [10, 20] {
+            create_backup(
+                &db_path,
+                &backup_dir,
+                start + chrono::Duration::minutes(minutes),
+            )
+            .unwrap();
+        }
+
+        let backups = list_backups(&backup_dir).unwrap();
+        let labels: Vec<Option<&str>> = backups.iter().map(|b| b.label.as_deref()).collect();
+        assert_eq!(
+            labels,
+            vec![None, None, Some(PRE_REPAIR), Some(PRE_RESTORE)]
+        );
+        assert_eq!(backups[3].created_at, "2024-01-01T08:00:00.000Z");
+
+        let settings = BackupSettings {
+            keep_hourly: 1,
+            keep_daily: 1,
+            keep_weekly: 1,
+            ..Default::default()
+        };
+        let removed = prune_backups(&backup_dir, &settings).unwrap();
+        assert_eq!(removed, vec!["jdnotes-20240101-081000.db"]);
+        assert!(Path::new(&snapshot.path).exists());
+        assert_eq!(list_backups(&backup_dir).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn encrypts_plaintext_backups_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        drop(create_db(&db_path, "客户资料"));
+        let backup_dir = dir.path().join("backups");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        create_backup(&db_path, &backup_dir, start).unwrap();
+        create_snapshot(&db_path, &backup_dir, PRE_ENCRYPT, start).unwrap();
+        assert_eq!(plaintext_backups(&backup_dir).unwrap().len(), 2);
+
+        let remaining = encrypt_backups(&backup_dir, "backup passphrase").unwrap();
+        assert!(remaining.is_empty());
+        assert!(plaintext_backups(&backup_dir).unwrap().is_empty());
+        for backup in list_backups(&backup_dir).unwrap() {
+            let path = Path::new(&backup.path);
+            assert!(encryption::is_encrypted(path));
+            assert_eq!(
+                validate_backup(path, Some("backup passphrase"))
+                    .unwrap()
+                    .notes,
+                1
+            );
+            assert!(validate_backup(path, None).is_err());
+        }
+    }
+
+    #[test]
+    fn rekeys_backups_with_current_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        drop(create_db(&db_path, "客户资料"));
+        let backup_dir = dir.path().join("backups");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+        let backup = create_backup(&db_path, &backup_dir, start).unwrap();
+        let backup_path = Path::new(&backup.path);
+        encryption::encrypt_database(&db_path, "old passphrase").unwrap();
+        assert!(encrypt_backups(&backup_dir, "old passphrase")
+            .unwrap()
+            .is_empty());
+
+        // 修改密码后备份改用新密码加密，可以恢复到当前数据库
+        encryption::change_passphrase(&db_path, "old passphrase", "new passphrase").unwrap();
+        let remaining =
+            rekey_backups(&backup_dir, "old passphrase", Some("new passphrase")).unwrap();
+        assert!(remaining.is_empty());
+        assert!(validate_backup(backup_path, Some("old passphrase")).is_err());
+        assert_eq!(
+            validate_backup(backup_path, Some("new passphrase"))
+                .unwrap()
+                .notes,
+            1
+        );
+        restore_backup(backup_path, &db_path, Some("new passphrase")).unwrap();
+
+        // 关闭加密后备份解密为明文
+        encryption::decrypt_database(&db_path, "new passphrase").unwrap();
+        let remaining = rekey_backups(&backup_dir, "new passphrase", None).unwrap();
+        assert!(remaining.is_empty());
+        assert!(!encryption::is_encrypted(backup_path));
+        restore_backup(backup_path, &db_path, None).unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        let title: String = conn
+            .query_row("SELECT title FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(title, "客户资料");
+    }
+
+    #[test]
+    fn validates_backup_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.db");
+        drop(create_db(&good, "备份"));
+        let report = validate_backup(&good, None).unwrap();
+        assert_eq!(report.notes, 1);
+        // 没有迁移记录但已有 notes 表，视为版本 1
+        assert_eq!(report.schema_version, 1);
+
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "这不是数据库").unwrap();
+        assert!(validate_backup(&text, None)
+            .unwrap_err()
+            .contains("不是有效的 SQLite"));
+        // 没有文件头的文件只有用当前数据库的密码能打开时才视为加密备份
+        assert!(validate_backup(&text, Some("current passphrase"))
+            .unwrap_err()
+            .contains("不是有效的 SQLite"));
+
+        let other = dir.path().join("other.db");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT);")
+            .unwrap();
+        assert!(validate_backup(&other, None)
+            .unwrap_err()
+            .contains("缺少列"));
+
+        let newer = dir.path().join("newer.db");
+        let conn = create_db(&newer, "新版本");
+        conn.execute_batch(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, success BOOLEAN NOT NULL);
+             INSERT INTO _sqlx_migrations VALUES (1, 1), (2, 1);",
+        )
+        .unwrap();
+        drop(conn);
+        assert!(validate_backup(&newer, None)
+            .unwrap_err()
+            .contains("请先升级应用"));
+    }
+
+    #[test]
+    fn restores_backup_into_open_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup_path = dir.path().join("backup.db");
+        drop(create_db(&backup_path, "备份中的笔记"));
+
+        let db_path = dir.path().join("jdnotes.db");
+        let live = create_db(&db_path, "当前笔记");
+        live.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES ('第二篇', '', '2024-01-02T00:00:00.000Z', '2024-01-02T00:00:00.000Z');",
+        )
+        .unwrap();
+
+        validate_backup(&backup_path, None).unwrap();
+        restore_backup(&backup_path, &db_path, None).unwrap();
+
+        // 恢复前打开的连接直接读到恢复后的数据
+        let notes = storage::read_notes(&live).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title, "备份中的笔记");
+    }
+}
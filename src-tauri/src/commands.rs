@@ -1,12 +1,39 @@
-use crate::db::{self, AISettings};
-use crate::models::ExportData;
+use std::path::Path;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use tauri::{Emitter, Manager};
+use zeroize::Zeroizing;
+
+use crate::archive;
+use crate::backup;
+use crate::db::{self, AISettings, Vault, VaultList};
+use crate::encryption;
+use crate::enex;
+use crate::export::{self, ExportState};
+use crate::export_format;
+use crate::health;
+use crate::indexeddb;
+use crate::joplin;
+use crate::maintenance;
+use crate::markdown;
+use crate::models::{
+    ArchiveExportOptions, ArchiveExportReport, BackupInfo, BackupSettings, EncryptionReport,
+    EncryptionStatus, ExportFileReport, ExportFilter, ExportProgressEvent, HealthReport,
+    HtmlExportOptions, HtmlExportReport, ImportOptions, ImportReport, MaintenanceReport,
+    MarkdownExportOptions, MarkdownExportReport, MarkdownImportOptions, MarkdownImportReport,
+    PdfExportOptions, PdfExportReport, RepairReport, RestoreReport, StorageStats, ValidationReport,
+};
+use crate::notion;
+use crate::pdf;
+use crate::site;
+use crate::storage;
 
 // ============= 架构说明 =============
 // JD Notes 使用 tauri-plugin-sql 插件在前端直接执行 SQL 操作
 // 笔记和聊天消息的 CRUD 操作都在前端 src/lib/db.ts 中实现
 // 后端命令仅用于：
 // 1. 数据库路径管理（获取/更改数据库位置）
-// 2. 数据导入导出
+// 2. 数据导入导出（后端直接读写数据库，不依赖 WebView）
 
 // ============= 数据库路径管理 =============
 
@@ -35,13 +62,16 @@ pub async fn get_database_info(app: tauri::AppHandle) -> Result<serde_json::Valu
     }))
 }
 
-/// 更改数据库存储位置
+/// 更改数据库存储位置（立即切换，无需重启应用）
+/// 新数据库准备完成后关闭旧连接并重新加载前端，失败时继续使用原数据库
 #[tauri::command]
 pub async fn change_database_location(app: tauri::AppHandle, new_dir: String) -> Result<String, String> {
     log::info!("change_database_location called with: {}", new_dir);
+    let old_url = db::get_database_url(&app)?;
     match db::change_database_location(&app, &new_dir) {
         Ok(path) => {
             log::info!("Database location changed to: {}", path);
+            reload_database(&app, &old_url).await;
             Ok(path)
         }
         Err(e) => {
@@ -51,6 +81,62 @@ pub async fn change_database_location(app: tauri::AppHandle, new_dir: String) ->
     }
 }
 
+/// 关闭 SQL 插件中数据库的连接池
+async fn close_sql_pool(app: &tauri::AppHandle, url: &str) {
+    if let Some(instances) = app.try_state::<tauri_plugin_sql::DbInstances>() {
+        let pool = instances.0.write().await.remove(url);
+        if let Some(pool) = pool.as_ref().and_then(|p| p.sqlite()) {
+            pool.close().await;
+            log::info!("已关闭数据库连接: {}", url);
+        }
+    }
+}
+
+/// 当前数据库已加密并解锁时，由后端设置密钥建立 SQL 插件的连接池
+/// SQL 插件只能通过连接 URL 打开数据库，无法设置密钥，前端对加密数据库用 `Database.get` 复用这里的连接池
+async fn register_encrypted_pool(app: &tauri::AppHandle) -> Result<(), String> {
+    let db_path = db::get_database_path(app)?;
+    let Some(passphrase) = encryption::passphrase_for(&db_path) else {
+        return Ok(());
+    };
+    let key = Zeroizing::new(format!("'{}'", passphrase.replace('\'', "''")));
+    let options = SqliteConnectOptions::new()
+        .filename(&db_path)
+        .pragma("key", key.to_string())
+        .pragma("kdf_iter", encryption::KDF_ITERATIONS.to_string());
+    let pool = SqlitePool::connect_with(options)
+        .await
+        .map_err(|e| format!("连接加密数据库失败: {}", e))?;
+
+    let db_url = db::get_database_url(app)?;
+    if let Some(instances) = app.try_state::<tauri_plugin_sql::DbInstances>() {
+        let old = instances
+            .0
+            .write()
+            .await
+            .insert(db_url, tauri_plugin_sql::DbPool::Sqlite(pool));
+        if let Some(old) = old.as_ref().and_then(|p| p.sqlite()) {
+            old.close().await;
+        }
+    }
+    Ok(())
+}
+
+/// 关闭 SQL 插件中旧数据库的连接池，发送 `database-switched` 事件并重新加载主窗口
+/// 页面重新加载后前端通过 `get_database_url` 连接到新的数据库
+async fn reload_database(app: &tauri::AppHandle, old_url: &str) {
+    close_sql_pool(app, old_url).await;
+    if let Err(e) = register_encrypted_pool(app).await {
+        log::error!("{}", e);
+    }
+
+    let new_url = db::get_database_url(app).unwrap_or_default();
+    let _ = app.emit("database-switched", &new_url);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.eval("window.location.reload()");
+    }
+}
+
 /// 格式化文件大小
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -77,46 +163,424 @@ pub async fn copy_database_to(app: tauri::AppHandle, new_path: String) -> Result
 // ============= 数据导入导出 =============
 
 /// 导出数据库为 JSON
+/// 如果提供 file_path 则写入该文件并返回文件路径，否则直接返回 JSON 字符串
+/// 未提供 filter 时导出全部数据（包括废纸篓）
 #[tauri::command]
-pub async fn export_database_json() -> Result<String, String> {
-    let export_data = ExportData {
-        version: "1.0".to_string(),
-        exported_at: chrono::Utc::now().to_rfc3339(),
-        notes: vec![],
-        chat_messages: vec![],
-    };
-    
-    serde_json::to_string_pretty(&export_data).map_err(|e| e.to_string())
+pub async fn export_database_json(
+    app: tauri::AppHandle,
+    file_path: Option<String>,
+    filter: Option<ExportFilter>,
+) -> Result<String, String> {
+    let db_path = db::get_database_path(&app)?;
+    let conn = storage::open_read_only(&db_path)?;
+    let export_data = storage::build_filtered_export(&conn, filter.as_ref())?;
+    log::info!(
+        "导出 {} 条笔记、{} 条聊天消息",
+        export_data.notes.len(),
+        export_data.chat_messages.len()
+    );
+
+    match file_path {
+        Some(path) => {
+            // 先写临时文件再替换，崩溃或磁盘已满时不会留下截断的 JSON
+            export::write_atomically(Path::new(&path), |writer| {
+                serde_json::to_writer_pretty(writer, &export_data)
+                    .map_err(|e| format!("写入导出文件失败: {}", e))
+            })?;
+            Ok(path)
+        }
+        None => serde_json::to_string_pretty(&export_data).map_err(|e| e.to_string()),
+    }
+}
+
+/// 流式导出数据库到文件（路径由前端通过对话框选择）
+/// export_id 由前端生成，通过 `export-progress` 事件报告带 id 的进度，可调用 cancel_export(export_id) 取消
+/// 未提供 filter 时导出全部数据（包括废纸篓）
+#[tauri::command]
+pub async fn export_database_to_file(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ExportState>,
+    export_id: String,
+    file_path: String,
+    filter: Option<ExportFilter>,
+) -> Result<ExportFileReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let task = state.start(&export_id)?;
+    let cancel = task.cancel.clone();
+
+    let handle = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        export::export_to_file(
+            &conn,
+            Path::new(&file_path),
+            filter.as_ref(),
+            &cancel,
+            &mut |progress| {
+                let event = ExportProgressEvent {
+                    export_id: export_id.clone(),
+                    progress,
+                };
+                let _ = handle.emit("export-progress", event);
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("导出任务异常: {}", e))??;
+
+    log::info!(
+        "已导出到 {}（{} 条笔记、{} 条消息，{}）",
+        report.path,
+        report.notes_exported,
+        report.messages_exported,
+        format_size(report.bytes_written)
+    );
+    Ok(report)
+}
+
+/// 取消指定的导出任务（任务已结束时忽略）
+#[tauri::command]
+pub async fn cancel_export(
+    state: tauri::State<'_, ExportState>,
+    export_id: String,
+) -> Result<(), String> {
+    if !state.cancel(&export_id) {
+        log::info!("导出任务 {} 已结束，无需取消", export_id);
+    }
+    Ok(())
+}
+
+/// 校验导出文件：按升级链升级旧版本格式并返回结构化的错误/警告报告
+#[tauri::command]
+pub async fn validate_export_json(json_data: String) -> Result<ValidationReport, String> {
+    let (_, report) = export_format::parse_export(&json_data);
+    Ok(report)
 }
 
 /// 从 JSON 导入数据
+/// 先升级并校验导入文件，存在校验错误时不写入任何数据；
+/// 所有记录在同一事务中写入，任意记录失败时整体回滚；
+/// options 可指定与已有笔记冲突时的处理策略，dry_run 时仅返回导入计划
 #[tauri::command]
-pub async fn import_database_json(json_data: String) -> Result<serde_json::Value, String> {
-    let import_data: ExportData = serde_json::from_str(&json_data)
-        .map_err(|e| format!("JSON 解析失败: {}", e))?;
-    
-    // 返回导入统计
-    Ok(serde_json::json!({
-        "notes_count": import_data.notes.len(),
-        "messages_count": import_data.chat_messages.len()
-    }))
+pub async fn import_database_json(
+    app: tauri::AppHandle,
+    json_data: String,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let (import_data, validation) = export_format::parse_export(&json_data);
+    let Some(import_data) = import_data else {
+        log::warn!("导入文件校验失败，共 {} 个错误", validation.errors.len());
+        return Ok(ImportReport {
+            validation: Some(validation),
+            ..Default::default()
+        });
+    };
+
+    let db_path = db::get_database_path(&app)?;
+    let mut conn = storage::open_read_write(&db_path)?;
+    let mut report =
+        storage::import_export_data(&mut conn, &import_data, &options.unwrap_or_default())?;
+    report.validation = Some(validation);
+    log::info!(
+        "导入完成: dry_run={}, committed={}, 新增笔记 {}, 覆盖笔记 {}, 消息 {}, 跳过 {}, 失败 {}",
+        report.dry_run,
+        report.committed,
+        report.notes_inserted,
+        report.notes_updated,
+        report.messages_inserted,
+        report.skipped.len(),
+        report.failed.len()
+    );
+
+    Ok(report)
 }
 
 /// 从 IndexedDB 数据导入
+/// 将旧版 Dexie 导出的驼峰命名记录转换后写入数据库，无法转换的记录会列入 skipped
+/// 未提供 options 时跳过标题和创建时间相同的已有笔记，重复迁移不会产生重复数据
 #[tauri::command]
-pub async fn import_from_indexeddb(data: serde_json::Value) -> Result<serde_json::Value, String> {
-    // 解析 IndexedDB 导出的数据格式
-    let notes = data.get("notes").and_then(|v| v.as_array());
-    let messages = data.get("chatMessages").and_then(|v| v.as_array());
-    
-    let notes_count = notes.map(|n| n.len()).unwrap_or(0);
-    let messages_count = messages.map(|m| m.len()).unwrap_or(0);
-    
-    Ok(serde_json::json!({
-        "success": true,
-        "notes_imported": notes_count,
-        "messages_imported": messages_count
-    }))
+pub async fn import_from_indexeddb(
+    app: tauri::AppHandle,
+    data: serde_json::Value,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let (import_data, issues) = indexeddb::convert(&data);
+    if !issues.is_empty() {
+        log::warn!("IndexedDB 数据中有 {} 条记录无法转换", issues.len());
+    }
+
+    let db_path = db::get_database_path(&app)?;
+    let mut conn = storage::open_read_write(&db_path)?;
+    let options = options.unwrap_or_else(indexeddb::default_import_options);
+    let mut report = storage::import_export_data(&mut conn, &import_data, &options)?;
+    report.skipped.extend(issues);
+    log::info!(
+        "IndexedDB 迁移完成: committed={}, 笔记 {}, 消息 {}",
+        report.committed,
+        report.notes_inserted,
+        report.messages_inserted
+    );
+
+    Ok(report)
+}
+
+/// 导出为 Markdown 文件夹（每篇笔记一个带 YAML front matter 的 .md 文件）
+/// 默认不导出废纸篓中的笔记，可通过 filter 选择要导出的笔记
+#[tauri::command]
+pub async fn export_markdown_vault(
+    app: tauri::AppHandle,
+    output_dir: String,
+    options: Option<MarkdownExportOptions>,
+    filter: Option<ExportFilter>,
+) -> Result<MarkdownExportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        let notes = storage::read_filtered_notes(&conn, &filter.unwrap_or_default())?;
+        let messages = storage::read_chat_messages(&conn)?;
+        markdown::export_to_dir(
+            &notes,
+            &messages,
+            Path::new(&output_dir),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("导出任务异常: {}", e))??;
+    log::info!("已导出 {} 篇笔记到 {}", report.notes_exported, report.output_dir);
+
+    Ok(report)
+}
+
+/// 导入 Markdown 文件夹（兼容 Obsidian / Logseq），dry_run 时仅返回预览
+#[tauri::command]
+pub async fn import_markdown_folder(
+    app: tauri::AppHandle,
+    source_dir: String,
+    options: Option<MarkdownImportOptions>,
+) -> Result<MarkdownImportReport, String> {
+    let options = options.unwrap_or_default();
+    let db_path = db::get_database_path(&app)?;
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = storage::open_read_write(&db_path)?;
+        markdown::import_dir(&mut conn, Path::new(&source_dir), &options)
+    })
+    .await
+    .map_err(|e| format!("导入任务异常: {}", e))??;
+    log::info!(
+        "Markdown 导入: dry_run={}, 解析 {} 篇, 新增 {} 篇, 覆盖 {} 篇, 失败 {} 个文件",
+        report.dry_run,
+        report.notes.len(),
+        report.notes_inserted,
+        report.notes_updated,
+        report.failed.len()
+    );
+
+    Ok(report)
+}
+
+/// 导出为 ZIP 归档，内嵌图片提取到 `assets/` 目录
+/// 未提供 filter 时按归档格式导出全部笔记
+#[tauri::command]
+pub async fn export_archive(
+    app: tauri::AppHandle,
+    file_path: String,
+    options: Option<ArchiveExportOptions>,
+    filter: Option<ExportFilter>,
+) -> Result<ArchiveExportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        archive::export_archive(&conn, Path::new(&file_path), &options, filter.as_ref())
+    })
+    .await
+    .map_err(|e| format!("导出任务异常: {}", e))??;
+
+    log::info!(
+        "已导出 ZIP 归档 {}（{} 篇笔记、{} 张图片，{}）",
+        report.path,
+        report.notes_exported,
+        report.assets_exported,
+        format_size(report.bytes_written)
+    );
+    Ok(report)
+}
+
+/// 导入 ZIP 归档，`assets/` 中的图片还原为内嵌图片
+#[tauri::command]
+pub async fn import_archive(
+    app: tauri::AppHandle,
+    file_path: String,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = storage::open_read_write(&db_path)?;
+        archive::import_archive_file(&mut conn, Path::new(&file_path), &options)
+    })
+    .await
+    .map_err(|e| format!("导入任务异常: {}", e))??;
+
+    log::info!(
+        "ZIP 归档导入: dry_run={}, committed={}, 新增笔记 {}, 覆盖笔记 {}, 消息 {}, 跳过 {}, 失败 {}",
+        report.dry_run,
+        report.committed,
+        report.notes_inserted,
+        report.notes_updated,
+        report.messages_inserted,
+        report.skipped.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// 导入 Evernote `.enex` 文件（流式解析，适用于包含上千篇笔记的大文件）
+#[tauri::command]
+pub async fn import_enex(
+    app: tauri::AppHandle,
+    file_path: String,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let file =
+            std::fs::File::open(&file_path).map_err(|e| format!("打开 .enex 文件失败: {}", e))?;
+        let mut conn = storage::open_read_write(&db_path)?;
+        enex::import_enex(&mut conn, std::io::BufReader::new(file), &options)
+    })
+    .await
+    .map_err(|e| format!("导入任务异常: {}", e))??;
+
+    log::info!(
+        "Evernote 导入: dry_run={}, committed={}, 新增笔记 {}, 覆盖笔记 {}, 跳过 {}, 失败 {}",
+        report.dry_run,
+        report.committed,
+        report.notes_inserted,
+        report.notes_updated,
+        report.skipped.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// 导入 Notion「Markdown & CSV」导出的 zip（页面、数据库行、页面间链接和图片）
+#[tauri::command]
+pub async fn import_notion_export(
+    app: tauri::AppHandle,
+    file_path: String,
+    options: Option<MarkdownImportOptions>,
+) -> Result<MarkdownImportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let file = std::fs::File::open(&file_path)
+            .map_err(|e| format!("打开 Notion 导出文件失败: {}", e))?;
+        let mut conn = storage::open_read_write(&db_path)?;
+        notion::import_export(&mut conn, std::io::BufReader::new(file), &options)
+    })
+    .await
+    .map_err(|e| format!("导入任务异常: {}", e))??;
+
+    log::info!(
+        "Notion 导入: dry_run={}, committed={}, 新增笔记 {}, 覆盖笔记 {}, 失败 {}",
+        report.dry_run,
+        report.committed,
+        report.notes_inserted,
+        report.notes_updated,
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// 导入 Joplin 导出（`.jex` 文件或 RAW 文件夹），笔记本和标签转换为标签，资源以 data URL 内嵌
+#[tauri::command]
+pub async fn import_joplin_export(
+    app: tauri::AppHandle,
+    path: String,
+    options: Option<ImportOptions>,
+) -> Result<ImportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = storage::open_read_write(&db_path)?;
+        joplin::import_path(&mut conn, std::path::Path::new(&path), &options)
+    })
+    .await
+    .map_err(|e| format!("导入任务异常: {}", e))??;
+
+    log::info!(
+        "Joplin 导入: dry_run={}, committed={}, 新增笔记 {}, 覆盖笔记 {}, 跳过 {}, 失败 {}",
+        report.dry_run,
+        report.committed,
+        report.notes_inserted,
+        report.notes_updated,
+        report.skipped.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+/// 将满足筛选条件的笔记导出为可离线浏览的静态网站
+#[tauri::command]
+pub async fn export_html_site(
+    app: tauri::AppHandle,
+    output_dir: String,
+    filter: Option<ExportFilter>,
+    options: Option<HtmlExportOptions>,
+) -> Result<HtmlExportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let filter = filter.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        let notes = storage::read_filtered_notes(&conn, &filter)?;
+        site::export_site(&notes, Path::new(&output_dir), &options)
+    })
+    .await
+    .map_err(|e| format!("导出任务异常: {}", e))??;
+
+    log::info!(
+        "静态网站导出: {} 篇笔记, {} 个标签页, 共 {} 个文件 -> {}",
+        report.notes_exported,
+        report.tag_pages,
+        report.files_written,
+        report.output_dir
+    );
+    Ok(report)
+}
+
+/// 将满足筛选条件的笔记按创建时间排列导出为一个 PDF（如单篇笔记或整个标签）
+#[tauri::command]
+pub async fn export_pdf(
+    app: tauri::AppHandle,
+    file_path: String,
+    filter: Option<ExportFilter>,
+    options: Option<PdfExportOptions>,
+) -> Result<PdfExportReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let filter = filter.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        let mut notes = storage::read_filtered_notes(&conn, &filter)?;
+        notes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        pdf::export_pdf(&notes, Path::new(&file_path), &options)
+    })
+    .await
+    .map_err(|e| format!("导出任务异常: {}", e))??;
+
+    log::info!(
+        "PDF 导出: {} 篇笔记, {} 页, 字体 {}, 缺字 {} -> {}",
+        report.notes_exported,
+        report.pages,
+        report.font_path,
+        report.missing_glyphs,
+        report.path
+    );
+    Ok(report)
 }
 
 // ============= 初始化相关 =============
@@ -177,3 +641,278 @@ pub async fn save_ai_settings(
 pub async fn get_config_path(app: tauri::AppHandle) -> Result<String, String> {
     db::get_config_file_path(&app)
 }
+
+// ============= 笔记库管理 =============
+
+/// 获取笔记库列表和当前笔记库 ID
+#[tauri::command]
+pub async fn list_vaults(app: tauri::AppHandle) -> Result<VaultList, String> {
+    db::list_vaults(&app)
+}
+
+/// 新建笔记库（不切换），未指定目录时保存在应用数据目录下
+#[tauri::command]
+pub async fn create_vault(
+    app: tauri::AppHandle,
+    name: String,
+    directory: Option<String>,
+) -> Result<Vault, String> {
+    let vault = db::create_vault(&app, &name, directory.as_deref())?;
+    crate::refresh_tray_menu(&app);
+    Ok(vault)
+}
+
+/// 重命名笔记库
+#[tauri::command]
+pub async fn rename_vault(app: tauri::AppHandle, id: String, name: String) -> Result<(), String> {
+    db::rename_vault(&app, &id, &name)?;
+    crate::refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// 从列表中移除笔记库（不删除数据库文件）
+#[tauri::command]
+pub async fn remove_vault(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    db::remove_vault(&app, &id)?;
+    crate::refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// 设置笔记库是否使用单独的 AI 设置
+#[tauri::command]
+pub async fn set_vault_ai_override(
+    app: tauri::AppHandle,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    db::set_vault_ai_override(&app, &id, enabled)
+}
+
+/// 切换当前笔记库（立即切换，无需重启应用）
+#[tauri::command]
+pub async fn switch_vault(app: tauri::AppHandle, id: String) -> Result<Vault, String> {
+    let old_url = db::get_database_url(&app)?;
+    let vault = db::switch_vault(&app, &id)?;
+    crate::refresh_tray_menu(&app);
+    reload_database(&app, &old_url).await;
+    Ok(vault)
+}
+
+// ============= 自动备份 =============
+
+/// 获取自动备份设置
+#[tauri::command]
+pub async fn get_backup_settings(app: tauri::AppHandle) -> Result<BackupSettings, String> {
+    db::get_backup_settings(&app)
+}
+
+/// 保存自动备份设置
+#[tauri::command]
+pub async fn save_backup_settings(
+    app: tauri::AppHandle,
+    settings: BackupSettings,
+) -> Result<(), String> {
+    db::save_backup_settings(&app, settings)
+}
+
+/// 列出备份（文件名、路径、备份时间、大小），从新到旧排序
+#[tauri::command]
+pub async fn list_backups(app: tauri::AppHandle) -> Result<Vec<BackupInfo>, String> {
+    db::list_backups(&app)
+}
+
+/// 立即备份数据库
+#[tauri::command]
+pub async fn create_backup(app: tauri::AppHandle) -> Result<BackupInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || db::backup_database(&app))
+        .await
+        .map_err(|e| format!("备份任务异常: {}", e))??
+        .ok_or_else(|| "当前数据库文件不存在".to_string())
+}
+
+/// 从备份文件恢复数据库（恢复前自动为当前数据库生成快照）
+/// 完成后发送 `database-restored` 事件并重新加载主窗口，前端重新建立数据库连接
+#[tauri::command]
+pub async fn restore_database(
+    app: tauri::AppHandle,
+    backup_path: String,
+) -> Result<RestoreReport, String> {
+    let handle = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        db::restore_database(&handle, Path::new(&backup_path))
+    })
+    .await
+    .map_err(|e| format!("恢复任务异常: {}", e))??;
+
+    log::info!(
+        "已从 {} 恢复数据库: {} 条笔记、{} 条聊天消息，恢复前快照: {:?}",
+        report.backup_path,
+        report.notes,
+        report.chat_messages,
+        report.snapshot_path
+    );
+
+    let _ = app.emit("database-restored", &report);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.eval("window.location.reload()");
+    }
+    Ok(report)
+}
+
+// ============= 数据库维护 =============
+
+/// 检查数据库（完整性、外键、孤立聊天消息、tags JSON、时间格式），不做修改
+#[tauri::command]
+pub async fn check_database(app: tauri::AppHandle) -> Result<HealthReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        health::check_database(&conn)
+    })
+    .await
+    .map_err(|e| format!("检查任务异常: {}", e))??;
+
+    log::info!(
+        "数据库检查: healthy={}, 笔记 {}, 聊天消息 {}, 问题 {}",
+        report.healthy,
+        report.notes_checked,
+        report.messages_checked,
+        report.issues.len()
+    );
+    Ok(report)
+}
+
+/// 修复数据库中可自动修复的问题（修复前先备份）
+#[tauri::command]
+pub async fn repair_database(app: tauri::AppHandle) -> Result<RepairReport, String> {
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let snapshot = db::snapshot_database(&app, backup::PRE_REPAIR)?;
+        let db_path = db::get_database_path(&app)?;
+        let mut conn = storage::open_read_write(&db_path)?;
+        let mut report = health::repair_database(&mut conn)?;
+        report.snapshot_path = snapshot.map(|b| b.path);
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| format!("修复任务异常: {}", e))??;
+
+    log::info!(
+        "数据库修复: 删除孤立聊天消息 {}, 修复标签 {}, 修复时间 {}, 重建索引 {}, 剩余问题 {}",
+        report.messages_deleted,
+        report.tags_fixed,
+        report.dates_fixed,
+        report.reindexed,
+        report.after.issues.len()
+    );
+    Ok(report)
+}
+
+/// 获取存储统计（各表行数、内容大小、内嵌图片占比、最大的笔记、页面和 WAL 情况）
+#[tauri::command]
+pub async fn get_storage_stats(app: tauri::AppHandle) -> Result<StorageStats, String> {
+    let db_path = db::get_database_path(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_only(&db_path)?;
+        maintenance::storage_stats(&conn, &db_path)
+    })
+    .await
+    .map_err(|e| format!("统计任务异常: {}", e))?
+}
+
+/// 整理数据库（VACUUM、ANALYZE、PRAGMA optimize），返回每步回收的空间
+#[tauri::command]
+pub async fn optimize_database(app: tauri::AppHandle) -> Result<MaintenanceReport, String> {
+    let db_path = db::get_database_path(&app)?;
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let conn = storage::open_read_write(&db_path)?;
+        maintenance::optimize_database(&conn, &db_path)
+    })
+    .await
+    .map_err(|e| format!("维护任务异常: {}", e))??;
+
+    for step in &report.steps {
+        log::info!(
+            "数据库维护 {}: {} -> {} 字节，耗时 {} ms",
+            step.name,
+            step.bytes_before,
+            step.bytes_after,
+            step.duration_ms
+        );
+    }
+    log::info!("数据库维护完成，共回收 {} 字节", report.bytes_reclaimed);
+    Ok(report)
+}
+
+// ============= 数据库加密 =============
+
+/// 获取当前数据库的加密状态（前端启动时据此决定是否显示解锁界面）
+#[tauri::command]
+pub async fn get_encryption_status(app: tauri::AppHandle) -> Result<EncryptionStatus, String> {
+    db::encryption_status(&app)
+}
+
+/// 用密码解锁当前数据库，并为前端建立加密连接
+#[tauri::command]
+pub async fn unlock_database(app: tauri::AppHandle, passphrase: String) -> Result<(), String> {
+    let passphrase = Zeroizing::new(passphrase);
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || db::unlock_database(&handle, &passphrase))
+        .await
+        .map_err(|e| format!("解锁任务异常: {}", e))??;
+    register_encrypted_pool(&app).await
+}
+
+/// 在关闭前端连接的情况下重写数据库文件，完成后（无论成败）重新建立连接并重新加载前端
+async fn rewrite_database<F>(app: tauri::AppHandle, task: F) -> Result<EncryptionReport, String>
+where
+    F: FnOnce(&tauri::AppHandle) -> Result<EncryptionReport, String> + Send + 'static,
+{
+    let db_url = db::get_database_url(&app)?;
+    close_sql_pool(&app, &db_url).await;
+    let handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || task(&handle))
+        .await
+        .map_err(|e| format!("加密任务异常: {}", e))
+        .and_then(|r| r);
+    reload_database(&app, &db_url).await;
+    if let Err(e) = &result {
+        log::error!("修改数据库加密失败: {}", e);
+    }
+    result
+}
+
+/// 启用数据库加密，返回加密前的快照（已用新密码加密，可直接恢复），
+/// 以及仍是明文、应手动删除的备份
+#[tauri::command]
+pub async fn enable_encryption(
+    app: tauri::AppHandle,
+    passphrase: String,
+) -> Result<EncryptionReport, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    rewrite_database(app, move |app| db::enable_encryption(app, &passphrase)).await
+}
+
+/// 修改数据库密码
+#[tauri::command]
+pub async fn change_database_passphrase(
+    app: tauri::AppHandle,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<EncryptionReport, String> {
+    let old_passphrase = Zeroizing::new(old_passphrase);
+    let new_passphrase = Zeroizing::new(new_passphrase);
+    rewrite_database(app, move |app| {
+        db::change_database_passphrase(app, &old_passphrase, &new_passphrase)
+    })
+    .await
+}
+
+/// 关闭数据库加密
+#[tauri::command]
+pub async fn disable_encryption(
+    app: tauri::AppHandle,
+    passphrase: String,
+) -> Result<EncryptionReport, String> {
+    let passphrase = Zeroizing::new(passphrase);
+    rewrite_database(app, move |app| db::disable_encryption(app, &passphrase)).await
+}
@@ -0,0 +1,478 @@
+//! 配置文件（config.json）的版本升级与原子写入
+//!
+//! 版本历史（记录在 `config_version` 字段中，没有该字段的旧配置视为版本 0）：
+//! - 1：空字符串的 database_path 改为 null（使用默认路径）
+//! - 2：没有 provider 字段的旧 AI 设置，base_url 缺少版本号时补全 `/v1`
+//! - 3：AI 提供商名称统一为当前枚举值（如 `openai` → `OpenAICompatible`）
+//!
+//! 说明：读取时按升级链逐个版本升级后再反序列化，升级过的配置由调用方写回。
+//! 内容无法整体反序列化时（如手动编辑写错了某个字段）逐个字段挽救，只有无效的字段使用默认值。
+//! 写入时先写同目录下的临时文件并 fsync，再重命名覆盖原文件，
+//! 崩溃或断电时 config.json 要么是旧内容，要么是完整的新内容
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tempfile::NamedTempFile;
+
+/// 当前配置文件版本
+pub const CONFIG_VERSION: u64 = 3;
+
+/// 配置文件中记录版本号的字段
+const VERSION_KEY: &str = "config_version";
+
+/// 升级函数：就地修改配置对象
+type Migration = fn(&mut Map<String, Value>);
+
+/// 升级链：(目标版本, 说明, 升级函数)，按版本号升序排列
+const MIGRATIONS: &[(u64, &str, Migration)] = &[
+    (1, "清理空的 database_path", clear_empty_database_path),
+    (2, "补全旧版 AI 接口地址中的 /v1", append_legacy_api_version),
+    (3, "统一 AI 提供商名称", rename_providers),
+];
+
+/// 空字符串的 database_path 会被当作自定义路径，改为 null 使用默认路径
+fn clear_empty_database_path(config: &mut Map<String, Value>) {
+    if config.get("database_path").and_then(Value::as_str) == Some("") {
+        config.insert("database_path".to_string(), Value::Null);
+    }
+}
+
+/// 支持多个提供商之前，base_url 可以不带版本号（如 `https://api.deepseek.com`），
+/// 请求时再拼接 `/v1`；现在 base_url 需要包含版本号
+fn append_legacy_api_version(config: &mut Map<String, Value>) {
+    let Some(ai) = config.get_mut("ai_settings").and_then(Value::as_object_mut) else {
+        return;
+    };
+    if ai.contains_key("provider") {
+        return;
+    }
+    let Some(base_url) = ai.get("base_url").and_then(Value::as_str) else {
+        return;
+    };
+    let base_url = base_url.trim_end_matches('/');
+    // 已包含版本号（如 /v1、智谱的 /api/paas/v4）时保持不变
+    let has_version = base_url.rsplit('/').any(|segment| {
+        segment.len() > 1
+            && segment.starts_with('v')
+            && segment[1..].starts_with(|c: char| c.is_ascii_digit())
+    });
+    if !base_url.is_empty() && !has_version {
+        let fixed = format!("{}/v1", base_url);
+        ai.insert("base_url".to_string(), Value::String(fixed));
+    }
+}
+
+/// 旧版本和手动编辑的配置中提供商名称不统一，无法识别的名称按 OpenAI 兼容格式处理
+fn rename_providers(config: &mut Map<String, Value>) {
+    let Some(ai) = config.get_mut("ai_settings").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let Some(provider) = ai.get("provider").and_then(Value::as_str) else {
+        return;
+    };
+    let renamed = match provider.to_ascii_lowercase().as_str() {
+        "anthropic" | "claude" => "Anthropic",
+        "google" | "gemini" => "Google",
+        "ollama" => "Ollama",
+        _ => "OpenAICompatible",
+    };
+    ai.insert("provider".to_string(), Value::String(renamed.to_string()));
+}
+
+/// 读取配置中的版本号，没有记录时为 0
+fn version_of(config: &Map<String, Value>) -> u64 {
+    config.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0)
+}
+
+/// 按升级链将配置升级到当前版本，返回执行的升级说明
+///
+/// 比当前版本更新的配置（来自更高版本的应用）保持不变
+pub fn migrate(config: &mut Map<String, Value>) -> Vec<String> {
+    let version = version_of(config);
+    if version > CONFIG_VERSION {
+        log::warn!(
+            "配置文件版本 {} 高于当前支持的版本 {}，按当前版本读取",
+            version,
+            CONFIG_VERSION
+        );
+        return Vec::new();
+    }
+
+    let mut applied = Vec::new();
+    for (to, description, migration) in MIGRATIONS.iter().filter(|(to, _, _)| *to > version) {
+        migration(config);
+        applied.push(format!("{} -> {}: {}", to - 1, to, description));
+    }
+    config.insert(VERSION_KEY.to_string(), Value::from(CONFIG_VERSION));
+    applied
+}
+
+/// 解析并升级配置文件内容，返回配置以及执行的升级说明（不为空时应写回文件）
+pub fn parse<T: DeserializeOwned>(content: &str) -> Result<(T, Vec<String>), String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("配置文件格式错误: {}", e))?;
+    let Value::Object(mut config) = value else {
+        return Err("配置文件格式错误: 根节点不是对象".to_string());
+    };
+    let applied = migrate(&mut config);
+    let parsed = serde_json::from_value(Value::Object(config))
+        .map_err(|e| format!("配置文件内容无效: {}", e))?;
+    Ok((parsed, applied))
+}
+
+/// 配置无法整体反序列化时逐个字段挽救：无效的字段使用默认值（对象逐层处理），
+/// 数组中无效的元素被丢弃。返回挽救后的配置以及被丢弃的字段路径（JSON Pointer）；
+/// 内容不是 JSON 对象时返回 None
+pub fn salvage<T>(content: &str) -> Option<(T, Vec<String>)>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let Ok(Value::Object(mut parsed)) = serde_json::from_str::<Value>(content) else {
+        return None;
+    };
+    migrate(&mut parsed);
+    let mut root = serde_json::to_value(T::default()).ok()?;
+    let mut dropped = Vec::new();
+    merge_valid::<T>(&mut root, "", &parsed, &mut dropped);
+    let config = T::deserialize(&root).ok()?;
+    Some((config, dropped))
+}
+
+fn is_valid<T: DeserializeOwned>(root: &Value) -> bool {
+    T::deserialize(root).is_ok()
+}
+
+/// 把 parsed 中的字段逐个写入 root 中 pointer 所指的对象，写入后整体无法反序列化的字段还原
+fn merge_valid<T: DeserializeOwned>(
+    root: &mut Value,
+    pointer: &str,
+    parsed: &Map<String, Value>,
+    dropped: &mut Vec<String>,
+) {
+    for (key, value) in parsed {
+        let child = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+        let Some(target) = root.pointer_mut(pointer).and_then(Value::as_object_mut) else {
+            return;
+        };
+        let previous = target.insert(key.clone(), value.clone());
+        if is_valid::<T>(root) {
+            continue;
+        }
+
+        let target = root
+            .pointer_mut(pointer)
+            .and_then(Value::as_object_mut)
+            .expect("刚写入的对象");
+        match (value, previous) {
+            (Value::Object(fields), Some(previous @ Value::Object(_))) => {
+                target.insert(key.clone(), previous);
+                merge_valid::<T>(root, &child, fields, dropped);
+            }
+            (Value::Array(items), previous) => {
+                target.insert(key.clone(), Value::Array(Vec::new()));
+                if !is_valid::<T>(root) {
+                    restore(root, pointer, key, previous);
+                    dropped.push(child);
+                    continue;
+                }
+                for (index, item) in items.iter().enumerate() {
+                    let Some(array) = root.pointer_mut(&child).and_then(Value::as_array_mut) else {
+                        break;
+                    };
+                    array.push(item.clone());
+                    if !is_valid::<T>(root) {
+                        if let Some(array) = root.pointer_mut(&child).and_then(Value::as_array_mut)
+                        {
+                            array.pop();
+                        }
+                        dropped.push(format!("{}/{}", child, index));
+                    }
+                }
+            }
+            (_, previous) => {
+                restore(root, pointer, key, previous);
+                dropped.push(child);
+            }
+        }
+    }
+}
+
+/// 还原对象中的字段（原来没有该字段时删除）
+fn restore(root: &mut Value, pointer: &str, key: &str, previous: Option<Value>) {
+    let Some(target) = root.pointer_mut(pointer).and_then(Value::as_object_mut) else {
+        return;
+    };
+    match previous {
+        Some(previous) => target.insert(key.to_string(), previous),
+        None => target.remove(key),
+    };
+}
+
+/// 序列化配置（写入当前版本号）
+pub fn to_string<T: Serialize>(config: &T) -> Result<String, String> {
+    let mut value = serde_json::to_value(config).map_err(|e| format!("序列化配置失败: {}", e))?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert(VERSION_KEY.to_string(), Value::from(CONFIG_VERSION));
+    }
+    serde_json::to_string_pretty(&value).map_err(|e| format!("序列化配置失败: {}", e))
+}
+
+/// 原子写入文件：写入同目录下的临时文件并 fsync，再重命名覆盖目标文件
+/// 临时文件名唯一，并发写入不会互相覆盖临时文件；失败时临时文件自动删除
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut temp = NamedTempFile::new_in(dir).map_err(|e| format!("创建临时文件失败: {}", e))?;
+    temp.write_all(content)
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+    temp.as_file()
+        .sync_all()
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+    temp.persist(path)
+        .map_err(|e| format!("替换文件失败: {}", e.error))?;
+
+    // 同步目录，确保重命名本身已落盘（Windows 不支持打开目录，忽略）
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// 序列化配置并原子写入
+pub fn save<T: Serialize>(path: &Path, config: &T) -> Result<(), String> {
+    let content = to_string(config)?;
+    write_atomic(path, content.as_bytes()).map_err(|e| format!("保存配置文件失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn migrated(config: Value) -> (Value, Vec<String>) {
+        let Value::Object(mut config) = config else {
+            panic!("配置必须是对象");
+        };
+        let applied = migrate(&mut config);
+        (Value::Object(config), applied)
+    }
+
+    #[test]
+    fn migration_chain_is_ordered_and_ends_at_current_version() {
+        for (index, (to, _, _)) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(*to, index as u64 + 1);
+        }
+        assert_eq!(MIGRATIONS.last().unwrap().0, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn clears_empty_database_path() {
+        let (config, _) = migrated(json!({ "database_path": "" }));
+        assert_eq!(config["database_path"], Value::Null);
+
+        let (config, _) = migrated(json!({ "database_path": "/data/jdnotes.db" }));
+        assert_eq!(config["database_path"], "/data/jdnotes.db");
+    }
+
+    #[test]
+    fn appends_v1_to_legacy_base_url() {
+        let legacy = |base_url: &str| {
+            migrated(json!({
+                "database_path": null,
+                "ai_settings": { "base_url": base_url, "api_key": "sk", "model": "deepseek-chat" }
+            }))
+            .0["ai_settings"]["base_url"]
+                .clone()
+        };
+        assert_eq!(
+            legacy("https://api.deepseek.com"),
+            "https://api.deepseek.com/v1"
+        );
+        assert_eq!(
+            legacy("https://api.deepseek.com/"),
+            "https://api.deepseek.com/v1"
+        );
+        assert_eq!(
+            legacy("https://api.openai.com/v1"),
+            "https://api.openai.com/v1"
+        );
+        assert_eq!(
+            legacy("https://open.bigmodel.cn/api/paas/v4"),
+            "https://open.bigmodel.cn/api/paas/v4"
+        );
+
+        // 已有 provider 的配置由用户按提供商填写，不做修改
+        let (config, _) = migrated(json!({
+            "ai_settings": {
+                "provider": "Anthropic",
+                "base_url": "https://api.anthropic.com",
+                "api_key": "",
+                "model": "claude"
+            }
+        }));
+        assert_eq!(
+            config["ai_settings"]["base_url"],
+            "https://api.anthropic.com"
+        );
+    }
+
+    #[test]
+    fn renames_providers() {
+        let provider = |name: &str| {
+            migrated(json!({
+                "config_version": 2,
+                "ai_settings": { "provider": name, "base_url": "", "api_key": "", "model": "" }
+            }))
+            .0["ai_settings"]["provider"]
+                .clone()
+        };
+        assert_eq!(provider("openai"), "OpenAICompatible");
+        assert_eq!(provider("DeepSeek"), "OpenAICompatible");
+        assert_eq!(provider("OpenAICompatible"), "OpenAICompatible");
+        assert_eq!(provider("anthropic"), "Anthropic");
+        assert_eq!(provider("gemini"), "Google");
+        assert_eq!(provider("Ollama"), "Ollama");
+    }
+
+    #[test]
+    fn upgrades_versionless_config_once() {
+        let (config, applied) = migrated(json!({
+            "database_path": "",
+            "ai_settings": { "base_url": "https://api.deepseek.com", "api_key": "", "model": "" }
+        }));
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(config[VERSION_KEY], CONFIG_VERSION);
+        assert_eq!(
+            config["ai_settings"]["base_url"],
+            "https://api.deepseek.com/v1"
+        );
+
+        let (again, applied) = migrated(config.clone());
+        assert!(applied.is_empty());
+        assert_eq!(again, config);
+    }
+
+    #[test]
+    fn keeps_config_from_newer_version() {
+        let newer = json!({ "config_version": CONFIG_VERSION + 1, "database_path": "" });
+        let (config, applied) = migrated(newer.clone());
+        assert!(applied.is_empty());
+        assert_eq!(config, newer);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_content() {
+        assert!(parse::<Value>("{\"database_path\": ").is_err());
+        assert!(parse::<Value>("[1, 2]").is_err());
+        let (config, applied) = parse::<Value>("{}").unwrap();
+        assert_eq!(config[VERSION_KEY], CONFIG_VERSION);
+        assert_eq!(applied.len(), MIGRATIONS.len());
+    }
+
+    #[derive(Serialize, serde::Deserialize, Default, Debug, PartialEq)]
+    #[serde(default)]
+    struct Settings {
+        mode: Mode,
+        api_key: String,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Default, Debug, PartialEq)]
+    enum Mode {
+        #[default]
+        Fast,
+        Slow,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Item {
+        id: String,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Default, Debug, PartialEq)]
+    struct Sample {
+        path: Option<String>,
+        #[serde(default)]
+        settings: Settings,
+        #[serde(default)]
+        items: Vec<Item>,
+        #[serde(default)]
+        active: Option<String>,
+    }
+
+    #[test]
+    fn salvages_valid_fields_from_invalid_config() {
+        let content = r#"{
+            "config_version": 3,
+            "path": "/data/jdnotes.db",
+            "settings": { "mode": "unknown", "api_key": "sk-1" },
+            "items": [{ "id": "a" }, { "name": "缺少 id" }, { "id": "b" }],
+            "active": 42
+        }"#;
+        assert!(parse::<Sample>(content).is_err());
+
+        let (config, dropped) = salvage::<Sample>(content).unwrap();
+        assert_eq!(config.path.as_deref(), Some("/data/jdnotes.db"));
+        assert_eq!(config.settings.mode, Mode::Fast);
+        assert_eq!(config.settings.api_key, "sk-1");
+        let ids: Vec<&str> = config.items.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(config.active, None);
+        assert_eq!(dropped, vec!["/active", "/items/1", "/settings/mode"]);
+
+        assert!(salvage::<Sample>("不是 JSON").is_none());
+        assert!(salvage::<Sample>("[1]").is_none());
+    }
+
+    #[test]
+    fn saves_atomically_with_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "旧内容").unwrap();
+
+        save(&path, &json!({ "database_path": null })).unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved[VERSION_KEY], CONFIG_VERSION);
+        // 临时文件已重命名为目标文件
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let missing_dir = dir.path().join("missing").join("config.json");
+        assert!(save(&missing_dir, &json!({})).is_err());
+        assert!(!dir.path().join("missing").exists());
+    }
+
+    #[test]
+    fn concurrent_saves_leave_a_complete_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        std::thread::scope(|scope| {
+            for index in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        save(
+                            path,
+                            &json!({ "writer": index, "padding": "x".repeat(4096) }),
+                        )
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(saved["writer"].is_u64());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! 导入冲突处理
+//!
+//! 说明：向已有数据的数据库导入时，先按「标题 + 创建时间」或「正文哈希」
+//! 匹配已有笔记，再根据所选策略生成每篇笔记的处理计划；
+//! 同一批导入中重复的笔记只按第一篇处理，之后的重复项跳过（保留两者策略除外）；
+//! dry_run 时直接返回计划，否则按计划写入
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use chrono::DateTime;
+use rusqlite::{params, Connection, Transaction};
+use sha2::{Digest, Sha256};
+
+use crate::models::{ConflictOptions, ConflictStrategy, MatchBy, Note, NoteAction, PlannedAction};
+use crate::storage;
+
+/// 已有笔记的匹配信息
+struct Existing {
+    id: i64,
+    updated_at: String,
+}
+
+/// 计算正文内容的 SHA-256 哈希
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn match_key(match_by: MatchBy, title: &str, content: &str, created_at: &str) -> String {
+    match match_by {
+        MatchBy::ContentHash => content_hash(content),
+        MatchBy::TitleCreatedAt => {
+            // 统一时间格式，避免 "2024-01-01T00:00:00Z" 与 ".000Z" 被视为不同
+            let created_at = storage::normalize_timestamp(&serde_json::json!(created_at))
+                .unwrap_or_else(|| created_at.to_string());
+            format!("{}\u{1f}{}", title.trim(), created_at)
+        }
+    }
+}
+
+/// 比较两个 ISO 8601 时间，无法解析时按字符串比较
+fn compare_times(a: &str, b: &str) -> Ordering {
+    match (
+        DateTime::parse_from_rfc3339(a),
+        DateTime::parse_from_rfc3339(b),
+    ) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// 建立已有笔记（不含废纸篓）的匹配索引
+fn load_existing(
+    conn: &Connection,
+    match_by: MatchBy,
+) -> Result<HashMap<String, Existing>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, content, created_at, updated_at FROM notes \
+             WHERE is_deleted = 0 ORDER BY id",
+        )
+        .map_err(|e| format!("查询已有笔记失败: {}", e))?;
+    let mut rows = stmt
+        .query([])
+        .map_err(|e| format!("查询已有笔记失败: {}", e))?;
+
+    let mut index = HashMap::new();
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("读取已有笔记失败: {}", e))?
+    {
+        let read = || -> rusqlite::Result<(String, Existing)> {
+            let title: String = row.get("title")?;
+            let content: String = row.get("content")?;
+            let created_at: String = row.get("created_at")?;
+            let existing = Existing {
+                id: row.get("id")?,
+                updated_at: row.get("updated_at")?,
+            };
+            Ok((match_key(match_by, &title, &content, &created_at), existing))
+        };
+        let (key, existing) = read().map_err(|e| format!("读取已有笔记失败: {}", e))?;
+        // 多篇已有笔记匹配同一个键时以最早的一篇为准
+        index.entry(key).or_insert(existing);
+    }
+    Ok(index)
+}
+
+/// 冲突计划生成器：预先建立已有笔记的匹配索引，之后可逐篇生成处理计划，
+/// 用于无法一次性加载全部笔记的流式导入
+pub struct Planner {
+    existing: HashMap<String, Existing>,
+    /// 本批次中已生成计划的笔记的匹配键
+    planned: HashSet<String>,
+    options: ConflictOptions,
+}
+
+impl Planner {
+    pub fn new(conn: &Connection, options: &ConflictOptions) -> Result<Self, String> {
+        Ok(Self {
+            existing: load_existing(conn, options.match_by)?,
+            planned: HashSet::new(),
+            options: *options,
+        })
+    }
+
+    /// 为单篇导入笔记生成处理计划，index 为笔记在导入数据中的下标
+    ///
+    /// 与本批次中前面的笔记重复时，除保留两者外一律跳过，
+    /// 避免重复项被多次插入或多次覆盖同一篇已有笔记
+    pub fn plan(&mut self, index: usize, note: &Note) -> PlannedAction {
+        let key = match_key(
+            self.options.match_by,
+            &note.title,
+            &note.content,
+            &note.created_at,
+        );
+        let duplicate = !self.planned.insert(key.clone());
+        let matched = self.existing.get(&key);
+        let action = match (matched, self.options.strategy) {
+            (_, ConflictStrategy::KeepBoth) if duplicate => NoteAction::KeepBoth,
+            _ if duplicate => NoteAction::Skip,
+            (None, _) => NoteAction::Insert,
+            (Some(_), ConflictStrategy::Skip) => NoteAction::Skip,
+            (Some(_), ConflictStrategy::Overwrite) => NoteAction::Overwrite,
+            (Some(_), ConflictStrategy::KeepBoth) => NoteAction::KeepBoth,
+            (Some(existing), ConflictStrategy::Newer) => {
+                if compare_times(&note.updated_at, &existing.updated_at) == Ordering::Greater {
+                    NoteAction::Overwrite
+                } else {
+                    NoteAction::Skip
+                }
+            }
+        };
+        PlannedAction {
+            index,
+            title: note.title.clone(),
+            action,
+            existing_id: matched.map(|e| e.id),
+        }
+    }
+}
+
+/// 为导入的笔记生成处理计划
+pub fn plan(
+    conn: &Connection,
+    notes: &[Note],
+    options: &ConflictOptions,
+) -> Result<Vec<PlannedAction>, String> {
+    let mut planner = Planner::new(conn, options)?;
+    Ok(notes
+        .iter()
+        .enumerate()
+        .map(|(index, note)| planner.plan(index, note))
+        .collect())
+}
+
+/// 用导入的笔记覆盖已有笔记
+pub fn overwrite_note(tx: &Transaction, id: i64, note: &Note) -> rusqlite::Result<()> {
+    let tags = serde_json::to_string(&note.tags).unwrap_or_else(|_| "[]".to_string());
+    tx.execute(
+        "UPDATE notes SET title = ?1, content = ?2, tags = ?3, is_favorite = ?4, is_deleted = ?5, \
+         created_at = ?6, updated_at = ?7, reminder_date = ?8, reminder_enabled = ?9 WHERE id = ?10",
+        params![
+            note.title,
+            note.content,
+            tags,
+            note.is_favorite,
+            note.is_deleted,
+            note.created_at,
+            note.updated_at,
+            note.reminder_date,
+            note.reminder_enabled,
+            id,
+        ],
+    )?;
+    Ok(())
+}
+
+/// 按计划写入单篇笔记，返回写入后的笔记 ID（跳过时返回 None）
+pub fn apply(
+    tx: &Transaction,
+    note: &Note,
+    action: &PlannedAction,
+) -> rusqlite::Result<Option<i64>> {
+    match (action.action, action.existing_id) {
+        (NoteAction::Skip, _) => Ok(None),
+        (NoteAction::Overwrite, Some(id)) => overwrite_note(tx, id, note).map(|_| Some(id)),
+        _ => storage::insert_note(tx, note).map(Some),
+    }
+}
+
+/// 在单个事务中按冲突策略导入一批笔记（不含聊天消息）
+/// 返回 (插入数, 覆盖数)，任意一条失败时整体回滚
+pub fn import_notes(
+    conn: &mut Connection,
+    notes: &[Note],
+    actions: &[PlannedAction],
+) -> Result<(usize, usize), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    let (mut inserted, mut updated) = (0, 0);
+    for (note, action) in notes.iter().zip(actions) {
+        let written = apply(&tx, note, action)
+            .map_err(|e| format!("写入笔记「{}」失败: {}", note.title, e))?;
+        match (written, action.action) {
+            (None, _) => {}
+            (Some(_), NoteAction::Overwrite) => updated += 1,
+            (Some(_), _) => inserted += 1,
+        }
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok((inserted, updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str, created_at: &str, updated_at: &str) -> Note {
+        Note {
+            id: None,
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: Vec::new(),
+            is_favorite: 0,
+            is_deleted: 0,
+            created_at: created_at.to_string(),
+            updated_at: updated_at.to_string(),
+            reminder_date: None,
+            reminder_enabled: 0,
+        }
+    }
+
+    fn setup() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        let existing = vec![
+            note(
+                "周报",
+                "旧内容",
+                "2024-01-01T00:00:00.000Z",
+                "2024-01-05T00:00:00.000Z",
+            ),
+            note(
+                "日记",
+                "今天",
+                "2024-02-01T00:00:00.000Z",
+                "2024-02-01T00:00:00.000Z",
+            ),
+        ];
+        let actions = plan(&conn, &existing, &ConflictOptions::default()).unwrap();
+        import_notes(&mut conn, &existing, &actions).unwrap();
+        conn
+    }
+
+    fn actions_for(conn: &Connection, notes: &[Note], options: ConflictOptions) -> Vec<NoteAction> {
+        plan(conn, notes, &options)
+            .unwrap()
+            .into_iter()
+            .map(|a| a.action)
+            .collect()
+    }
+
+    #[test]
+    fn plans_actions_per_strategy() {
+        let conn = setup();
+        let incoming = vec![
+            // 标题 + 创建时间匹配（时间格式不同），更新时间更新
+            note(
+                "周报",
+                "新内容",
+                "2024-01-01T00:00:00Z",
+                "2024-01-06T00:00:00.000Z",
+            ),
+            // 标题 + 创建时间匹配，更新时间更旧
+            note(
+                "日记",
+                "昨天",
+                "2024-02-01T00:00:00.000Z",
+                "2023-12-01T00:00:00.000Z",
+            ),
+            note(
+                "新笔记",
+                "x",
+                "2024-03-01T00:00:00.000Z",
+                "2024-03-01T00:00:00.000Z",
+            ),
+        ];
+
+        let with = |strategy| ConflictOptions {
+            strategy,
+            match_by: MatchBy::TitleCreatedAt,
+        };
+        use NoteAction::*;
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::Skip)),
+            [Skip, Skip, Insert]
+        );
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::Overwrite)),
+            [Overwrite, Overwrite, Insert]
+        );
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::KeepBoth)),
+            [KeepBoth, KeepBoth, Insert]
+        );
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::Newer)),
+            [Overwrite, Skip, Insert]
+        );
+    }
+
+    #[test]
+    fn matches_by_content_hash() {
+        let conn = setup();
+        let incoming = vec![note("改了标题", "今天", "2025-01-01T00:00:00.000Z", "x")];
+        let options = ConflictOptions {
+            strategy: ConflictStrategy::Skip,
+            match_by: MatchBy::ContentHash,
+        };
+        let planned = plan(&conn, &incoming, &options).unwrap();
+        assert_eq!(planned[0].action, NoteAction::Skip);
+        assert_eq!(planned[0].existing_id, Some(2));
+    }
+
+    #[test]
+    fn applies_overwrite_in_place() {
+        let mut conn = setup();
+        let incoming = vec![note(
+            "周报",
+            "新内容",
+            "2024-01-01T00:00:00.000Z",
+            "2024-01-06T00:00:00.000Z",
+        )];
+        let options = ConflictOptions {
+            strategy: ConflictStrategy::Newer,
+            match_by: MatchBy::TitleCreatedAt,
+        };
+        let actions = plan(&conn, &incoming, &options).unwrap();
+        assert_eq!(
+            import_notes(&mut conn, &incoming, &actions).unwrap(),
+            (0, 1)
+        );
+
+        let notes = storage::read_notes(&conn).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].content, "新内容");
+    }
+
+    #[test]
+    fn duplicates_within_batch_resolve_against_earlier_notes() {
+        let mut conn = setup();
+        let fresh = note(
+            "新笔记",
+            "x",
+            "2024-03-01T00:00:00.000Z",
+            "2024-03-01T00:00:00.000Z",
+        );
+        let weekly = note(
+            "周报",
+            "新内容",
+            "2024-01-01T00:00:00.000Z",
+            "2024-01-06T00:00:00.000Z",
+        );
+        let incoming = vec![fresh.clone(), fresh, weekly.clone(), weekly];
+
+        let with = |strategy| ConflictOptions {
+            strategy,
+            match_by: MatchBy::TitleCreatedAt,
+        };
+        use NoteAction::*;
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::Skip)),
+            [Insert, Skip, Skip, Skip]
+        );
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::Newer)),
+            [Insert, Skip, Overwrite, Skip]
+        );
+        assert_eq!(
+            actions_for(&conn, &incoming, with(ConflictStrategy::KeepBoth)),
+            [Insert, KeepBoth, KeepBoth, KeepBoth]
+        );
+
+        let actions = plan(&conn, &incoming, &with(ConflictStrategy::Overwrite)).unwrap();
+        assert_eq!(
+            actions.iter().map(|a| a.action).collect::<Vec<_>>(),
+            [Insert, Skip, Overwrite, Skip]
+        );
+        assert_eq!(
+            import_notes(&mut conn, &incoming, &actions).unwrap(),
+            (1, 1)
+        );
+        assert_eq!(storage::read_notes(&conn).unwrap().len(), 3);
+    }
+}
@@ -1,11 +1,31 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use tauri::Manager;
 
+use crate::backup;
+use crate::config;
+use crate::encryption;
+use crate::models::{
+    BackupInfo, BackupSettings, EncryptionReport, EncryptionStatus, RestoreReport,
+};
+use crate::schema;
+use crate::storage;
+
 const CONFIG_FILE: &str = "config.json";
 
+/// 默认笔记库 ID（由旧版单一 database_path 配置迁移而来）
+const DEFAULT_VAULT_ID: &str = "default";
+
+/// 自动备份线程检查定时备份的间隔
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// 配置文件锁：读取、修改、写回配置的整个过程持有，避免并发修改互相覆盖
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
 /// AI 提供商类型
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq)]
 pub enum AIProvider {
     /// OpenAI 兼容格式（包括 OpenAI、DeepSeek、智谱AI、通义千问、Moonshot 等）
     #[default]
@@ -18,11 +38,25 @@ pub enum AIProvider {
     Ollama,
 }
 
-/// AI 设置结构
+/// 读取时不区分大小写并接受常见别名，无法识别的名称按 OpenAI 兼容格式处理，
+/// 避免手动编辑的提供商名称导致整个配置无法读取
+impl<'de> serde::Deserialize<'de> for AIProvider {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "anthropic" | "claude" => AIProvider::Anthropic,
+            "google" | "gemini" => AIProvider::Google,
+            "ollama" => AIProvider::Ollama,
+            _ => AIProvider::OpenAICompatible,
+        })
+    }
+}
+
+/// AI 设置结构（缺少的字段使用默认值）
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct AISettings {
     /// AI 提供商
-    #[serde(default)]
     pub provider: AIProvider,
     /// AI API 基础 URL
     pub base_url: String,
@@ -43,14 +77,81 @@ impl Default for AISettings {
     }
 }
 
+/// 笔记库（每个笔记库使用独立的数据库文件）
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Vault {
+    pub id: String,
+    pub name: String,
+    /// 数据库文件路径（如果为 None 则使用默认路径）
+    pub database_path: Option<String>,
+    /// 笔记库单独的 AI 设置（如果为 None 则使用全局设置）
+    #[serde(default)]
+    pub ai_settings: Option<AISettings>,
+    /// 最近一次打开的时间（ISO 8601）
+    #[serde(default)]
+    pub last_opened_at: Option<String>,
+}
+
+/// 笔记库列表
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct VaultList {
+    pub vaults: Vec<Vault>,
+    pub active_vault: String,
+}
+
 /// 配置结构
 #[derive(serde::Serialize, serde::Deserialize, Default)]
 pub struct AppConfig {
     /// 用户自定义的数据库路径（如果为 None 则使用默认路径）
+    /// 始终与当前笔记库的路径一致，旧版本读取配置时仍能打开当前笔记库
     pub database_path: Option<String>,
     /// AI 设置
     #[serde(default)]
     pub ai_settings: AISettings,
+    /// 自动备份设置
+    #[serde(default)]
+    pub backup_settings: BackupSettings,
+    /// 笔记库列表（旧配置中没有时由 database_path 生成默认笔记库）
+    #[serde(default)]
+    pub vaults: Vec<Vault>,
+    /// 当前笔记库 ID
+    #[serde(default)]
+    pub active_vault: Option<String>,
+}
+
+impl AppConfig {
+    /// 确保至少有一个笔记库且当前笔记库有效，返回配置是否有变化
+    /// 旧配置只有 database_path 时，将其作为默认笔记库
+    fn ensure_vaults(&mut self) -> bool {
+        if self.vaults.is_empty() {
+            self.vaults.push(Vault {
+                id: DEFAULT_VAULT_ID.to_string(),
+                name: "默认笔记库".to_string(),
+                database_path: self.database_path.clone(),
+                ai_settings: None,
+                last_opened_at: None,
+            });
+            self.active_vault = Some(DEFAULT_VAULT_ID.to_string());
+            return true;
+        }
+        if self.active_vault().is_none() {
+            self.active_vault = Some(self.vaults[0].id.clone());
+            self.database_path = self.vaults[0].database_path.clone();
+            return true;
+        }
+        false
+    }
+
+    /// 当前笔记库
+    fn active_vault(&self) -> Option<&Vault> {
+        let id = self.active_vault.as_deref()?;
+        self.vaults.iter().find(|v| v.id == id)
+    }
+
+    fn active_vault_mut(&mut self) -> Option<&mut Vault> {
+        let id = self.active_vault.clone()?;
+        self.vaults.iter_mut().find(|v| v.id == id)
+    }
 }
 
 /// 获取配置文件路径
@@ -68,141 +169,94 @@ fn get_config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(app_data_dir.join(CONFIG_FILE))
 }
 
+fn lock_config() -> MutexGuard<'static, ()> {
+    CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 /// 读取配置
 pub fn load_config(app: &tauri::AppHandle) -> Result<AppConfig, String> {
-    let config_path = get_config_path(app)?;
-
-    if config_path.exists() {
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("读取配置文件失败: {}", e))?;
-
-        // 尝试解析配置文件
-        match serde_json::from_str::<AppConfig>(&content) {
-            Ok(config) => {
-                log::info!("配置加载成功，database_path: {:?}", config.database_path);
-                Ok(config)
-            },
-            Err(e) => {
-                log::warn!("配置文件解析失败，尝试迁移旧配置: {}", e);
-
-                // 尝试解析旧配置格式（可能只有 database_path 和旧的 ai_settings）
-                if let Ok(old_config) = serde_json::from_str::<serde_json::Value>(&content) {
-                    let mut new_config = AppConfig::default();
-
-                    // 迁移 database_path - 同时处理字符串和 null 值
-                    if let Some(db_path_value) = old_config.get("database_path") {
-                        if let Some(db_path) = db_path_value.as_str() {
-                            // 值是有效的字符串
-                            if !db_path.is_empty() {
-                                log::info!("迁移 database_path: {}", db_path);
-                                new_config.database_path = Some(db_path.to_string());
-                            }
-                        }
-                        // 如果是 null，保持 database_path 为 None（使用默认路径）
-                    }
-
-                    // 迁移旧的 ai_settings
-                    if let Some(ai_settings) = old_config.get("ai_settings") {
-                        if let Some(base_url) = ai_settings.get("base_url").and_then(|v| v.as_str()) {
-                            // 如果旧的 base_url 不包含版本号，添加 /v1
-                            new_config.ai_settings.base_url = if base_url.ends_with("/v1") || base_url.contains("/v4") {
-                                base_url.to_string()
-                            } else {
-                                format!("{}/v1", base_url.trim_end_matches('/'))
-                            };
-                        }
-                        if let Some(api_key) = ai_settings.get("api_key").and_then(|v| v.as_str()) {
-                            new_config.ai_settings.api_key = api_key.to_string();
-                        }
-                        if let Some(model) = ai_settings.get("model").and_then(|v| v.as_str()) {
-                            new_config.ai_settings.model = model.to_string();
-                        }
-                        // 迁移 provider 字段
-                        if let Some(provider) = ai_settings.get("provider").and_then(|v| v.as_str()) {
-                            new_config.ai_settings.provider = match provider {
-                                "Anthropic" => AIProvider::Anthropic,
-                                "Google" => AIProvider::Google,
-                                "Ollama" => AIProvider::Ollama,
-                                _ => AIProvider::OpenAICompatible,
-                            };
-                        }
-                    }
-
-                    // 保存迁移后的配置
-                    if let Err(save_err) = save_config_internal(&config_path, &new_config) {
-                        log::warn!("保存迁移后的配置失败: {}", save_err);
-                    } else {
-                        log::info!("配置迁移成功，database_path: {:?}", new_config.database_path);
-                    }
-
-                    Ok(new_config)
-                } else {
-                    // 无法解析，尝试从备份恢复
-                    log::error!("配置文件完全无法解析");
-                    let backup_path = config_path.with_extension("json.backup");
-                    let mut recovered_db_path: Option<String> = None;
-
-                    // 尝试从备份文件恢复 database_path
-                    if backup_path.exists() {
-                        log::info!("尝试从备份文件恢复配置: {:?}", backup_path);
-                        if let Ok(backup_content) = fs::read_to_string(&backup_path) {
-                            if let Ok(backup_value) = serde_json::from_str::<serde_json::Value>(&backup_content) {
-                                if let Some(db_path) = backup_value.get("database_path").and_then(|v| v.as_str()) {
-                                    if !db_path.is_empty() {
-                                        log::info!("从备份恢复 database_path: {}", db_path);
-                                        recovered_db_path = Some(db_path.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
+    let _guard = lock_config();
+    read_config(app)
+}
 
-                    // 备份当前损坏的配置文件（如果还没有备份）
-                    if !backup_path.exists() {
-                        if let Err(backup_err) = fs::copy(&config_path, &backup_path) {
-                            log::warn!("备份旧配置文件失败: {}", backup_err);
-                        } else {
-                            log::info!("旧配置文件已备份到: {:?}", backup_path);
-                        }
-                    }
+/// 读取、修改并写回配置，整个过程持有配置文件锁
+/// `update` 返回错误时不写回
+fn update_config<T>(
+    app: &tauri::AppHandle,
+    update: impl FnOnce(&mut AppConfig) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = lock_config();
+    let mut config = read_config(app)?;
+    let result = update(&mut config)?;
+    write_config(app, &config)?;
+    Ok(result)
+}
 
-                    // 创建新配置，保留恢复的 database_path
-                    let mut new_config = AppConfig::default();
-                    new_config.database_path = recovered_db_path;
+/// 读取配置（调用方需持有配置文件锁）
+/// 旧版本的配置按升级链升级到当前版本后写回；
+/// 配置文件损坏时保留一份 `.json.corrupt` 副本后重建配置
+fn read_config(app: &tauri::AppHandle) -> Result<AppConfig, String> {
+    let config_path = get_config_path(app)?;
 
-                    if let Err(save_err) = save_config_internal(&config_path, &new_config) {
-                        log::warn!("保存配置失败: {}", save_err);
-                    } else {
-                        log::info!("配置已重建，database_path: {:?}", new_config.database_path);
-                    }
+    if !config_path.exists() {
+        log::info!("配置文件不存在，使用默认配置");
+        return Ok(AppConfig::default());
+    }
 
-                    Ok(new_config)
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("读取配置文件失败: {}", e))?;
+    match config::parse::<AppConfig>(&content) {
+        Ok((config, applied)) => {
+            if !applied.is_empty() {
+                log::info!("配置文件已升级: {}", applied.join("; "));
+                if let Err(e) = config::save(&config_path, &config) {
+                    log::warn!("保存升级后的配置失败: {}", e);
                 }
             }
+            log::info!("配置加载成功，database_path: {:?}", config.database_path);
+            Ok(config)
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            Ok(recover_config(&config_path, &content))
         }
-    } else {
-        log::info!("配置文件不存在，使用默认配置");
-        Ok(AppConfig::default())
     }
 }
 
-/// 内部保存配置函数（不依赖 AppHandle）
-fn save_config_internal(config_path: &PathBuf, config: &AppConfig) -> Result<(), String> {
-    let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("序列化配置失败: {}", e))?;
-    fs::write(config_path, content)
-        .map_err(|e| format!("保存配置文件失败: {}", e))?;
-    Ok(())
+/// 配置文件无法解析时重建配置：逐个字段保留原配置中有效的内容，无效的字段使用默认值；
+/// 内容不是 JSON 对象时使用默认配置
+///
+/// 不从 `.json.backup` 恢复：该文件是修改数据库位置前的配置，其中的路径指向移动前的旧数据库
+fn recover_config(config_path: &Path, content: &str) -> AppConfig {
+    let corrupt_path = config_path.with_extension("json.corrupt");
+    if let Err(e) = fs::copy(config_path, &corrupt_path) {
+        log::warn!("保留损坏的配置文件失败: {}", e);
+    } else {
+        log::info!("损坏的配置文件已保存到: {:?}", corrupt_path);
+    }
+
+    let config = match config::salvage::<AppConfig>(content) {
+        Some((config, dropped)) => {
+            if !dropped.is_empty() {
+                log::warn!("配置中以下字段无效，已使用默认值: {}", dropped.join(", "));
+            }
+            config
+        }
+        None => AppConfig::default(),
+    };
+
+    if let Err(e) = config::save(config_path, &config) {
+        log::warn!("保存配置失败: {}", e);
+    } else {
+        log::info!("配置已重建，database_path: {:?}", config.database_path);
+    }
+    config
 }
 
-/// 保存配置
-pub fn save_config(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
+/// 保存配置（原子写入，调用方需持有配置文件锁）
+fn write_config(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
     let config_path = get_config_path(app)?;
-    let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("序列化配置失败: {}", e))?;
-    fs::write(&config_path, content)
-        .map_err(|e| format!("保存配置文件失败: {}", e))?;
-    Ok(())
+    config::save(&config_path, config)
 }
 
 /// 获取默认数据库路径
@@ -284,8 +338,11 @@ pub fn get_database_size(app: &tauri::AppHandle) -> Result<u64, String> {
 
 /// 更改数据库存储位置
 /// 1. 备份当前配置
-/// 2. 将当前数据库复制到新位置
-/// 3. 更新配置（下次启动时使用新位置）
+/// 2. 将当前数据库在线复制到新位置并做完整性检查
+/// 3. 在新数据库上执行迁移（新位置没有数据库时新建）
+/// 4. 更新配置，之后由调用方关闭旧连接并通知前端重新加载
+///
+/// 任意一步失败都不会更新配置，应用继续使用原数据库
 pub fn change_database_location(app: &tauri::AppHandle, new_dir: &str) -> Result<String, String> {
     let current_path = get_database_path(app)?;
     let new_path = PathBuf::from(new_dir).join("jdnotes.db");
@@ -293,6 +350,10 @@ pub fn change_database_location(app: &tauri::AppHandle, new_dir: &str) -> Result
     log::info!("当前数据库路径: {:?}", current_path);
     log::info!("新数据库路径: {:?}", new_path);
 
+    if new_path == current_path {
+        return Err("新位置与当前数据库位置相同".to_string());
+    }
+
     // 先备份当前配置（在做任何更改之前）
     let config_path = get_config_path(app)?;
     let config_backup_path = config_path.with_extension("json.backup");
@@ -320,18 +381,30 @@ pub fn change_database_location(app: &tauri::AppHandle, new_dir: &str) -> Result
                 .map_err(|e| format!("备份目标位置已存在的文件失败: {}", e))?;
         }
 
-        // 复制数据库文件到新位置
+        // 在线复制数据库到新位置（包含 -wal 中的内容），校验通过后才更新配置
         log::info!("复制数据库文件...");
-        fs::copy(&current_path, &new_path)
-            .map_err(|e| format!("复制数据库文件失败: {}", e))?;
+        let size = storage::copy_database(&current_path, &new_path)?;
 
-        log::info!("数据库复制成功");
+        log::info!("数据库复制成功并通过完整性检查，大小 {} 字节", size);
     }
 
-    // 更新配置
-    let mut config = load_config(app)?;
-    config.database_path = Some(new_path.to_string_lossy().to_string());
-    save_config(app, &config)?;
+    storage::prepare_database(&new_path)?;
+
+    // 更新配置（同时更新当前笔记库的路径）
+    update_config(app, |config| {
+        config.ensure_vaults();
+        let database_path = Some(new_path.to_string_lossy().to_string());
+        if let Some(vault) = config.active_vault_mut() {
+            vault.database_path = database_path.clone();
+        }
+        config.database_path = database_path;
+        Ok(())
+    })?;
+
+    // 复制的加密数据库使用相同的密码，解锁状态随数据库一起迁移
+    if let Some(passphrase) = encryption::passphrase_for(&current_path) {
+        encryption::unlock(&new_path, &passphrase)?;
+    }
 
     log::info!("配置已更新，新数据库路径: {}", new_path.to_string_lossy());
 
@@ -346,31 +419,51 @@ pub fn copy_database(app: &tauri::AppHandle, new_path: &str) -> Result<(), Strin
         return Err("当前数据库文件不存在".to_string());
     }
     
-    fs::copy(&current_path, new_path)
-        .map_err(|e| format!("复制数据库文件失败: {}", e))?;
+    let size = storage::copy_database(&current_path, Path::new(new_path))?;
+    log::info!("数据库已复制到 {}，大小 {} 字节", new_path, size);
     
     Ok(())
 }
 
-/// 获取初始化 SQL
-pub fn get_init_sql() -> &'static str {
-    include_str!("../migrations/001_initial.sql")
+/// 执行数据库结构迁移（启动时在注册 SQL 插件之前调用）
+/// 加密数据库在解锁后才能执行迁移
+pub fn migrate_database(app: &tauri::AppHandle) -> Result<(), String> {
+    let db_path = get_database_path(app)?;
+    if encryption::is_locked(&db_path) {
+        log::info!("数据库已加密，解锁后执行结构迁移");
+        return Ok(());
+    }
+    let applied = schema::migrate_file(&db_path)?;
+    if !applied.is_empty() {
+        log::info!(
+            "已执行数据库迁移 {:?}，当前结构版本 {}",
+            applied,
+            schema::latest_version()
+        );
+    }
+    Ok(())
 }
 
 // ============= AI 设置管理 =============
 
 /// 获取 AI 设置
+/// 当前笔记库有单独的 AI 设置时优先使用
 pub fn get_ai_settings(app: &tauri::AppHandle) -> Result<AISettings, String> {
     let config = load_config(app)?;
-    Ok(config.ai_settings)
+    let vault_settings = config.active_vault().and_then(|v| v.ai_settings.clone());
+    Ok(vault_settings.unwrap_or(config.ai_settings))
 }
 
 /// 保存 AI 设置
+/// 当前笔记库有单独的 AI 设置时保存到笔记库，否则保存为全局设置
 pub fn save_ai_settings(app: &tauri::AppHandle, settings: AISettings) -> Result<(), String> {
-    let mut config = load_config(app)?;
-    config.ai_settings = settings;
-    save_config(app, &config)?;
-    Ok(())
+    update_config(app, |config| {
+        match config.active_vault_mut() {
+            Some(vault) if vault.ai_settings.is_some() => vault.ai_settings = Some(settings),
+            _ => config.ai_settings = settings,
+        }
+        Ok(())
+    })
 }
 
 /// 获取配置文件路径（供外部调用）
@@ -378,3 +471,493 @@ pub fn get_config_file_path(app: &tauri::AppHandle) -> Result<String, String> {
     let config_path = get_config_path(app)?;
     Ok(config_path.to_string_lossy().to_string())
 }
+
+// ============= 笔记库管理 =============
+
+/// 获取笔记库列表（旧配置会在这里生成默认笔记库）
+pub fn list_vaults(app: &tauri::AppHandle) -> Result<VaultList, String> {
+    let _guard = lock_config();
+    let mut config = read_config(app)?;
+    if config.ensure_vaults() {
+        write_config(app, &config)?;
+    }
+    Ok(VaultList {
+        active_vault: config.active_vault.clone().unwrap_or_default(),
+        vaults: config.vaults,
+    })
+}
+
+/// 新建笔记库
+/// 未指定目录时数据库保存在应用数据目录下的 `vaults/<ID>/jdnotes.db`
+pub fn create_vault(
+    app: &tauri::AppHandle,
+    name: &str,
+    directory: Option<&str>,
+) -> Result<Vault, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("笔记库名称不能为空".to_string());
+    }
+
+    let vaults_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("获取应用数据目录失败: {}", e))?
+        .join("vaults");
+    let custom_dir = directory.filter(|d| !d.is_empty()).map(PathBuf::from);
+    let (vault, db_path) = update_config(app, |config| {
+        config.ensure_vaults();
+        // 同一毫秒内新建的笔记库（或默认目录已被占用）追加序号，避免共用配置项和备份目录
+        let base_id = format!("vault-{}", chrono::Utc::now().timestamp_millis());
+        let mut id = base_id.clone();
+        let mut counter = 2;
+        while config.vaults.iter().any(|v| v.id == id)
+            || (custom_dir.is_none() && vaults_dir.join(&id).exists())
+        {
+            id = format!("{}-{}", base_id, counter);
+            counter += 1;
+        }
+        let dir = custom_dir.clone().unwrap_or_else(|| vaults_dir.join(&id));
+        let db_path = dir.join("jdnotes.db");
+        if config
+            .vaults
+            .iter()
+            .any(|v| v.database_path.as_deref() == Some(&*db_path.to_string_lossy()))
+        {
+            return Err("该目录已被其他笔记库使用".to_string());
+        }
+        fs::create_dir_all(&dir).map_err(|e| format!("创建笔记库目录失败: {}", e))?;
+
+        let vault = Vault {
+            id,
+            name: name.to_string(),
+            database_path: Some(db_path.to_string_lossy().to_string()),
+            ai_settings: None,
+            last_opened_at: None,
+        };
+        config.vaults.push(vault.clone());
+        Ok((vault, db_path))
+    })?;
+    log::info!("已新建笔记库「{}」: {:?}", vault.name, db_path);
+    Ok(vault)
+}
+
+/// 重命名笔记库
+pub fn rename_vault(app: &tauri::AppHandle, id: &str, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("笔记库名称不能为空".to_string());
+    }
+    update_config(app, |config| {
+        config.ensure_vaults();
+        let vault = config
+            .vaults
+            .iter_mut()
+            .find(|v| v.id == id)
+            .ok_or_else(|| format!("笔记库不存在: {}", id))?;
+        vault.name = name.to_string();
+        Ok(())
+    })
+}
+
+/// 从列表中移除笔记库（不删除数据库文件，当前笔记库不能移除）
+pub fn remove_vault(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    update_config(app, |config| {
+        config.ensure_vaults();
+        if config.active_vault.as_deref() == Some(id) {
+            return Err("不能移除当前正在使用的笔记库".to_string());
+        }
+        let before = config.vaults.len();
+        config.vaults.retain(|v| v.id != id);
+        if config.vaults.len() == before {
+            return Err(format!("笔记库不存在: {}", id));
+        }
+        Ok(())
+    })
+}
+
+/// 设置笔记库是否使用单独的 AI 设置
+/// 开启时以当前全局设置为初始值，关闭时恢复使用全局设置
+pub fn set_vault_ai_override(
+    app: &tauri::AppHandle,
+    id: &str,
+    enabled: bool,
+) -> Result<(), String> {
+    update_config(app, |config| {
+        config.ensure_vaults();
+        let global = config.ai_settings.clone();
+        let vault = config
+            .vaults
+            .iter_mut()
+            .find(|v| v.id == id)
+            .ok_or_else(|| format!("笔记库不存在: {}", id))?;
+        vault.ai_settings = match (enabled, vault.ai_settings.take()) {
+            (true, Some(existing)) => Some(existing),
+            (true, None) => Some(global),
+            (false, _) => None,
+        };
+        Ok(())
+    })
+}
+
+/// 切换当前笔记库
+/// 先在笔记库的数据库上执行迁移，成功后才更新配置，之后由调用方重新加载数据库
+pub fn switch_vault(app: &tauri::AppHandle, id: &str) -> Result<Vault, String> {
+    let default_path = get_default_database_path(app)?;
+    let (vault, db_path) = update_config(app, |config| {
+        config.ensure_vaults();
+        let vault = config
+            .vaults
+            .iter_mut()
+            .find(|v| v.id == id)
+            .ok_or_else(|| format!("笔记库不存在: {}", id))?;
+
+        let db_path = vault
+            .database_path
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or(default_path);
+        if let Some(parent) = db_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建笔记库目录失败: {}", e))?;
+        }
+        // 加密的笔记库在解锁后执行迁移
+        if !encryption::is_encrypted(&db_path) {
+            storage::prepare_database(&db_path)?;
+        }
+
+        vault.last_opened_at = Some(storage::to_iso(chrono::Utc::now()));
+        let vault = vault.clone();
+        config.active_vault = Some(vault.id.clone());
+        config.database_path = vault.database_path.clone();
+        Ok((vault, db_path))
+    })?;
+    // 每个笔记库单独加密，切换后清除上一个笔记库的密码
+    if !encryption::is_unlocked(&db_path) {
+        encryption::lock();
+    }
+    log::info!(
+        "已切换到笔记库「{}」: {:?}",
+        vault.name,
+        vault.database_path
+    );
+    Ok(vault)
+}
+
+/// 记录当前笔记库的打开时间（启动时调用）
+pub fn touch_active_vault(app: &tauri::AppHandle) -> Result<(), String> {
+    update_config(app, |config| {
+        config.ensure_vaults();
+        if let Some(vault) = config.active_vault_mut() {
+            vault.last_opened_at = Some(storage::to_iso(chrono::Utc::now()));
+        }
+        Ok(())
+    })
+}
+
+// ============= 自动备份 =============
+
+/// 获取当前笔记库的备份目录：备份根目录下以笔记库 ID 命名的子目录
+/// 备份根目录未配置时使用应用数据目录下的 backups
+/// 各笔记库的备份互不混杂，清理和列出备份只涉及当前笔记库；
+/// 旧版本直接保存在备份根目录中的备份不再列出，仍可按文件路径恢复
+pub fn get_backup_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let mut config = load_config(app)?;
+    config.ensure_vaults();
+    let root = match config
+        .backup_settings
+        .directory
+        .as_deref()
+        .filter(|d| !d.is_empty())
+    {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("获取应用数据目录失败: {}", e))?
+            .join("backups"),
+    };
+    let vault_id = config.active_vault.as_deref().unwrap_or(DEFAULT_VAULT_ID);
+    Ok(root.join(vault_id))
+}
+
+/// 获取自动备份设置
+pub fn get_backup_settings(app: &tauri::AppHandle) -> Result<BackupSettings, String> {
+    let config = load_config(app)?;
+    Ok(config.backup_settings)
+}
+
+/// 保存自动备份设置
+pub fn save_backup_settings(
+    app: &tauri::AppHandle,
+    settings: BackupSettings,
+) -> Result<(), String> {
+    update_config(app, |config| {
+        config.backup_settings = settings;
+        Ok(())
+    })
+}
+
+/// 列出备份目录中的备份（从新到旧）
+pub fn list_backups(app: &tauri::AppHandle) -> Result<Vec<BackupInfo>, String> {
+    backup::list_backups(&get_backup_dir(app)?)
+}
+
+/// 立即备份当前数据库并按保留策略清理旧备份（数据库尚未创建时返回 None）
+pub fn backup_database(app: &tauri::AppHandle) -> Result<Option<BackupInfo>, String> {
+    let settings = get_backup_settings(app)?;
+    let db_path = get_database_path(app)?;
+    if !db_path.exists() {
+        log::info!("数据库文件尚未创建，跳过备份");
+        return Ok(None);
+    }
+    if encryption::is_locked(&db_path) {
+        log::info!("数据库已加密且尚未解锁，跳过备份");
+        return Ok(None);
+    }
+    let dir = get_backup_dir(app)?;
+    backup::run_backup(&db_path, &dir, &settings).map(Some)
+}
+
+/// 为当前数据库生成带标记的快照（恢复、修复前调用），快照不参与保留策略清理
+/// 数据库尚未创建时返回 None
+pub fn snapshot_database(
+    app: &tauri::AppHandle,
+    label: &str,
+) -> Result<Option<BackupInfo>, String> {
+    let db_path = get_database_path(app)?;
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    let dir = get_backup_dir(app)?;
+    let snapshot = backup::create_snapshot(&db_path, &dir, label, chrono::Utc::now())?;
+    log::info!("{} 快照已保存到: {}", label, snapshot.path);
+    Ok(Some(snapshot))
+}
+
+/// 从备份恢复数据库
+/// 1. 校验备份文件（SQLite 格式、完整性、表结构和结构版本）
+/// 2. 为当前数据库生成快照，保存在备份目录中（不参与保留策略清理）
+/// 3. 用备份内容替换当前数据库
+/// 4. 备份来自旧版本时执行结构迁移
+///
+/// 第 3、4 步失败时数据库可能已被部分替换，自动从第 2 步的快照还原，错误信息中包含快照路径
+pub fn restore_database(
+    app: &tauri::AppHandle,
+    backup_path: &Path,
+) -> Result<RestoreReport, String> {
+    let db_path = get_database_path(app)?;
+    let passphrase = encryption::passphrase_for(&db_path);
+    let passphrase = passphrase.as_deref().map(String::as_str);
+    let mut report = backup::validate_backup(backup_path, passphrase)?;
+
+    let snapshot = snapshot_database(app, backup::PRE_RESTORE)?;
+    report.snapshot_path = snapshot.map(|s| s.path);
+
+    let result = backup::restore_backup(backup_path, &db_path, passphrase)
+        .and_then(|()| schema::migrate_file(&db_path).map(|_| ()));
+    if let Err(e) = result {
+        let Some(snapshot) = report.snapshot_path.as_deref() else {
+            return Err(e);
+        };
+        log::error!("恢复数据库失败，从恢复前的快照还原: {}", e);
+        return Err(
+            match backup::restore_backup(Path::new(snapshot), &db_path, passphrase) {
+                Ok(()) => format!("{}；已从恢复前的快照还原当前数据库: {}", e, snapshot),
+                Err(rollback) => format!(
+                    "{}；从恢复前的快照还原也失败了（{}），请手动从快照恢复: {}",
+                    e, rollback, snapshot
+                ),
+            },
+        );
+    }
+    Ok(report)
+}
+
+/// 按配置执行一次自动备份（启动、定时、退出时调用），失败只记录日志
+pub fn run_auto_backup(app: &tauri::AppHandle, reason: &str) {
+    match backup_database(app) {
+        Ok(Some(info)) => log::info!("{}自动备份完成: {}", reason, info.file_name),
+        Ok(None) => {}
+        Err(e) => log::error!("{}自动备份失败: {}", reason, e),
+    }
+}
+
+/// 启动自动备份后台线程：启动时备份一次，之后按间隔定时备份
+/// 每次检查时重新读取配置，修改备份设置后无需重启
+pub fn spawn_backup_scheduler(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let settings = get_backup_settings(&app).unwrap_or_default();
+        if settings.enabled && settings.on_startup {
+            run_auto_backup(&app, "启动");
+        }
+
+        let mut last_backup = Instant::now();
+        loop {
+            std::thread::sleep(BACKUP_CHECK_INTERVAL);
+            let settings = get_backup_settings(&app).unwrap_or_default();
+            if !settings.enabled || settings.interval_hours == 0 {
+                continue;
+            }
+            let interval = Duration::from_secs(u64::from(settings.interval_hours) * 3600);
+            if last_backup.elapsed() >= interval {
+                run_auto_backup(&app, "定时");
+                last_backup = Instant::now();
+            }
+        }
+    });
+}
+
+// ============= 数据库加密 =============
+
+/// 获取当前数据库的加密状态
+pub fn encryption_status(app: &tauri::AppHandle) -> Result<EncryptionStatus, String> {
+    let db_path = get_database_path(app)?;
+    Ok(EncryptionStatus {
+        encrypted: encryption::is_encrypted(&db_path),
+        unlocked: !encryption::is_locked(&db_path),
+    })
+}
+
+/// 用密码解锁当前数据库，并执行启动时因未解锁而跳过的结构迁移
+pub fn unlock_database(app: &tauri::AppHandle, passphrase: &str) -> Result<(), String> {
+    let db_path = get_database_path(app)?;
+    encryption::unlock(&db_path, passphrase)?;
+    migrate_database(app)?;
+    log::info!("数据库已解锁");
+    Ok(())
+}
+
+/// 修改加密状态前备份当前数据库，并确认备份可以正常读取
+fn verified_backup(app: &tauri::AppHandle, db_path: &Path) -> Result<BackupInfo, String> {
+    let dir = get_backup_dir(app)?;
+    let info = backup::create_backup(db_path, &dir, chrono::Utc::now())?;
+    let passphrase = encryption::passphrase_for(db_path);
+    backup::validate_backup(
+        Path::new(&info.path),
+        passphrase.as_deref().map(String::as_str),
+    )?;
+    log::info!("已备份当前数据库并通过校验: {}", info.path);
+    Ok(info)
+}
+
+/// 加密当前笔记库备份目录中的明文备份，返回仍是明文的备份
+/// 旧版本直接保存在备份根目录中的备份无法确定属于哪个笔记库，只列出不加密
+fn encrypt_plaintext_backups(dir: &Path, passphrase: &str) -> Result<Vec<String>, String> {
+    let mut remaining = backup::encrypt_backups(dir, passphrase)?;
+    if let Some(root) = dir.parent() {
+        remaining.extend(backup::plaintext_backups(root)?.into_iter().map(|b| b.path));
+    }
+    Ok(remaining)
+}
+
+/// 把当前笔记库备份目录中用原密码加密的备份转换为数据库当前的加密状态，返回转换失败的备份
+/// 旧版本直接保存在备份根目录中的备份无法确定属于哪个笔记库，保持不变
+fn rekey_vault_backups(
+    dir: &Path,
+    old_passphrase: &str,
+    new_passphrase: Option<&str>,
+    backup: &BackupInfo,
+) -> Vec<String> {
+    let stale = backup::rekey_backups(dir, old_passphrase, new_passphrase).unwrap_or_else(|e| {
+        log::warn!("转换已有备份的加密方式失败: {}", e);
+        vec![backup.path.clone()]
+    });
+    if !stale.is_empty() {
+        log::warn!("仍有使用原密码加密的备份: {:?}", stale);
+    }
+    stale
+}
+
+/// 启用数据库加密
+/// 1. 生成启用前的快照并校验（快照不参与保留策略清理）
+/// 2. 导出为加密副本，校验后替换原文件
+/// 3. 用新密码解锁
+/// 4. 用新密码就地加密该快照和已有的明文备份，无法加密的明文备份在结果中列出
+pub fn enable_encryption(
+    app: &tauri::AppHandle,
+    passphrase: &str,
+) -> Result<EncryptionReport, String> {
+    encryption::check_passphrase(passphrase)?;
+    let db_path = get_database_path(app)?;
+    if encryption::is_encrypted(&db_path) {
+        return Err("数据库已经加密".to_string());
+    }
+    if !db_path.exists() {
+        schema::migrate_file(&db_path)?;
+    }
+
+    let dir = get_backup_dir(app)?;
+    let snapshot =
+        backup::create_snapshot(&db_path, &dir, backup::PRE_ENCRYPT, chrono::Utc::now())?;
+    backup::validate_backup(Path::new(&snapshot.path), None)?;
+    log::info!("已生成启用加密前的快照并通过校验: {}", snapshot.path);
+
+    encryption::encrypt_database(&db_path, passphrase)?;
+    encryption::unlock(&db_path, passphrase)?;
+    log::info!("数据库已加密");
+
+    let plaintext_backups = encrypt_plaintext_backups(&dir, passphrase).unwrap_or_else(|e| {
+        log::warn!("加密已有备份失败: {}", e);
+        vec![snapshot.path.clone()]
+    });
+    if !plaintext_backups.is_empty() {
+        log::warn!("仍有明文备份，应手动删除: {:?}", plaintext_backups);
+    }
+    Ok(EncryptionReport {
+        encrypted: true,
+        backup_path: snapshot.path,
+        plaintext_backups,
+        stale_backups: Vec::new(),
+    })
+}
+
+/// 修改数据库密码
+/// 操作前的备份和已有备份随后改用新密码加密，使其能直接恢复
+pub fn change_database_passphrase(
+    app: &tauri::AppHandle,
+    old_passphrase: &str,
+    new_passphrase: &str,
+) -> Result<EncryptionReport, String> {
+    encryption::check_passphrase(new_passphrase)?;
+    let db_path = get_database_path(app)?;
+    // 校验原密码，同时确保备份时使用的是原密码
+    encryption::unlock(&db_path, old_passphrase)?;
+
+    let dir = get_backup_dir(app)?;
+    let backup = verified_backup(app, &db_path)?;
+    encryption::change_passphrase(&db_path, old_passphrase, new_passphrase)?;
+    encryption::unlock(&db_path, new_passphrase)?;
+    log::info!("数据库密码已修改");
+
+    let stale_backups = rekey_vault_backups(&dir, old_passphrase, Some(new_passphrase), &backup);
+    Ok(EncryptionReport {
+        encrypted: true,
+        backup_path: backup.path,
+        plaintext_backups: Vec::new(),
+        stale_backups,
+    })
+}
+
+/// 关闭数据库加密，数据库恢复为明文
+/// 操作前的备份和已有备份随后解密为明文，使其能直接恢复
+pub fn disable_encryption(
+    app: &tauri::AppHandle,
+    passphrase: &str,
+) -> Result<EncryptionReport, String> {
+    let db_path = get_database_path(app)?;
+    encryption::unlock(&db_path, passphrase)?;
+
+    let dir = get_backup_dir(app)?;
+    let backup = verified_backup(app, &db_path)?;
+    encryption::decrypt_database(&db_path, passphrase)?;
+    encryption::lock();
+    log::info!("数据库加密已关闭");
+
+    let stale_backups = rekey_vault_backups(&dir, passphrase, None, &backup);
+    Ok(EncryptionReport {
+        encrypted: false,
+        backup_path: backup.path,
+        plaintext_backups: Vec::new(),
+        stale_backups,
+    })
+}
@@ -0,0 +1,353 @@
+//! 数据库加密（SQLCipher）
+//!
+//! 说明：启用加密后整个数据库文件由 SQLCipher 以 AES-256 加密，
+//! 密钥由密码经 PBKDF2-HMAC-SHA512（256000 次迭代，每个数据库使用随机盐值）派生。
+//! 是否加密直接由文件头判断：明文 SQLite 文件以 `SQLite format 3` 开头，加密后的文件没有可识别的文件头，
+//! 因此每个笔记库可以单独加密。解锁后密码只保存在内存中（锁定或退出时清零），
+//! 后端打开数据库（导出、备份、维护等）时自动设置密钥，加密数据库的备份使用相同的密码加密。
+//! 启用、关闭加密和修改密码都通过 `sqlcipher_export` 导出到同目录下的临时文件，
+//! 校验完整性和各表行数一致后再替换原文件，失败时原数据库保持不变
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, DatabaseName, OpenFlags};
+use zeroize::Zeroizing;
+
+use crate::storage;
+
+/// 密码最短长度
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// PBKDF2 迭代次数（与 SQLCipher 4 默认值一致，显式设置避免随默认值变化）
+pub const KDF_ITERATIONS: u32 = 256_000;
+
+/// SQLite 数据库文件头
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// 已解锁的数据库及其密码
+struct UnlockedKey {
+    path: PathBuf,
+    passphrase: Zeroizing<String>,
+}
+
+static UNLOCKED: Mutex<Option<UnlockedKey>> = Mutex::new(None);
+
+fn unlocked() -> std::sync::MutexGuard<'static, Option<UnlockedKey>> {
+    UNLOCKED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 判断数据库文件是否已加密（文件不存在或为空时视为未加密）
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        Err(_) => false,
+    }
+}
+
+/// 判断数据库是否已用密码解锁
+pub fn is_unlocked(path: &Path) -> bool {
+    unlocked().as_ref().is_some_and(|key| key.path == path)
+}
+
+/// 数据库已加密且尚未解锁
+pub fn is_locked(path: &Path) -> bool {
+    is_encrypted(path) && !is_unlocked(path)
+}
+
+/// 检查新密码是否满足要求
+pub fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("密码至少需要 {} 个字符", MIN_PASSPHRASE_LEN));
+    }
+    Ok(())
+}
+
+/// 为连接设置密钥并确认能够读取数据库
+fn key_connection(conn: &Connection, passphrase: &str) -> Result<(), String> {
+    conn.pragma_update(None, "key", passphrase)
+        .map_err(|e| format!("设置数据库密钥失败: {}", e))?;
+    conn.pragma_update(None, "kdf_iter", KDF_ITERATIONS)
+        .map_err(|e| format!("设置数据库密钥失败: {}", e))?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|_| ())
+    .map_err(|_| "密码错误或数据库已损坏".to_string())
+}
+
+/// 打开数据库后调用：文件已加密时使用已解锁的密码设置密钥
+pub fn apply_key(conn: &Connection, path: &Path) -> Result<(), String> {
+    if !is_encrypted(path) {
+        return Ok(());
+    }
+    let passphrase = unlocked()
+        .as_ref()
+        .map(|key| key.passphrase.clone())
+        .ok_or_else(|| "数据库已加密，请先输入密码解锁".to_string())?;
+    key_connection(conn, &passphrase)
+}
+
+/// 以读写方式打开数据库，passphrase 为 None 时按明文打开
+pub fn open_with_passphrase(path: &Path, passphrase: Option<&str>) -> Result<Connection, String> {
+    open_keyed(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        passphrase,
+    )
+}
+
+/// 以只读方式打开数据库，passphrase 为 None 时按明文打开
+/// 与 `storage::open_read_only` 不同，不使用已解锁的密码，用于校验、恢复备份等需要明确密钥的场景
+pub fn open_read_only_with_passphrase(
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<Connection, String> {
+    if !path.exists() {
+        return Err(format!("数据库文件不存在: {}", path.to_string_lossy()));
+    }
+    open_keyed(path, OpenFlags::SQLITE_OPEN_READ_ONLY, passphrase)
+}
+
+fn open_keyed(
+    path: &Path,
+    flags: OpenFlags,
+    passphrase: Option<&str>,
+) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| format!("打开数据库失败: {}", e))?;
+    conn.busy_timeout(storage::BUSY_TIMEOUT)
+        .map_err(|e| format!("设置数据库超时失败: {}", e))?;
+    if let Some(passphrase) = passphrase {
+        key_connection(&conn, passphrase)?;
+    }
+    Ok(conn)
+}
+
+/// 校验加密数据库的密码
+pub fn verify_passphrase(path: &Path, passphrase: &str) -> Result<(), String> {
+    if !is_encrypted(path) {
+        return Err("数据库未加密".to_string());
+    }
+    open_with_passphrase(path, Some(passphrase)).map(|_| ())
+}
+
+/// 校验密码后解锁数据库，密码保存在内存中供后端打开数据库时使用
+pub fn unlock(path: &Path, passphrase: &str) -> Result<(), String> {
+    verify_passphrase(path, passphrase)?;
+    *unlocked() = Some(UnlockedKey {
+        path: path.to_path_buf(),
+        passphrase: Zeroizing::new(passphrase.to_string()),
+    });
+    Ok(())
+}
+
+/// 清除内存中的密码
+pub fn lock() {
+    *unlocked() = None;
+}
+
+/// 读取已解锁数据库的密码（用于前端连接设置密钥）
+pub fn passphrase_for(path: &Path) -> Option<Zeroizing<String>> {
+    unlocked()
+        .as_ref()
+        .filter(|key| key.path == path)
+        .map(|key| key.passphrase.clone())
+}
+
+/// 统计每个表的行数，用于校验导出的副本
+fn table_counts(conn: &Connection) -> Result<Vec<(String, u64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .map_err(|e| format!("读取表列表失败: {}", e))?;
+    let tables: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("读取表列表失败: {}", e))?;
+    tables
+        .into_iter()
+        .map(|name| {
+            let rows = storage::count_rows(conn, &format!("\"{}\"", name.replace('"', "\"\"")))?;
+            Ok((name, rows))
+        })
+        .collect()
+}
+
+/// 将数据库导出为使用新密码（None 表示明文）的副本，校验通过后替换原文件
+fn rewrite(
+    path: &Path,
+    passphrase: Option<&str>,
+    new_passphrase: Option<&str>,
+) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("无效的数据库路径: {}", path.to_string_lossy()))?;
+    let temp_path = path.with_file_name(format!(".{}.encrypting", file_name.to_string_lossy()));
+    if temp_path.exists() {
+        fs::remove_file(&temp_path).map_err(|e| format!("清理临时文件失败: {}", e))?;
+    }
+
+    let result = (|| {
+        let conn = open_with_passphrase(path, passphrase)?;
+        let expected = table_counts(&conn)?;
+        let journal_mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .map_err(|e| format!("读取日志模式失败: {}", e))?;
+
+        conn.execute(
+            "ATTACH DATABASE ?1 AS target KEY ?2",
+            params![temp_path.to_string_lossy(), new_passphrase.unwrap_or("")],
+        )
+        .map_err(|e| format!("创建数据库副本失败: {}", e))?;
+        if new_passphrase.is_some() {
+            conn.pragma_update(
+                Some(DatabaseName::Attached("target")),
+                "kdf_iter",
+                KDF_ITERATIONS,
+            )
+            .map_err(|e| format!("设置数据库密钥失败: {}", e))?;
+        }
+        conn.query_row("SELECT sqlcipher_export('target')", [], |_| Ok(()))
+            .map_err(|e| format!("导出数据库失败: {}", e))?;
+        conn.execute("DETACH DATABASE target", [])
+            .map_err(|e| format!("创建数据库副本失败: {}", e))?;
+
+        let copy = open_with_passphrase(&temp_path, new_passphrase)?;
+        let problems = storage::integrity_check(&copy)?;
+        if !problems.is_empty() {
+            return Err(format!("数据库副本校验失败: {}", problems.join("; ")));
+        }
+        if table_counts(&copy)? != expected {
+            return Err("数据库副本与原数据库的数据不一致".to_string());
+        }
+        if journal_mode.eq_ignore_ascii_case("wal") {
+            copy.pragma_update(None, "journal_mode", "WAL")
+                .map_err(|e| format!("设置日志模式失败: {}", e))?;
+        }
+        drop(copy);
+
+        // 先把 -wal 中的内容写回原文件，替换后不能留下与新文件不匹配的 -wal / -shm
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| format!("WAL 检查点失败: {}", e))?;
+        drop(conn);
+        for suffix in ["-wal", "-shm"] {
+            let mut side = path.as_os_str().to_owned();
+            side.push(suffix);
+            let side = PathBuf::from(side);
+            if side.exists() {
+                fs::remove_file(&side).map_err(|e| format!("清理 {} 文件失败: {}", suffix, e))?;
+            }
+        }
+        fs::rename(&temp_path, path).map_err(|e| format!("替换数据库文件失败: {}", e))
+    })();
+
+    if result.is_err() && temp_path.exists() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 加密明文数据库
+pub fn encrypt_database(path: &Path, passphrase: &str) -> Result<(), String> {
+    check_passphrase(passphrase)?;
+    if is_encrypted(path) {
+        return Err("数据库已经加密".to_string());
+    }
+    rewrite(path, None, Some(passphrase))
+}
+
+/// 解密数据库，恢复为明文
+pub fn decrypt_database(path: &Path, passphrase: &str) -> Result<(), String> {
+    if !is_encrypted(path) {
+        return Err("数据库未加密".to_string());
+    }
+    rewrite(path, Some(passphrase), None)
+}
+
+/// 修改加密数据库的密码
+pub fn change_passphrase(path: &Path, old: &str, new: &str) -> Result<(), String> {
+    check_passphrase(new)?;
+    if !is_encrypted(path) {
+        return Err("数据库未加密".to_string());
+    }
+    if old == new {
+        return Err("新密码不能与当前密码相同".to_string());
+    }
+    rewrite(path, Some(old), Some(new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_db(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES ('客户资料', '张三 13800000000', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (1, 'user', '总结', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_short_passphrase_and_plaintext_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jdnotes.db");
+        create_db(&path);
+
+        assert!(encrypt_database(&path, "短密码")
+            .unwrap_err()
+            .contains("至少"));
+        assert!(!is_encrypted(&path));
+        assert!(decrypt_database(&path, "passphrase").is_err());
+        assert!(verify_passphrase(&path, "passphrase").is_err());
+        assert!(!is_encrypted(&dir.path().join("missing.db")));
+    }
+
+    #[test]
+    fn encrypts_changes_passphrase_and_decrypts_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jdnotes.db");
+        create_db(&path);
+
+        encrypt_database(&path, "first passphrase").unwrap();
+        assert!(is_encrypted(&path));
+        let raw = fs::read(&path).unwrap();
+        assert!(!raw.windows("张三".len()).any(|w| w == "张三".as_bytes()));
+        assert!(!dir.path().join(".jdnotes.db.encrypting").exists());
+
+        // 未解锁时后端无法打开，密码错误时无法解锁
+        assert!(storage::open_read_only(&path).is_err());
+        assert!(unlock(&path, "wrong passphrase").is_err());
+        assert!(is_locked(&path));
+
+        unlock(&path, "first passphrase").unwrap();
+        let conn = storage::open_read_only(&path).unwrap();
+        assert_eq!(storage::read_notes(&conn).unwrap()[0].title, "客户资料");
+        assert_eq!(storage::count_rows(&conn, "chat_messages").unwrap(), 1);
+        drop(conn);
+
+        assert!(change_passphrase(&path, "wrong passphrase", "second passphrase").is_err());
+        change_passphrase(&path, "first passphrase", "second passphrase").unwrap();
+        assert!(verify_passphrase(&path, "first passphrase").is_err());
+        verify_passphrase(&path, "second passphrase").unwrap();
+
+        decrypt_database(&path, "second passphrase").unwrap();
+        lock();
+        assert!(!is_encrypted(&path));
+        let conn = storage::open_read_only(&path).unwrap();
+        assert_eq!(storage::read_notes(&conn).unwrap().len(), 1);
+        assert!(storage::integrity_check(&conn).unwrap().is_empty());
+    }
+}
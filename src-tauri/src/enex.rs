@@ -0,0 +1,723 @@
+//! Evernote `.enex` 导入
+//!
+//! 说明：`.enex` 可能包含上千篇笔记和大量 base64 图片，这里逐个 `<note>` 流式解析，
+//! 每解析完一篇就按冲突策略写入，不会把整个文件加载到内存；
+//! ENML 正文转换为 Markdown，`<en-media>` 引用的图片资源解码后以 data URL 内嵌
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::mem;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{NaiveDateTime, Utc};
+use md5::{Digest, Md5};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::Reader;
+use rusqlite::Connection;
+
+use crate::conflict::{self, Planner};
+use crate::models::{ImportOptions, ImportReport, Note, NoteAction};
+use crate::storage::{self, to_iso};
+
+/// Evernote 的时间格式，如 `20240101T083000Z`
+const ENEX_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// `<resource>` 中的附件
+#[derive(Debug, Default)]
+pub struct Resource {
+    pub mime: String,
+    pub data: Vec<u8>,
+    pub file_name: Option<String>,
+}
+
+/// 从 `.enex` 中解析出的一篇笔记（尚未转换）
+#[derive(Debug, Default)]
+pub struct EnexNote {
+    pub title: String,
+    /// ENML 正文
+    pub content: String,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub tags: Vec<String>,
+    pub reminder_time: Option<String>,
+    pub reminder_done: bool,
+    pub resources: Vec<Resource>,
+}
+
+/// 逐篇读取 `.enex` 中的笔记，XML 格式错误时返回 Err 并结束迭代
+pub struct EnexReader<R: BufRead> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> EnexReader<R> {
+    pub fn new(source: R) -> Self {
+        Self {
+            reader: Reader::from_reader(source),
+            buf: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn read_note(&mut self) -> Result<Option<EnexNote>, String> {
+        // 当前 <note> 内的元素路径，如 ["note", "resource", "data"]
+        let mut path: Vec<String> = Vec::new();
+        let mut note: Option<EnexNote> = None;
+        let mut resource = Resource::default();
+        let mut raw_data = String::new();
+        let mut text = String::new();
+
+        loop {
+            self.buf.clear();
+            let event = self.reader.read_event_into(&mut self.buf).map_err(|e| {
+                format!(
+                    "XML 解析失败（位置 {}）: {}",
+                    self.reader.error_position(),
+                    e
+                )
+            })?;
+            match event {
+                Event::Start(e) => {
+                    let name = local_name(&e);
+                    if note.is_none() {
+                        if name != "note" {
+                            continue;
+                        }
+                        note = Some(EnexNote::default());
+                    }
+                    path.push(name);
+                    text.clear();
+                }
+                Event::Text(e) if note.is_some() => text.push_str(&unescape(&e)),
+                Event::CData(e) if note.is_some() => text.push_str(&String::from_utf8_lossy(&e)),
+                Event::End(_) => {
+                    let Some(current) = note.as_mut() else {
+                        continue;
+                    };
+                    let value = mem::take(&mut text);
+                    match path.join("/").as_str() {
+                        "note" => return Ok(note),
+                        "note/title" => current.title = value.trim().to_string(),
+                        "note/content" => current.content = value,
+                        "note/created" => current.created = Some(value.trim().to_string()),
+                        "note/updated" => current.updated = Some(value.trim().to_string()),
+                        "note/tag" => current.tags.push(value.trim().to_string()),
+                        "note/note-attributes/reminder-time" => {
+                            current.reminder_time = Some(value.trim().to_string())
+                        }
+                        "note/note-attributes/reminder-done-time" => current.reminder_done = true,
+                        "note/resource/data" => raw_data = value,
+                        "note/resource/mime" => resource.mime = value.trim().to_string(),
+                        "note/resource/resource-attributes/file-name" => {
+                            resource.file_name = Some(value.trim().to_string())
+                        }
+                        "note/resource" => {
+                            let cleaned: String =
+                                raw_data.chars().filter(|c| !c.is_whitespace()).collect();
+                            raw_data.clear();
+                            match STANDARD.decode(cleaned) {
+                                Ok(data) => {
+                                    resource.data = data;
+                                    current.resources.push(mem::take(&mut resource));
+                                }
+                                Err(e) => {
+                                    log::warn!("笔记「{}」中的附件解码失败: {}", current.title, e);
+                                    resource = Resource::default();
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    path.pop();
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EnexReader<R> {
+    type Item = Result<EnexNote, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_note().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).to_lowercase()
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// ENML 中常见的 HTML 实体（XML 只预定义了 5 个）
+fn resolve_entity(name: &str) -> Option<&'static str> {
+    let resolved = match name {
+        "nbsp" | "ensp" | "emsp" | "thinsp" => " ",
+        "mdash" => "—",
+        "ndash" => "–",
+        "hellip" => "…",
+        "middot" => "·",
+        "bull" => "•",
+        "times" => "×",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "laquo" => "«",
+        "raquo" => "»",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        _ => return resolve_predefined_entity(name),
+    };
+    Some(resolved)
+}
+
+/// 解码文本，遇到无法识别的实体时保留原文
+fn unescape(e: &BytesText) -> String {
+    e.unescape_with(resolve_entity)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| String::from_utf8_lossy(e).into_owned())
+}
+
+/// 解析 Evernote 时间，兼容 ISO 8601
+fn parse_time(raw: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(raw, ENEX_TIME_FORMAT)
+        .ok()
+        .map(|dt| to_iso(dt.and_utc()))
+        .or_else(|| storage::normalize_timestamp(&serde_json::json!(raw)))
+}
+
+/// 转换过程中打开的 ENML 元素
+enum Element {
+    Block,
+    Inline(&'static str),
+    Link(String),
+    List,
+    ListItem,
+    Pre,
+    Table,
+    Row,
+    Cell,
+    Other,
+}
+
+/// ENML → Markdown 转换器
+struct MarkdownWriter<'a> {
+    out: String,
+    stack: Vec<Element>,
+    /// 嵌套列表，Some(n) 为有序列表的下一个序号
+    lists: Vec<Option<usize>>,
+    pre_depth: usize,
+    table_rows: usize,
+    row_cells: usize,
+    /// 附件 MD5 -> 附件
+    resources: &'a HashMap<String, &'a Resource>,
+}
+
+impl MarkdownWriter<'_> {
+    /// 确保开始新的段落
+    fn block_break(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\n']).len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() {
+            self.out.push_str("\n\n");
+        }
+    }
+
+    /// 确保开始新的一行
+    fn line_break(&mut self) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// 列表项和表格单元格中的段落不换段
+    fn in_inline_container(&self) -> bool {
+        self.stack
+            .iter()
+            .any(|e| matches!(e, Element::ListItem | Element::Cell))
+    }
+
+    fn start(&mut self, e: &BytesStart) {
+        let name = local_name(e);
+        let element = match name.as_str() {
+            "p" | "div" | "blockquote" | "center" => {
+                let style = attribute(e, "style").unwrap_or_default();
+                if style.contains("-en-codeblock") {
+                    self.start_pre()
+                } else if self.pre_depth > 0 {
+                    // 代码块中的每个 div 是一行
+                    self.line_break();
+                    Element::Block
+                } else {
+                    if !self.in_inline_container() {
+                        self.block_break();
+                    }
+                    if name == "blockquote" {
+                        self.out.push_str("> ");
+                    }
+                    Element::Block
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                let level = name[1..].parse().unwrap_or(1);
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+                Element::Block
+            }
+            "b" | "strong" => self.inline("**"),
+            "i" | "em" => self.inline("*"),
+            "s" | "strike" | "del" => self.inline("~~"),
+            "code" if self.pre_depth == 0 => self.inline("`"),
+            "a" => {
+                self.out.push('[');
+                Element::Link(attribute(e, "href").unwrap_or_default())
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.line_break();
+                }
+                self.lists.push((name == "ol").then_some(1));
+                Element::List
+            }
+            "li" => {
+                self.line_break();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&indent);
+                self.out.push_str(&marker);
+                Element::ListItem
+            }
+            "pre" => self.start_pre(),
+            "table" => {
+                self.block_break();
+                self.table_rows = 0;
+                Element::Table
+            }
+            "tr" => {
+                self.line_break();
+                self.out.push('|');
+                self.row_cells = 0;
+                Element::Row
+            }
+            "td" | "th" => {
+                self.out.push(' ');
+                Element::Cell
+            }
+            _ => {
+                self.empty(e);
+                Element::Other
+            }
+        };
+        self.stack.push(element);
+    }
+
+    fn inline(&mut self, marker: &'static str) -> Element {
+        self.out.push_str(marker);
+        Element::Inline(marker)
+    }
+
+    fn start_pre(&mut self) -> Element {
+        self.block_break();
+        self.out.push_str("```\n");
+        self.pre_depth += 1;
+        Element::Pre
+    }
+
+    fn end(&mut self) {
+        match self.stack.pop() {
+            Some(Element::Block) if self.pre_depth > 0 => self.line_break(),
+            Some(Element::Block) if !self.in_inline_container() => self.block_break(),
+            Some(Element::Inline(marker)) => self.out.push_str(marker),
+            Some(Element::Link(href)) => self.out.push_str(&format!("]({})", href)),
+            Some(Element::List) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.line_break();
+                }
+            }
+            Some(Element::ListItem) => self.line_break(),
+            Some(Element::Pre) => {
+                self.line_break();
+                self.out.push_str("```");
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.block_break();
+            }
+            Some(Element::Table) => self.block_break(),
+            Some(Element::Row) => {
+                self.table_rows += 1;
+                // Markdown 表格需要在首行后加分隔行
+                if self.table_rows == 1 {
+                    self.out.push_str("\n|");
+                    self.out.push_str(&" --- |".repeat(self.row_cells.max(1)));
+                }
+                self.line_break();
+            }
+            Some(Element::Cell) => {
+                self.out.push_str(" |");
+                self.row_cells += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理自闭合元素
+    fn empty(&mut self, e: &BytesStart) {
+        match local_name(e).as_str() {
+            "br" => self.out.push('\n'),
+            "hr" => {
+                self.block_break();
+                self.out.push_str("---");
+                self.block_break();
+            }
+            "en-todo" => {
+                if self.lists.is_empty() {
+                    self.out.push_str("- ");
+                }
+                let checked = attribute(e, "checked").is_some_and(|v| v == "true");
+                self.out.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            "en-media" => self.media(e),
+            "img" => {
+                if let Some(src) = attribute(e, "src") {
+                    let alt = attribute(e, "alt").unwrap_or_default();
+                    self.out.push_str(&format!("![{}]({})", alt, src));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 将 `<en-media>` 替换为内嵌图片，非图片附件保留为文件名占位
+    fn media(&mut self, e: &BytesStart) {
+        let hash = attribute(e, "hash").unwrap_or_default().to_lowercase();
+        let Some(resource) = self.resources.get(&hash) else {
+            return;
+        };
+        let name = resource.file_name.clone().unwrap_or_default();
+        if resource.mime.starts_with("image/") {
+            self.out.push_str(&format!(
+                "![{}](data:{};base64,{})",
+                name,
+                resource.mime,
+                STANDARD.encode(&resource.data)
+            ));
+        } else {
+            self.out.push_str(&format!("[附件: {}]", name));
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            self.out.push_str(text);
+            return;
+        }
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let leading = text.starts_with(char::is_whitespace);
+        let trailing = text.ends_with(char::is_whitespace);
+        let at_line_start = self.out.is_empty() || self.out.ends_with('\n');
+        if leading && !at_line_start && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if trailing && !collapsed.is_empty() {
+            self.out.push(' ');
+        }
+    }
+}
+
+/// 将 ENML 正文转换为 Markdown
+pub fn enml_to_markdown(enml: &str, resources: &[Resource]) -> Result<String, String> {
+    let by_hash: HashMap<String, &Resource> = resources
+        .iter()
+        .map(|r| (format!("{:x}", Md5::digest(&r.data)), r))
+        .collect();
+    let mut writer = MarkdownWriter {
+        out: String::new(),
+        stack: Vec::new(),
+        lists: Vec::new(),
+        pre_depth: 0,
+        table_rows: 0,
+        row_cells: 0,
+        resources: &by_hash,
+    };
+
+    let mut reader = Reader::from_str(enml);
+    reader.config_mut().check_end_names = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => writer.start(&e),
+            Ok(Event::End(_)) => writer.end(),
+            Ok(Event::Empty(e)) => writer.empty(&e),
+            Ok(Event::Text(e)) => writer.text(&unescape(&e)),
+            Ok(Event::CData(e)) => writer.text(&String::from_utf8_lossy(&e)),
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!(
+                    "ENML 解析失败（位置 {}）: {}",
+                    reader.error_position(),
+                    e
+                ))
+            }
+        }
+    }
+
+    let mut markdown = writer.out.trim().to_string();
+    while markdown.contains("\n\n\n") {
+        markdown = markdown.replace("\n\n\n", "\n\n");
+    }
+    Ok(markdown)
+}
+
+/// 将解析出的 Evernote 笔记转换为 Note
+pub fn convert_note(enex: &EnexNote) -> Result<Note, String> {
+    let content = enml_to_markdown(&enex.content, &enex.resources)?;
+    let created_at = enex
+        .created
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or_else(|| to_iso(Utc::now()));
+    let updated_at = enex
+        .updated
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or_else(|| created_at.clone());
+    let reminder_date = enex.reminder_time.as_deref().and_then(parse_time);
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in enex.tags.iter().filter(|t| !t.is_empty()) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    Ok(Note {
+        id: None,
+        title: enex.title.clone(),
+        content,
+        tags,
+        is_favorite: 0,
+        is_deleted: 0,
+        created_at,
+        updated_at,
+        reminder_enabled: (reminder_date.is_some() && !enex.reminder_done) as i32,
+        reminder_date,
+    })
+}
+
+/// 流式导入 `.enex`，所有笔记在同一事务中按冲突策略写入
+/// 无法转换的笔记列入 skipped，写入失败时整体回滚；dry_run 时仅返回导入计划
+pub fn import_enex<R: BufRead>(
+    conn: &mut Connection,
+    source: R,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let mut planner = Planner::new(conn, &options.conflict)?;
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    for (index, parsed) in EnexReader::new(source).enumerate() {
+        let enex = parsed?;
+        let note = match convert_note(&enex) {
+            Ok(note) => note,
+            Err(reason) => {
+                report.skipped.push(storage::import_issue(
+                    "note",
+                    index,
+                    None,
+                    format!("「{}」无法转换: {}", enex.title, reason),
+                ));
+                continue;
+            }
+        };
+
+        let action = planner.plan(index, &note);
+        if !options.dry_run {
+            match conflict::apply(&tx, &note, &action) {
+                Ok(None) => {}
+                Ok(Some(_)) if action.action == NoteAction::Overwrite => report.notes_updated += 1,
+                Ok(Some(_)) => report.notes_inserted += 1,
+                Err(e) => {
+                    report
+                        .failed
+                        .push(storage::import_issue("note", index, None, e.to_string()))
+                }
+            }
+        }
+        report.actions.push(action);
+    }
+
+    if options.dry_run || !report.failed.is_empty() {
+        tx.rollback().map_err(|e| format!("回滚事务失败: {}", e))?;
+        report.notes_inserted = 0;
+        report.notes_updated = 0;
+    } else {
+        tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+        report.committed = true;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn enex(notes: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE en-export SYSTEM \"http://xml.evernote.com/pub/evernote-export3.dtd\">\n<en-export export-date=\"20240101T000000Z\">{}</en-export>",
+            notes
+        )
+    }
+
+    fn sample_note(title: &str) -> String {
+        let hash = format!("{:x}", Md5::digest(PNG));
+        format!(
+            r#"<note><title>{title}</title><content><![CDATA[<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><h1>标题</h1><div>第一行&nbsp;<b>加粗</b> <a href="https://example.com">链接</a></div>
+<div><en-todo checked="true"/>已完成</div><ul><li>一</li><li><div>二</div></li></ul>
+<div><en-media type="image/png" hash="{hash}"/></div></en-note>]]></content>
+<created>20240101T083000Z</created><updated>20240102T000000Z</updated>
+<tag>工作</tag><tag>迁移</tag><tag>工作</tag>
+<note-attributes><reminder-time>20240103T090000Z</reminder-time></note-attributes>
+<resource><data encoding="base64">
+{data}
+</data><mime>image/png</mime><resource-attributes><file-name>a.png</file-name></resource-attributes></resource>
+</note>"#,
+            title = title,
+            hash = hash,
+            data = STANDARD.encode(PNG),
+        )
+    }
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn reads_notes_one_by_one() {
+        let xml = enex(&format!("{}{}", sample_note("甲"), sample_note("乙")));
+        let notes: Vec<_> = EnexReader::new(xml.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[1].title, "乙");
+        assert_eq!(notes[0].tags, vec!["工作", "迁移", "工作"]);
+        assert_eq!(notes[0].resources[0].data, PNG);
+        assert_eq!(notes[0].resources[0].file_name.as_deref(), Some("a.png"));
+    }
+
+    #[test]
+    fn converts_enml_to_markdown() {
+        let xml = enex(&sample_note("甲"));
+        let enex_note = EnexReader::new(xml.as_bytes()).next().unwrap().unwrap();
+        let note = convert_note(&enex_note).unwrap();
+
+        assert_eq!(note.title, "甲");
+        assert_eq!(note.tags, vec!["工作", "迁移"]);
+        assert_eq!(note.created_at, "2024-01-01T08:30:00.000Z");
+        assert_eq!(note.updated_at, "2024-01-02T00:00:00.000Z");
+        assert_eq!(
+            note.reminder_date.as_deref(),
+            Some("2024-01-03T09:00:00.000Z")
+        );
+        assert_eq!(note.reminder_enabled, 1);
+
+        let expected = format!(
+            "# 标题\n\n第一行 **加粗** [链接](https://example.com)\n\n- [x] 已完成\n\n- 一\n- 二\n\n![a.png](data:image/png;base64,{})",
+            STANDARD.encode(PNG)
+        );
+        assert_eq!(note.content, expected);
+    }
+
+    #[test]
+    fn converts_tables_and_code_blocks() {
+        let enml = "<en-note><table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td></tr></table>\
+                    <div style=\"-en-codeblock:true\"><div>let x = 1;</div></div><ol><li>x</li><li>y</li></ol></en-note>";
+        let markdown = enml_to_markdown(enml, &[]).unwrap();
+        assert_eq!(
+            markdown,
+            "| a | b |\n| --- | --- |\n| 1 | 2 |\n\n```\nlet x = 1;\n```\n\n1. x\n2. y"
+        );
+    }
+
+    #[test]
+    fn imports_stream_with_dry_run() {
+        let xml = enex(&format!(
+            "{}<note><title>坏</title><content><![CDATA[<en-note><!-- </en-note>]]></content></note>{}",
+            sample_note("甲"),
+            sample_note("乙")
+        ));
+        let mut conn = setup();
+
+        let dry_run = ImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let preview = import_enex(&mut conn, xml.as_bytes(), &dry_run).unwrap();
+        assert_eq!(preview.actions.len(), 2);
+        assert!(!preview.committed);
+        assert!(storage::read_notes(&conn).unwrap().is_empty());
+
+        let report = import_enex(&mut conn, xml.as_bytes(), &ImportOptions::default()).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].index, 1);
+
+        let notes = storage::read_notes(&conn).unwrap();
+        assert_eq!(notes[1].title, "乙");
+        assert!(notes[1].content.contains("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn malformed_xml_aborts_import() {
+        let xml = format!("<en-export>{}<note><title>x</note>", sample_note("甲"));
+        let mut conn = setup();
+        assert!(import_enex(&mut conn, xml.as_bytes(), &ImportOptions::default()).is_err());
+        assert!(storage::read_notes(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,339 @@
+//! 流式导出
+//!
+//! 说明：逐行读取数据库并直接写入文件，避免把包含大量 base64 图片的笔记
+//! 一次性序列化为字符串；数据先写入目标目录下的临时文件，完成后再原子替换目标文件，
+//! 导出失败或取消时不会留下写了一半的文件
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rusqlite::Connection;
+use tempfile::NamedTempFile;
+
+use crate::models::{ExportFileReport, ExportFilter, ExportProgress};
+use crate::storage;
+
+/// 每处理多少行发送一次进度
+const PROGRESS_INTERVAL: u64 = 20;
+
+/// 导出任务状态（由 Tauri 托管，用于取消正在进行的导出）
+/// 每个导出任务使用前端生成的 id 登记自己的取消标记，取消时只影响对应的任务
+#[derive(Default)]
+pub struct ExportState {
+    tasks: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ExportState {
+    fn tasks(&self) -> MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 登记导出任务，任务结束（ExportTask 被释放）时自动注销
+    pub fn start(&self, id: &str) -> Result<ExportTask<'_>, String> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut tasks = self.tasks();
+        if tasks.contains_key(id) {
+            return Err(format!("导出任务 {} 正在进行", id));
+        }
+        tasks.insert(id.to_string(), cancel.clone());
+        Ok(ExportTask {
+            state: self,
+            id: id.to_string(),
+            cancel,
+        })
+    }
+
+    /// 取消指定的导出任务，任务不存在（已结束）时返回 false
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.tasks().get(id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 正在进行的导出任务
+pub struct ExportTask<'a> {
+    state: &'a ExportState,
+    id: String,
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Drop for ExportTask<'_> {
+    fn drop(&mut self) {
+        self.state.tasks().remove(&self.id);
+    }
+}
+
+/// 导出进度跟踪
+struct Progress<'a> {
+    done: u64,
+    total: u64,
+    cancel: &'a AtomicBool,
+    on_progress: &'a mut dyn FnMut(ExportProgress),
+}
+
+impl Progress<'_> {
+    /// 记录一行已写入，按间隔发送进度；已取消时返回错误
+    fn advance(&mut self) -> Result<(), String> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err("导出已取消".to_string());
+        }
+        self.done += 1;
+        if self.done % PROGRESS_INTERVAL == 0 || self.done == self.total {
+            self.report();
+        }
+        Ok(())
+    }
+
+    fn report(&mut self) {
+        (self.on_progress)(ExportProgress {
+            done: self.done,
+            total: self.total,
+        });
+    }
+}
+
+fn io_err(e: std::io::Error) -> String {
+    format!("写入导出文件失败: {}", e)
+}
+
+/// 以 JSON 数组元素的形式写入一条记录
+fn write_item<W: Write, T: serde::Serialize>(
+    writer: &mut W,
+    item: &T,
+    first: &mut bool,
+) -> Result<(), String> {
+    writer
+        .write_all(if *first { b"\n    " } else { b",\n    " })
+        .map_err(io_err)?;
+    *first = false;
+    serde_json::to_writer(&mut *writer, item).map_err(|e| format!("序列化失败: {}", e))
+}
+
+/// 将数据库以 ExportData 格式流式写入 writer，返回 (笔记数, 消息数)
+/// 提供筛选条件时只写入满足条件的笔记及其聊天消息，否则写入全部数据
+pub fn write_export<W: Write>(
+    conn: &Connection,
+    writer: &mut W,
+    filter: Option<&ExportFilter>,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<(u64, u64), String> {
+    let filter = filter.map(storage::prepare_filter).transpose()?;
+
+    // 在同一个读事务中完成统计和读取，保证导出的是一致的快照
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("开启读事务失败: {}", e))?;
+
+    let total = storage::count_rows(&tx, "notes")? + storage::count_rows(&tx, "chat_messages")?;
+    let mut progress = Progress {
+        done: 0,
+        total,
+        cancel,
+        on_progress,
+    };
+    progress.report();
+
+    let header = format!(
+        "{{\n  \"version\": {},\n  \"exported_at\": {},\n  \"notes\": [",
+        serde_json::to_string(storage::EXPORT_VERSION).unwrap_or_default(),
+        serde_json::to_string(&chrono::Utc::now().to_rfc3339()).unwrap_or_default(),
+    );
+    writer.write_all(header.as_bytes()).map_err(io_err)?;
+
+    // 进度按扫描的行数计算，被筛选掉的行同样计入
+    let mut notes = 0;
+    let mut note_ids = HashSet::new();
+    let mut first = true;
+    storage::for_each_note(&tx, |note| {
+        if filter
+            .as_ref()
+            .map_or(true, |filter| storage::matches_filter(&note, filter))
+        {
+            write_item(writer, &note, &mut first)?;
+            note_ids.extend(note.id);
+            notes += 1;
+        }
+        progress.advance()
+    })?;
+
+    writer
+        .write_all(b"\n  ],\n  \"chat_messages\": [")
+        .map_err(io_err)?;
+
+    let mut messages = 0;
+    let mut first = true;
+    storage::for_each_chat_message(&tx, |message| {
+        if filter.is_none() || note_ids.contains(&message.note_id) {
+            write_item(writer, &message, &mut first)?;
+            messages += 1;
+        }
+        progress.advance()
+    })?;
+
+    writer.write_all(b"\n  ]\n}\n").map_err(io_err)?;
+    Ok((notes, messages))
+}
+
+/// 原子地写入目标文件：先写入同目录的临时文件，成功后 fsync 并重命名
+/// 写入失败或被取消时临时文件会被自动删除
+pub fn write_atomically<F>(path: &Path, write: F) -> Result<u64, String>
+where
+    F: FnOnce(&mut BufWriter<&mut NamedTempFile>) -> Result<(), String>,
+{
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    let mut temp = NamedTempFile::new_in(dir).map_err(|e| format!("创建临时文件失败: {}", e))?;
+
+    {
+        let mut writer = BufWriter::new(&mut temp);
+        write(&mut writer)?;
+        writer.flush().map_err(io_err)?;
+    }
+
+    temp.as_file().sync_all().map_err(io_err)?;
+    temp.persist(path)
+        .map_err(|e| format!("保存导出文件失败: {}", e.error))?;
+
+    fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("读取导出文件信息失败: {}", e))
+}
+
+/// 将数据库流式导出到文件
+pub fn export_to_file(
+    conn: &Connection,
+    path: &Path,
+    filter: Option<&ExportFilter>,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<ExportFileReport, String> {
+    let mut counts = (0, 0);
+    let bytes_written = write_atomically(path, |writer| {
+        counts = write_export(conn, writer, filter, cancel, on_progress)?;
+        Ok(())
+    })?;
+
+    Ok(ExportFileReport {
+        path: path.to_string_lossy().to_string(),
+        notes_exported: counts.0,
+        messages_exported: counts.1,
+        bytes_written,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ExportData;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        for i in 0..45 {
+            conn.execute(
+                "INSERT INTO notes (title, content, tags, created_at, updated_at) VALUES (?1, ?2, '[\"t\"]', 'c', 'u')",
+                rusqlite::params![format!("笔记{}", i), "![](data:image/png;base64,AAAA)"],
+            )
+            .unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO chat_messages (note_id, role, content, timestamp) VALUES (1, 'user', '\"引号\"', 't')",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn streams_valid_export_with_progress() {
+        let conn = setup();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+
+        let mut events = Vec::new();
+        let cancel = AtomicBool::new(false);
+        let report = export_to_file(&conn, &path, None, &cancel, &mut |p| events.push(p)).unwrap();
+
+        assert_eq!(report.notes_exported, 45);
+        assert_eq!(report.messages_exported, 1);
+        assert_eq!(report.bytes_written, fs::metadata(&path).unwrap().len());
+
+        let data: ExportData = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(data.notes.len(), 45);
+        assert_eq!(data.notes[0].tags, vec!["t".to_string()]);
+        assert_eq!(data.chat_messages[0].content, "\"引号\"");
+
+        let last = events.last().unwrap();
+        assert_eq!((last.done, last.total), (46, 46));
+        assert_eq!(events[0].done, 0);
+    }
+
+    #[test]
+    fn filtered_export_keeps_matching_notes_and_their_messages() {
+        let conn = setup();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.json");
+
+        let filter = ExportFilter {
+            note_ids: vec![1, 2],
+            ..Default::default()
+        };
+        let mut events = Vec::new();
+        let cancel = AtomicBool::new(false);
+        let report = export_to_file(&conn, &path, Some(&filter), &cancel, &mut |p| {
+            events.push(p)
+        })
+        .unwrap();
+        assert_eq!(report.notes_exported, 2);
+        assert_eq!(report.messages_exported, 1);
+
+        let data: ExportData = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(data.notes.len(), 2);
+        assert_eq!(data.chat_messages.len(), 1);
+        assert_eq!(events.last().unwrap().done, 46);
+    }
+
+    #[test]
+    fn cancelled_export_leaves_no_file() {
+        let conn = setup();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        fs::write(&path, "旧文件").unwrap();
+
+        let cancel = AtomicBool::new(true);
+        let err = export_to_file(&conn, &path, None, &cancel, &mut |_| {}).unwrap_err();
+        assert!(err.contains("取消"));
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "旧文件");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn cancels_only_the_requested_task() {
+        let state = ExportState::default();
+        let json = state.start("json").unwrap();
+        let archive = state.start("archive").unwrap();
+        assert!(state.start("json").is_err());
+
+        assert!(state.cancel("json"));
+        assert!(json.cancel.load(Ordering::Relaxed));
+        assert!(!archive.cancel.load(Ordering::Relaxed));
+
+        drop(json);
+        assert!(!state.cancel("json"));
+        assert!(state.start("json").is_ok());
+    }
+}
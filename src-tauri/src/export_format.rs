@@ -0,0 +1,421 @@
+//! 导出文件格式版本与校验
+//!
+//! 版本历史：
+//! - 1.0：早期格式。前端 `dbOperations.exportJSON` 导出的笔记字段为驼峰命名
+//!   （isFavorite、createdAt 等），tags 可能是 JSON 字符串，时间格式不统一
+//! - 2.0：字段统一为下划线命名，tags 为字符串数组，时间均为 ISO 8601
+//!
+//! 导入时先按升级链将旧版本逐步升级到当前版本，再进行严格校验，
+//! 校验结果以结构化报告返回
+
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use serde_json::{Map, Value};
+
+use crate::models::{ExportData, ValidationIssue, ValidationReport};
+use crate::storage::{self, normalize_timestamp};
+
+/// 升级函数：就地修改 JSON，可追加警告
+type Upgrader = fn(&mut Value, &mut Vec<ValidationIssue>);
+
+/// 升级链：(起始版本, 目标版本, 升级函数)
+const UPGRADES: &[(&str, &str, Upgrader)] = &[("1.0", "2.0", upgrade_1_0_to_2_0)];
+
+/// chat_messages 表 CHECK 约束允许的角色
+const ALLOWED_ROLES: [&str; 2] = ["user", "assistant"];
+
+fn issue(path: impl Into<String>, message: impl Into<String>) -> ValidationIssue {
+    ValidationIssue {
+        path: path.into(),
+        message: message.into(),
+    }
+}
+
+/// 将对象中的驼峰字段重命名为下划线字段（目标字段已存在时保留目标字段）
+fn rename_keys(object: &mut Map<String, Value>, renames: &[(&str, &str)]) {
+    for (from, to) in renames {
+        if let Some(value) = object.remove(*from) {
+            object.entry(to.to_string()).or_insert(value);
+        }
+    }
+}
+
+/// 将时间字段统一为 ISO 8601，无法解析的保留原值交给校验报错
+fn normalize_time_field(object: &mut Map<String, Value>, key: &str) {
+    if let Some(value) = object.get_mut(key) {
+        if let Some(iso) = normalize_timestamp(value) {
+            *value = Value::String(iso);
+        }
+    }
+}
+
+/// 将布尔值或缺失的标记字段统一为 0/1
+fn normalize_flag_field(object: &mut Map<String, Value>, key: &str) {
+    let flag = match object.get(key) {
+        Some(Value::Bool(b)) => *b as i64,
+        Some(Value::Number(n)) => n.as_i64().map(|n| (n != 0) as i64).unwrap_or(0),
+        Some(Value::Null) | None => 0,
+        Some(_) => return,
+    };
+    object.insert(key.to_string(), Value::from(flag));
+}
+
+/// 1.0 -> 2.0：统一字段命名、tags 和时间格式
+fn upgrade_1_0_to_2_0(data: &mut Value, warnings: &mut Vec<ValidationIssue>) {
+    let Some(root) = data.as_object_mut() else {
+        return;
+    };
+    rename_keys(
+        root,
+        &[
+            ("exportedAt", "exported_at"),
+            ("chatMessages", "chat_messages"),
+        ],
+    );
+
+    if let Some(Value::Array(notes)) = root.get_mut("notes") {
+        for (i, note) in notes.iter_mut().enumerate() {
+            let Some(note) = note.as_object_mut() else {
+                continue;
+            };
+            rename_keys(
+                note,
+                &[
+                    ("isFavorite", "is_favorite"),
+                    ("isDeleted", "is_deleted"),
+                    ("createdAt", "created_at"),
+                    ("updatedAt", "updated_at"),
+                    ("reminderDate", "reminder_date"),
+                    ("reminderEnabled", "reminder_enabled"),
+                ],
+            );
+            if let Some(Value::String(raw)) = note.get("tags") {
+                let tags = storage::decode_tags(raw);
+                note.insert("tags".to_string(), Value::from(tags));
+            } else if !note.contains_key("tags") {
+                note.insert("tags".to_string(), Value::Array(Vec::new()));
+            }
+            for key in ["is_favorite", "is_deleted", "reminder_enabled"] {
+                normalize_flag_field(note, key);
+            }
+            for key in ["created_at", "updated_at", "reminder_date"] {
+                normalize_time_field(note, key);
+            }
+            if !note.contains_key("updated_at") {
+                if let Some(created_at) = note.get("created_at").cloned() {
+                    note.insert("updated_at".to_string(), created_at);
+                    warnings.push(issue(
+                        format!("notes[{}].updated_at", i),
+                        "缺少 updated_at，已使用 created_at",
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Array(messages)) = root.get_mut("chat_messages") {
+        for message in messages.iter_mut().filter_map(|m| m.as_object_mut()) {
+            rename_keys(message, &[("noteId", "note_id")]);
+            normalize_time_field(message, "timestamp");
+        }
+    }
+}
+
+fn check_string(
+    object: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    match object.get(key) {
+        Some(Value::String(_)) => {}
+        None | Some(Value::Null) => errors.push(issue(format!("{}.{}", path, key), "缺少必填字段")),
+        Some(_) => errors.push(issue(format!("{}.{}", path, key), "应为字符串")),
+    }
+}
+
+fn check_iso_date(
+    object: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    optional: bool,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    match object.get(key) {
+        Some(Value::String(s)) if DateTime::parse_from_rfc3339(s).is_ok() => {}
+        Some(Value::String(s)) => errors.push(issue(
+            format!("{}.{}", path, key),
+            format!("不是有效的 ISO 8601 时间: {}", s),
+        )),
+        None | Some(Value::Null) if optional => {}
+        None | Some(Value::Null) => errors.push(issue(format!("{}.{}", path, key), "缺少必填字段")),
+        Some(_) => errors.push(issue(format!("{}.{}", path, key), "应为 ISO 8601 字符串")),
+    }
+}
+
+fn check_flag(
+    object: &Map<String, Value>,
+    key: &str,
+    path: &str,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    match object.get(key).and_then(|v| v.as_i64()) {
+        Some(0) | Some(1) => {}
+        _ => errors.push(issue(format!("{}.{}", path, key), "应为 0 或 1")),
+    }
+}
+
+/// 严格校验当前版本的导出数据
+fn validate(data: &Value, report: &mut ValidationReport) {
+    let Some(root) = data.as_object() else {
+        report.errors.push(issue("$", "根节点应为对象"));
+        return;
+    };
+
+    let empty = Vec::new();
+    let notes = match root.get("notes") {
+        Some(Value::Array(notes)) => notes,
+        _ => {
+            report.errors.push(issue("notes", "缺少笔记数组"));
+            &empty
+        }
+    };
+    let messages = match root.get("chat_messages") {
+        Some(Value::Array(messages)) => messages,
+        None => &empty,
+        Some(_) => {
+            report.errors.push(issue("chat_messages", "应为数组"));
+            &empty
+        }
+    };
+
+    let mut note_ids = HashSet::new();
+    for (i, note) in notes.iter().enumerate() {
+        let path = format!("notes[{}]", i);
+        let Some(note) = note.as_object() else {
+            report.errors.push(issue(path, "应为对象"));
+            continue;
+        };
+        match note.get("id") {
+            Some(Value::Number(n)) if n.as_i64().is_some() => {
+                if !note_ids.insert(n.as_i64().unwrap_or_default()) {
+                    report.errors.push(issue(
+                        format!("{}.id", path),
+                        format!("笔记 ID 重复: {}", n),
+                    ));
+                }
+            }
+            None | Some(Value::Null) => report.warnings.push(issue(
+                format!("{}.id", path),
+                "缺少 id，该笔记的聊天记录无法关联",
+            )),
+            Some(_) => report
+                .errors
+                .push(issue(format!("{}.id", path), "应为整数")),
+        }
+        check_string(note, "title", &path, &mut report.errors);
+        check_string(note, "content", &path, &mut report.errors);
+        match note.get("tags") {
+            Some(Value::Array(tags)) if tags.iter().all(|t| t.is_string()) => {}
+            _ => report
+                .errors
+                .push(issue(format!("{}.tags", path), "应为字符串数组")),
+        }
+        for key in ["is_favorite", "is_deleted", "reminder_enabled"] {
+            check_flag(note, key, &path, &mut report.errors);
+        }
+        check_iso_date(note, "created_at", &path, false, &mut report.errors);
+        check_iso_date(note, "updated_at", &path, false, &mut report.errors);
+        check_iso_date(note, "reminder_date", &path, true, &mut report.errors);
+    }
+
+    for (i, message) in messages.iter().enumerate() {
+        let path = format!("chat_messages[{}]", i);
+        let Some(message) = message.as_object() else {
+            report.errors.push(issue(path, "应为对象"));
+            continue;
+        };
+        match message.get("note_id").and_then(|v| v.as_i64()) {
+            Some(note_id) if !note_ids.contains(&note_id) => report.warnings.push(issue(
+                format!("{}.note_id", path),
+                format!("引用了不存在的笔记 {}，导入时将跳过", note_id),
+            )),
+            Some(_) => {}
+            None => report
+                .errors
+                .push(issue(format!("{}.note_id", path), "缺少或不是整数")),
+        }
+        match message.get("role").and_then(|v| v.as_str()) {
+            Some(role) if ALLOWED_ROLES.contains(&role) => {}
+            Some(role) => report.errors.push(issue(
+                format!("{}.role", path),
+                format!("role 只能是 user 或 assistant，实际为 {}", role),
+            )),
+            None => report
+                .errors
+                .push(issue(format!("{}.role", path), "缺少必填字段")),
+        }
+        check_string(message, "content", &path, &mut report.errors);
+        check_iso_date(message, "timestamp", &path, false, &mut report.errors);
+    }
+}
+
+/// 解析、升级并校验导出文件
+/// 返回校验报告，以及在没有错误时反序列化得到的导出数据
+pub fn parse_export(json: &str) -> (Option<ExportData>, ValidationReport) {
+    let mut report = ValidationReport {
+        target_version: storage::EXPORT_VERSION.to_string(),
+        ..Default::default()
+    };
+
+    let mut data: Value = match serde_json::from_str(json) {
+        Ok(data) => data,
+        Err(e) => {
+            report.errors.push(issue(
+                format!("$ (第 {} 行第 {} 列)", e.line(), e.column()),
+                format!("JSON 解析失败: {}", e),
+            ));
+            return (None, report);
+        }
+    };
+
+    // 未声明版本的文件按最早的格式处理
+    let mut version = match data.get("version") {
+        Some(Value::String(v)) => v.clone(),
+        Some(Value::Number(n)) => format!("{:.1}", n.as_f64().unwrap_or_default()),
+        _ => {
+            report
+                .warnings
+                .push(issue("version", "缺少版本号，按 1.0 格式处理"));
+            UPGRADES[0].0.to_string()
+        }
+    };
+    report.source_version = version.clone();
+
+    while version != storage::EXPORT_VERSION {
+        let Some((from, to, upgrade)) = UPGRADES.iter().find(|(from, _, _)| *from == version)
+        else {
+            report.errors.push(issue(
+                "version",
+                format!("不支持的导出格式版本: {}", version),
+            ));
+            return (None, report);
+        };
+        upgrade(&mut data, &mut report.warnings);
+        report.upgrades.push(format!("{} -> {}", from, to));
+        version = to.to_string();
+    }
+    if let Some(root) = data.as_object_mut() {
+        root.insert("version".to_string(), Value::String(version));
+        root.entry("exported_at")
+            .or_insert_with(|| Value::String(String::new()));
+    }
+
+    validate(&data, &mut report);
+    if !report.errors.is_empty() {
+        return (None, report);
+    }
+
+    match serde_json::from_value::<ExportData>(data) {
+        Ok(export) => (Some(export), report),
+        Err(e) => {
+            report
+                .errors
+                .push(issue("$", format!("数据结构不匹配: {}", e)));
+            (None, report)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn upgrades_frontend_1_0_export() {
+        let json = json!({
+            "version": "1.0",
+            "exported_at": "2024-01-01T00:00:00.000Z",
+            "notes": [{
+                "id": 5, "title": "旧", "content": "x", "tags": "[\"a\"]",
+                "isFavorite": true, "isDeleted": 0,
+                "createdAt": "2024-01-01T00:00:00.000Z", "updatedAt": 1704067200000_i64
+            }],
+            "chat_messages": [
+                { "id": 1, "noteId": 5, "role": "user", "content": "hi", "timestamp": "2024-01-01T00:00:00Z" }
+            ]
+        })
+        .to_string();
+
+        let (data, report) = parse_export(&json);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.source_version, "1.0");
+        assert_eq!(report.upgrades, vec!["1.0 -> 2.0".to_string()]);
+
+        let data = data.unwrap();
+        assert_eq!(data.version, storage::EXPORT_VERSION);
+        assert_eq!(data.notes[0].tags, vec!["a".to_string()]);
+        assert_eq!(data.notes[0].is_favorite, 1);
+        assert_eq!(data.notes[0].updated_at, "2024-01-01T00:00:00.000Z");
+        assert_eq!(data.chat_messages[0].note_id, 5);
+    }
+
+    #[test]
+    fn reports_structured_errors_and_warnings() {
+        let json = json!({
+            "version": "2.0",
+            "exported_at": "",
+            "notes": [
+                { "id": 1, "title": "a", "content": "", "tags": [], "is_favorite": 0, "is_deleted": 0,
+                  "created_at": "昨天", "updated_at": "2024-01-01T00:00:00Z", "reminder_enabled": 0 },
+                { "id": 1, "content": "", "tags": [], "is_favorite": 2, "is_deleted": 0,
+                  "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z", "reminder_enabled": 0 }
+            ],
+            "chat_messages": [
+                { "note_id": 9, "role": "system", "content": "x", "timestamp": "2024-01-01T00:00:00Z" }
+            ]
+        })
+        .to_string();
+
+        let (data, report) = parse_export(&json);
+        assert!(data.is_none());
+        let paths: Vec<_> = report.errors.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&"notes[0].created_at"));
+        assert!(paths.contains(&"notes[1].id"));
+        assert!(paths.contains(&"notes[1].title"));
+        assert!(paths.contains(&"notes[1].is_favorite"));
+        assert!(paths.contains(&"chat_messages[0].role"));
+        assert_eq!(report.warnings[0].path, "chat_messages[0].note_id");
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_bad_json() {
+        let (data, report) = parse_export("{\"version\": \"9.0\", \"notes\": []}");
+        assert!(data.is_none());
+        assert_eq!(report.errors[0].path, "version");
+
+        let (data, report) = parse_export("{ not json");
+        assert!(data.is_none());
+        assert!(report.errors[0].message.starts_with("JSON 解析失败"));
+    }
+
+    #[test]
+    fn current_exports_validate_cleanly() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at) VALUES ('a', 'b', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp) VALUES (1, 'assistant', 'c', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&storage::build_export(&conn).unwrap()).unwrap();
+
+        let (data, report) = parse_export(&json);
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+        assert!(report.upgrades.is_empty());
+        assert_eq!(data.unwrap().chat_messages.len(), 1);
+    }
+}
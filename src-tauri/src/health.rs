@@ -0,0 +1,385 @@
+//! 数据库健康检查与修复
+//!
+//! 说明：除 SQLite 自带的 `integrity_check` / `foreign_key_check` 外，
+//! 还检查前端写入时可能留下的数据问题：孤立的聊天消息（前端连接未开启外键约束）、
+//! 格式错误的 tags JSON 和无法解析的时间。能自动修复的问题在同一事务中修复
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::models::{HealthIssue, HealthReport, RepairReport};
+use crate::storage::{self, normalize_timestamp, to_iso};
+
+/// 笔记表中需要检查的时间列（reminder_date 可以为空）
+const DATE_COLUMNS: [&str; 3] = ["created_at", "updated_at", "reminder_date"];
+
+fn issue(
+    kind: &str,
+    table: &str,
+    row_id: Option<i64>,
+    column: Option<&str>,
+    value: Option<String>,
+    message: String,
+    fixable: bool,
+) -> HealthIssue {
+    HealthIssue {
+        kind: kind.to_string(),
+        table: table.to_string(),
+        row_id,
+        column: column.map(str::to_string),
+        value,
+        message,
+        fixable,
+    }
+}
+
+/// 是否为合法的 ISO 8601 时间
+fn is_valid_date(value: &str) -> bool {
+    DateTime::parse_from_rfc3339(value).is_ok()
+}
+
+/// 是否为合法的 tags 列（字符串数组 JSON）
+fn is_valid_tags(raw: &str) -> bool {
+    serde_json::from_str::<Vec<String>>(raw).is_ok()
+}
+
+/// 尽量从格式错误的 tags 中恢复标签：非字符串数组元素转为字符串，
+/// 非 JSON 内容按逗号分隔
+fn repair_tags(raw: &str) -> Vec<String> {
+    let candidates: Vec<String> = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Array(items)) => items
+            .into_iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Ok(Value::String(s)) => s.split([',', '，']).map(str::to_string).collect(),
+        Ok(_) => Vec::new(),
+        Err(_) => raw
+            .trim_matches(|c| c == '[' || c == ']')
+            .split([',', '，'])
+            .map(|t| t.trim().trim_matches('"').to_string())
+            .collect(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in candidates {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// 执行 `foreign_key_check`，孤立聊天消息由单独的检查报告，这里跳过
+fn check_foreign_keys(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| format!("外键检查失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| format!("外键检查失败: {}", e))?;
+    for row in rows {
+        let (table, row_id, parent) = row.map_err(|e| format!("外键检查失败: {}", e))?;
+        if table == "chat_messages" && parent == "notes" {
+            continue;
+        }
+        issues.push(issue(
+            "foreign_key",
+            &table,
+            row_id,
+            None,
+            None,
+            format!("{} 中的记录引用了 {} 中不存在的记录", table, parent),
+            false,
+        ));
+    }
+    Ok(())
+}
+
+/// 查找 note_id 指向不存在笔记的聊天消息
+fn check_orphaned_messages(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.note_id FROM chat_messages m
+             LEFT JOIN notes n ON n.id = m.note_id
+             WHERE n.id IS NULL ORDER BY m.id",
+        )
+        .map_err(|e| format!("查询孤立聊天消息失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| format!("查询孤立聊天消息失败: {}", e))?;
+    for row in rows {
+        let (id, note_id) = row.map_err(|e| format!("查询孤立聊天消息失败: {}", e))?;
+        issues.push(issue(
+            "orphaned_message",
+            "chat_messages",
+            Some(id),
+            Some("note_id"),
+            Some(note_id.to_string()),
+            format!("聊天消息所属的笔记 {} 不存在", note_id),
+            true,
+        ));
+    }
+    Ok(())
+}
+
+/// 检查 notes 表中的 tags 和时间列
+fn check_notes(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT id, tags, created_at, updated_at, reminder_date FROM notes ORDER BY id")
+        .map_err(|e| format!("读取笔记失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                [
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ],
+            ))
+        })
+        .map_err(|e| format!("读取笔记失败: {}", e))?;
+    for row in rows {
+        let (id, tags, dates) = row.map_err(|e| format!("读取笔记失败: {}", e))?;
+        if !is_valid_tags(&tags) {
+            issues.push(issue(
+                "invalid_tags",
+                "notes",
+                Some(id),
+                Some("tags"),
+                Some(tags),
+                "tags 不是有效的字符串数组 JSON".to_string(),
+                true,
+            ));
+        }
+        for (column, value) in DATE_COLUMNS.iter().zip(dates) {
+            let Some(value) = value else {
+                continue;
+            };
+            if !is_valid_date(&value) {
+                issues.push(issue(
+                    "invalid_date",
+                    "notes",
+                    Some(id),
+                    Some(column),
+                    Some(value),
+                    format!("{} 不是有效的 ISO 8601 时间", column),
+                    true,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查数据库，不做任何修改
+pub fn check_database(conn: &Connection) -> Result<HealthReport, String> {
+    let mut issues = Vec::new();
+    for problem in storage::integrity_check(conn)? {
+        // 索引损坏可通过 REINDEX 修复
+        let fixable = problem.contains("index");
+        issues.push(issue("integrity", "", None, None, None, problem, fixable));
+    }
+    check_foreign_keys(conn, &mut issues)?;
+    check_orphaned_messages(conn, &mut issues)?;
+    check_notes(conn, &mut issues)?;
+
+    Ok(HealthReport {
+        healthy: issues.is_empty(),
+        notes_checked: storage::count_rows(conn, "notes")?,
+        messages_checked: storage::count_rows(conn, "chat_messages")?,
+        issues,
+    })
+}
+
+/// 修复无效的时间：能识别的格式转换为 ISO 8601；无法识别时，
+/// created_at / updated_at 互相替代（都无效时使用当前时间），reminder_date 清除并关闭提醒
+fn repair_date(
+    tx: &rusqlite::Transaction,
+    id: i64,
+    column: &str,
+    value: &str,
+) -> Result<(), String> {
+    if let Some(fixed) = normalize_timestamp(&Value::String(value.to_string())) {
+        let sql = format!("UPDATE notes SET {} = ?1 WHERE id = ?2", column);
+        tx.execute(&sql, params![fixed, id])
+            .map_err(|e| format!("修复时间失败: {}", e))?;
+        return Ok(());
+    }
+
+    if column == "reminder_date" {
+        tx.execute(
+            "UPDATE notes SET reminder_date = NULL, reminder_enabled = 0 WHERE id = ?1",
+            [id],
+        )
+        .map_err(|e| format!("修复时间失败: {}", e))?;
+        return Ok(());
+    }
+
+    let other = if column == "created_at" {
+        "updated_at"
+    } else {
+        "created_at"
+    };
+    let other_value: Option<String> = tx
+        .query_row(
+            &format!("SELECT {} FROM notes WHERE id = ?1", other),
+            [id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("修复时间失败: {}", e))?
+        .flatten();
+    let fixed = other_value
+        .filter(|v| is_valid_date(v))
+        .unwrap_or_else(|| to_iso(Utc::now()));
+    let sql = format!("UPDATE notes SET {} = ?1 WHERE id = ?2", column);
+    tx.execute(&sql, params![fixed, id])
+        .map_err(|e| format!("修复时间失败: {}", e))?;
+    Ok(())
+}
+
+/// 修复可自动修复的问题，完成后重新检查
+/// 孤立聊天消息被删除，tags 和时间尽量保留原有内容；完整性问题只尝试重建索引
+pub fn repair_database(conn: &mut Connection) -> Result<RepairReport, String> {
+    let before = check_database(conn)?;
+    let mut report = RepairReport::default();
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    for item in before.issues.iter().filter(|i| i.fixable) {
+        let (Some(id), Some(value)) = (item.row_id, item.value.as_deref()) else {
+            continue;
+        };
+        match item.kind.as_str() {
+            "orphaned_message" => {
+                tx.execute("DELETE FROM chat_messages WHERE id = ?1", [id])
+                    .map_err(|e| format!("删除孤立聊天消息失败: {}", e))?;
+                report.messages_deleted += 1;
+            }
+            "invalid_tags" => {
+                let tags = serde_json::to_string(&repair_tags(value))
+                    .map_err(|e| format!("序列化标签失败: {}", e))?;
+                tx.execute(
+                    "UPDATE notes SET tags = ?1 WHERE id = ?2",
+                    params![tags, id],
+                )
+                .map_err(|e| format!("修复标签失败: {}", e))?;
+                report.tags_fixed += 1;
+            }
+            "invalid_date" => {
+                let column = item.column.as_deref().unwrap_or_default();
+                if DATE_COLUMNS.contains(&column) {
+                    repair_date(&tx, id, column, value)?;
+                    report.dates_fixed += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+    if before
+        .issues
+        .iter()
+        .any(|i| i.kind == "integrity" && i.fixable)
+    {
+        conn.execute_batch("REINDEX")
+            .map_err(|e| format!("重建索引失败: {}", e))?;
+        report.reindexed = true;
+    }
+
+    report.after = check_database(conn)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn repairs_tags_from_common_mistakes() {
+        assert_eq!(repair_tags("工作, 周会"), vec!["工作", "周会"]);
+        assert_eq!(repair_tags("[\"工作\", 2024, null]"), vec!["工作", "2024"]);
+        assert_eq!(repair_tags("\"工作，生活\""), vec!["工作", "生活"]);
+        assert_eq!(repair_tags("[\"工作\", \"周会\""), vec!["工作", "周会"]);
+        assert!(repair_tags("").is_empty());
+    }
+
+    #[test]
+    fn checks_and_repairs_database() {
+        let mut conn = setup();
+        // 模拟前端连接未开启外键约束时写入的数据
+        conn.pragma_update(None, "foreign_keys", false).unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, tags, created_at, updated_at, reminder_date, reminder_enabled)
+             VALUES ('正常', '', '[\"工作\"]', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z', NULL, 0);
+             INSERT INTO notes (title, content, tags, created_at, updated_at, reminder_date, reminder_enabled)
+             VALUES ('有问题', '', '工作,周会', '1704067200000', 'yesterday', 'soon', 1);
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (1, 'user', '你好', '2024-01-01T00:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (99, 'user', '孤立', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+
+        let report = check_database(&conn).unwrap();
+        assert!(!report.healthy);
+        assert_eq!(report.notes_checked, 2);
+        let kinds: Vec<(&str, Option<&str>)> = report
+            .issues
+            .iter()
+            .map(|i| (i.kind.as_str(), i.column.as_deref()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("orphaned_message", Some("note_id")),
+                ("invalid_tags", Some("tags")),
+                ("invalid_date", Some("created_at")),
+                ("invalid_date", Some("updated_at")),
+                ("invalid_date", Some("reminder_date")),
+            ]
+        );
+
+        let repair = repair_database(&mut conn).unwrap();
+        assert_eq!(repair.messages_deleted, 1);
+        assert_eq!(repair.tags_fixed, 1);
+        assert_eq!(repair.dates_fixed, 3);
+        assert!(!repair.reindexed);
+        assert!(repair.after.healthy, "{:?}", repair.after.issues);
+
+        let note = storage::read_notes(&conn)
+            .unwrap()
+            .into_iter()
+            .find(|n| n.title == "有问题")
+            .unwrap();
+        assert_eq!(note.tags, vec!["工作", "周会"]);
+        assert_eq!(note.created_at, "2024-01-01T00:00:00.000Z");
+        assert_eq!(note.updated_at, "2024-01-01T00:00:00.000Z");
+        assert_eq!(note.reminder_date, None);
+        assert_eq!(note.reminder_enabled, 0);
+        assert_eq!(storage::read_chat_messages(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! 旧版 IndexedDB (Dexie.js) 数据转换
+//!
+//! 说明：1.x 之前的版本使用 Dexie 在 WebView 中存储数据，
+//! 记录字段为驼峰命名（noteId、isFavorite、createdAt 等），时间为毫秒时间戳或 Date。
+//! 这里将其转换为 001_initial.sql 中 notes / chat_messages 表对应的模型。
+//! 迁移默认跳过标题和创建时间相同的笔记，重复执行（重试或上次中途崩溃）不会产生重复数据
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::models::{
+    ChatMessage, ConflictOptions, ConflictStrategy, ExportData, ImportIssue, ImportOptions,
+    MatchBy, Note,
+};
+use crate::storage::{self, normalize_timestamp, to_iso};
+
+/// 读取 0/1 标记，兼容布尔值
+fn read_flag(record: &Value, key: &str) -> i32 {
+    match record.get(key) {
+        Some(Value::Bool(b)) => *b as i32,
+        Some(Value::Number(n)) => (n.as_i64().unwrap_or(0) != 0) as i32,
+        _ => 0,
+    }
+}
+
+fn read_id(record: &Value, key: &str) -> Option<i64> {
+    match record.get(key)? {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn read_tags(record: &Value) -> Vec<String> {
+    match record.get("tags") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|t| t.as_str())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        Some(Value::String(raw)) => storage::decode_tags(raw),
+        _ => Vec::new(),
+    }
+}
+
+/// 转换单条 Dexie 笔记记录
+pub fn convert_note(record: &Value) -> Result<Note, String> {
+    if !record.is_object() {
+        return Err("记录不是对象".to_string());
+    }
+    let id = read_id(record, "id").ok_or("缺少 id")?;
+    let created_at = record
+        .get("createdAt")
+        .and_then(normalize_timestamp)
+        .ok_or("createdAt 无法解析")?;
+    let updated_at = record
+        .get("updatedAt")
+        .and_then(normalize_timestamp)
+        .unwrap_or_else(|| created_at.clone());
+    let reminder_date = match record.get("reminderDate") {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(normalize_timestamp(v).ok_or("reminderDate 无法解析")?),
+    };
+
+    Ok(Note {
+        id: Some(id),
+        title: record
+            .get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        content: record
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        tags: read_tags(record),
+        is_favorite: read_flag(record, "isFavorite"),
+        is_deleted: read_flag(record, "isDeleted"),
+        created_at,
+        updated_at,
+        reminder_enabled: if reminder_date.is_some() {
+            read_flag(record, "reminderEnabled")
+        } else {
+            0
+        },
+        reminder_date,
+    })
+}
+
+/// 转换单条 Dexie 聊天消息记录
+pub fn convert_chat_message(record: &Value) -> Result<ChatMessage, String> {
+    if !record.is_object() {
+        return Err("记录不是对象".to_string());
+    }
+    let note_id = read_id(record, "noteId").ok_or("缺少 noteId")?;
+    let role = record
+        .get("role")
+        .and_then(|v| v.as_str())
+        .ok_or("缺少 role")?;
+    if role != "user" && role != "assistant" {
+        return Err(format!("不支持的 role: {}", role));
+    }
+    let timestamp = record
+        .get("timestamp")
+        .and_then(normalize_timestamp)
+        .ok_or("timestamp 无法解析")?;
+
+    Ok(ChatMessage {
+        id: read_id(record, "id"),
+        note_id,
+        role: role.to_string(),
+        content: record
+            .get("content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        timestamp,
+    })
+}
+
+/// 转换 Dexie 导出的 `{ notes, chatMessages }`
+/// 返回可导入的数据以及无法转换的记录
+pub fn convert(data: &Value) -> (ExportData, Vec<ImportIssue>) {
+    let mut issues = Vec::new();
+    let mut notes = Vec::new();
+    let mut chat_messages = Vec::new();
+
+    let records = |key: &str| {
+        data.get(key)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    for (index, record) in records("notes").iter().enumerate() {
+        match convert_note(record) {
+            Ok(note) => notes.push(note),
+            Err(reason) => issues.push(storage::import_issue(
+                "note",
+                index,
+                read_id(record, "id"),
+                format!("无法转换: {}", reason),
+            )),
+        }
+    }
+
+    for (index, record) in records("chatMessages").iter().enumerate() {
+        match convert_chat_message(record) {
+            Ok(message) => chat_messages.push(message),
+            Err(reason) => issues.push(storage::import_issue(
+                "chat_message",
+                index,
+                read_id(record, "id"),
+                format!("无法转换: {}", reason),
+            )),
+        }
+    }
+
+    let export = ExportData {
+        version: storage::EXPORT_VERSION.to_string(),
+        exported_at: to_iso(Utc::now()),
+        notes,
+        chat_messages,
+    };
+    (export, issues)
+}
+
+/// 迁移的默认导入选项：按标题 + 创建时间匹配，已存在的笔记及其聊天记录跳过
+pub fn default_import_options() -> ImportOptions {
+    ImportOptions {
+        conflict: ConflictOptions {
+            strategy: ConflictStrategy::Skip,
+            match_by: MatchBy::TitleCreatedAt,
+        },
+        dry_run: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    #[test]
+    fn converts_dexie_records_and_reports_failures() {
+        let data = json!({
+            "notes": [
+                {
+                    "id": 3, "title": "旧笔记", "content": "<p>x</p>", "tags": ["a", ""],
+                    "isFavorite": true, "isDeleted": 0,
+                    "createdAt": 1704067200000_i64, "updatedAt": "2024-01-02T00:00:00.000Z",
+                    "reminderDate": 1704153600000_i64, "reminderEnabled": 1
+                },
+                { "id": 4, "title": "坏数据", "createdAt": "not a date" }
+            ],
+            "chatMessages": [
+                { "id": 1, "noteId": 3, "role": "assistant", "content": "ok", "timestamp": 1704067200000_i64 },
+                { "id": 2, "noteId": 3, "role": "system", "content": "x", "timestamp": 1704067200000_i64 }
+            ]
+        });
+
+        let (export, issues) = convert(&data);
+        assert_eq!(export.notes.len(), 1);
+        assert_eq!(export.chat_messages.len(), 1);
+
+        let note = &export.notes[0];
+        assert_eq!(note.id, Some(3));
+        assert_eq!(note.tags, vec!["a".to_string()]);
+        assert_eq!(note.is_favorite, 1);
+        assert_eq!(note.created_at, "2024-01-01T00:00:00.000Z");
+        assert_eq!(
+            note.reminder_date.as_deref(),
+            Some("2024-01-02T00:00:00.000Z")
+        );
+        assert_eq!(note.reminder_enabled, 1);
+        assert_eq!(export.chat_messages[0].note_id, 3);
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].kind, "note");
+        assert_eq!(issues[0].source_id, Some(4));
+        assert_eq!(issues[1].kind, "chat_message");
+        assert_eq!(issues[1].index, 1);
+    }
+
+    #[test]
+    fn repeated_migration_does_not_duplicate_notes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        let data = json!({
+            "notes": [{ "id": 1, "title": "旧笔记", "content": "x", "createdAt": 1704067200000_i64 }],
+            "chatMessages": [
+                { "id": 1, "noteId": 1, "role": "user", "content": "hi", "timestamp": 1704067200000_i64 }
+            ]
+        });
+        let (export, _) = convert(&data);
+
+        let first =
+            storage::import_export_data(&mut conn, &export, &default_import_options()).unwrap();
+        assert_eq!(first.notes_inserted, 1);
+        let second =
+            storage::import_export_data(&mut conn, &export, &default_import_options()).unwrap();
+        assert_eq!(second.notes_inserted, 0);
+        assert_eq!(second.messages_inserted, 0);
+        assert_eq!(storage::read_notes(&conn).unwrap().len(), 1);
+        assert_eq!(storage::read_chat_messages(&conn).unwrap().len(), 1);
+    }
+}
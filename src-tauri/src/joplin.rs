@@ -0,0 +1,561 @@
+//! Joplin 导出（JEX / RAW）导入
+//!
+//! 说明：JEX 是 tar 包，RAW 是同样结构的文件夹：每个条目（笔记、笔记本、标签、
+//! 笔记-标签关联、资源）一个 `<ID>.md` 文件，正文后跟 `key: value` 元数据块；
+//! 资源文件位于 `resources/` 目录。笔记本和标签都转换为标签，
+//! 待办的截止时间转换为提醒，`:/<资源 ID>` 引用的资源以 data URL 内嵌
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use chrono::Utc;
+use regex::{Captures, Regex};
+use rusqlite::Connection;
+
+use crate::archive::{self, SizeLimit};
+use crate::conflict::{self, Planner};
+use crate::models::{ImportIssue, ImportOptions, ImportReport, Note, NoteAction};
+use crate::storage::{self, normalize_timestamp, to_iso};
+
+/// Joplin 条目类型（元数据中的 `type_`）
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_RESOURCE: &str = "4";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+
+const RESOURCES_DIR: &str = "resources/";
+
+/// 笔记本层级的最大深度（防止损坏的 parent_id 形成环）
+const MAX_FOLDER_DEPTH: usize = 32;
+
+fn resource_link_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(!?)\[([^\]]*)\]\(:/([0-9a-f]{32})\)").unwrap())
+}
+
+fn resource_src_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"src=":/([0-9a-f]{32})""#).unwrap())
+}
+
+fn meta_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^([a-z_]+): ?(.*)$").unwrap())
+}
+
+/// 反序列化后的 Joplin 条目
+#[derive(Debug, Default)]
+struct Item {
+    title: String,
+    body: String,
+    meta: HashMap<String, String>,
+}
+
+impl Item {
+    fn get(&self, key: &str) -> &str {
+        self.meta.get(key).map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// 解析条目：末尾连续的 `key: value` 行为元数据，之前为「标题、空行、正文」
+fn parse_item(text: &str) -> Option<Item> {
+    let lines: Vec<&str> = text.trim_start_matches('\u{feff}').lines().collect();
+    let mut item = Item::default();
+
+    let mut meta_start = lines.len();
+    while meta_start > 0 {
+        let line = lines[meta_start - 1];
+        let Some(caps) = meta_line_regex().captures(line) else {
+            break;
+        };
+        // 元数据中的换行以 `\n` 转义保存
+        item.meta
+            .insert(caps[1].to_string(), caps[2].replace("\\n", "\n"));
+        meta_start -= 1;
+    }
+    if !item.meta.contains_key("type_") {
+        return None;
+    }
+
+    let content = &lines[..meta_start];
+    let content = match content.last() {
+        Some(&"") => &content[..content.len() - 1],
+        _ => content,
+    };
+    if let Some((title, rest)) = content.split_first() {
+        item.title = title.trim().to_string();
+        let rest = match rest.first() {
+            Some(&"") => &rest[1..],
+            _ => rest,
+        };
+        item.body = rest.join("\n").trim_end().to_string();
+    }
+    Some(item)
+}
+
+/// 读取 JEX（tar）中的全部文件
+pub fn read_jex<R: Read>(reader: R) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = BTreeMap::new();
+    let mut limit = SizeLimit::default();
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("读取 JEX 文件失败: {}", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取 JEX 文件失败: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(|e| format!("读取 JEX 文件失败: {}", e))?
+            .to_string_lossy()
+            .replace('\\', "/");
+        let bytes = limit.read(&path, entry.size(), entry)?;
+        files.insert(path.trim_start_matches("./").to_string(), bytes);
+    }
+    Ok(files)
+}
+
+/// 读取 RAW 导出文件夹中的全部文件
+pub fn read_raw_dir(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut files = BTreeMap::new();
+    let mut limit = SizeLimit::default();
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry.map_err(|e| format!("读取导出文件夹失败: {}", e))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry
+            .path()
+            .strip_prefix(dir)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        let file = fs::File::open(entry.path())
+            .map_err(|e| format!("读取 {} 失败: {}", entry.path().to_string_lossy(), e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("读取 {} 失败: {}", entry.path().to_string_lossy(), e))?
+            .len();
+        let bytes = limit.read(&rel, size, file)?;
+        files.insert(rel, bytes);
+    }
+    Ok(files)
+}
+
+/// Joplin 资源
+struct Resource<'a> {
+    title: &'a str,
+    mime: &'a str,
+    ext: &'a str,
+    bytes: Option<&'a [u8]>,
+}
+
+impl Resource<'_> {
+    fn data_url(&self) -> Option<String> {
+        let bytes = self.bytes?;
+        if archive::is_image_ext(self.ext) {
+            return Some(archive::to_data_url(self.ext, bytes));
+        }
+        let mime = if self.mime.is_empty() {
+            "application/octet-stream"
+        } else {
+            self.mime
+        };
+        Some(format!(
+            "data:{};base64,{}",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))
+    }
+}
+
+/// 解析 Joplin 时间（ISO 8601 字符串或毫秒时间戳），0 表示未设置
+fn parse_time(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw == "0" {
+        return None;
+    }
+    normalize_timestamp(&serde_json::json!(raw))
+}
+
+/// 将导出中的全部笔记转换为 JD Notes 笔记，返回笔记和被跳过的资源引用
+fn convert_items(files: &BTreeMap<String, Vec<u8>>) -> (Vec<Note>, Vec<ImportIssue>) {
+    let mut notes = Vec::new();
+    let mut folders = HashMap::new();
+    let mut tags = HashMap::new();
+    let mut note_tags: HashMap<String, Vec<String>> = HashMap::new();
+    let mut resources = HashMap::new();
+
+    // 资源文件名为 `<ID>.<扩展名>`（旧版本没有扩展名）
+    let resource_files: HashMap<&str, &[u8]> = files
+        .iter()
+        .filter_map(|(path, bytes)| {
+            let name = path.strip_prefix(RESOURCES_DIR)?;
+            let id = name.split('.').next().unwrap_or(name);
+            Some((id, bytes.as_slice()))
+        })
+        .collect();
+
+    let items: Vec<Item> = files
+        .iter()
+        .filter(|(path, _)| path.ends_with(".md") && !path.starts_with(RESOURCES_DIR))
+        .filter_map(|(_, bytes)| parse_item(&String::from_utf8_lossy(bytes)))
+        .collect();
+
+    for item in &items {
+        match item.get("type_") {
+            TYPE_NOTE => notes.push(item),
+            TYPE_FOLDER => {
+                folders.insert(item.get("id"), (item.title.as_str(), item.get("parent_id")));
+            }
+            TYPE_TAG => {
+                tags.insert(item.get("id"), item.title.as_str());
+            }
+            TYPE_NOTE_TAG => note_tags
+                .entry(item.get("note_id").to_string())
+                .or_default()
+                .push(item.get("tag_id").to_string()),
+            TYPE_RESOURCE => {
+                let id = item.get("id");
+                let ext = match item.get("file_extension") {
+                    "" => item.get("mime").rsplit('/').next().unwrap_or_default(),
+                    ext => ext,
+                };
+                resources.insert(
+                    id,
+                    Resource {
+                        title: item.title.as_str(),
+                        mime: item.get("mime"),
+                        ext,
+                        bytes: resource_files.get(id).copied(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let now = to_iso(Utc::now());
+    let mut converted = Vec::with_capacity(notes.len());
+    let mut skipped = Vec::new();
+    for (index, item) in notes.into_iter().enumerate() {
+        // 笔记本从外到内依次作为标签
+        let mut note_tag_names = Vec::new();
+        let mut parent = item.get("parent_id");
+        while let Some((title, grandparent)) = folders.get(parent) {
+            if note_tag_names.len() >= MAX_FOLDER_DEPTH {
+                break;
+            }
+            note_tag_names.insert(0, title.to_string());
+            parent = grandparent;
+        }
+        for tag_id in note_tags.get(item.get("id")).into_iter().flatten() {
+            if let Some(tag) = tags.get(tag_id.as_str()) {
+                note_tag_names.push(tag.to_string());
+            }
+        }
+        let mut unique_tags: Vec<String> = Vec::new();
+        for tag in note_tag_names {
+            if !tag.is_empty() && !unique_tags.contains(&tag) {
+                unique_tags.push(tag);
+            }
+        }
+
+        let mut missing = Vec::new();
+        let content = resource_link_regex().replace_all(&item.body, |caps: &Captures| {
+            let (bang, text, id) = (&caps[1], &caps[2], &caps[3]);
+            match resources.get(id).and_then(Resource::data_url) {
+                Some(url) => format!("{}[{}]({})", bang, text, url),
+                None => {
+                    // 指向其他笔记的链接或缺失的资源只保留文字
+                    if resources.contains_key(id) {
+                        missing.push(id.to_string());
+                    }
+                    text.to_string()
+                }
+            }
+        });
+        let content = resource_src_regex().replace_all(&content, |caps: &Captures| match resources
+            .get(&caps[1])
+            .and_then(Resource::data_url)
+        {
+            Some(url) => format!("src=\"{}\"", url),
+            None => {
+                missing.push(caps[1].to_string());
+                caps[0].to_string()
+            }
+        });
+        for id in missing {
+            let title = resources
+                .get(id.as_str())
+                .map(|r| r.title)
+                .unwrap_or_default();
+            skipped.push(storage::import_issue(
+                "resource",
+                index,
+                None,
+                format!("「{}」引用的资源 {} {} 缺少文件", item.title, id, title),
+            ));
+        }
+
+        let created_at = parse_time(item.get("user_created_time"))
+            .or_else(|| parse_time(item.get("created_time")))
+            .unwrap_or_else(|| now.clone());
+        let updated_at = parse_time(item.get("user_updated_time"))
+            .or_else(|| parse_time(item.get("updated_time")))
+            .unwrap_or_else(|| created_at.clone());
+        let reminder_date = if item.get("is_todo") == "1" {
+            parse_time(item.get("todo_due"))
+        } else {
+            None
+        };
+        let completed = parse_time(item.get("todo_completed")).is_some();
+
+        converted.push(Note {
+            id: None,
+            title: if item.title.is_empty() {
+                "无标题".to_string()
+            } else {
+                item.title.clone()
+            },
+            content: content.into_owned(),
+            tags: unique_tags,
+            is_favorite: 0,
+            is_deleted: i32::from(parse_time(item.get("deleted_time")).is_some()),
+            created_at,
+            updated_at,
+            reminder_enabled: i32::from(reminder_date.is_some() && !completed),
+            reminder_date,
+        });
+    }
+    (converted, skipped)
+}
+
+/// 导入 Joplin 导出的全部笔记，所有笔记在同一事务中按冲突策略写入
+pub fn import_files(
+    conn: &mut Connection,
+    files: &BTreeMap<String, Vec<u8>>,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let (notes, skipped) = convert_items(files);
+    let mut planner = Planner::new(conn, &options.conflict)?;
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        skipped,
+        ..Default::default()
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    for (index, note) in notes.iter().enumerate() {
+        let action = planner.plan(index, note);
+        if !options.dry_run {
+            match conflict::apply(&tx, note, &action) {
+                Ok(None) => {}
+                Ok(Some(_)) if action.action == NoteAction::Overwrite => report.notes_updated += 1,
+                Ok(Some(_)) => report.notes_inserted += 1,
+                Err(e) => {
+                    report
+                        .failed
+                        .push(storage::import_issue("note", index, None, e.to_string()))
+                }
+            }
+        }
+        report.actions.push(action);
+    }
+
+    if options.dry_run || !report.failed.is_empty() {
+        tx.rollback().map_err(|e| format!("回滚事务失败: {}", e))?;
+        report.notes_inserted = 0;
+        report.notes_updated = 0;
+    } else {
+        tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+        report.committed = true;
+    }
+    Ok(report)
+}
+
+/// 导入 `.jex` 文件或 RAW 导出文件夹
+pub fn import_path(
+    conn: &mut Connection,
+    path: &Path,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let files = if path.is_dir() {
+        read_raw_dir(path)?
+    } else {
+        let file = fs::File::open(path).map_err(|e| format!("打开 JEX 文件失败: {}", e))?;
+        read_jex(std::io::BufReader::new(file))?
+    };
+    import_files(conn, &files, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = "11111111111111111111111111111111";
+    const CHILD: &str = "22222222222222222222222222222222";
+    const NOTE: &str = "33333333333333333333333333333333";
+    const TODO: &str = "44444444444444444444444444444444";
+    const TAG: &str = "55555555555555555555555555555555";
+    const IMAGE: &str = "66666666666666666666666666666666";
+    const MISSING: &str = "77777777777777777777777777777777";
+
+    fn sample_files() -> Vec<(String, String)> {
+        vec![
+            (
+                format!("{}.md", NOTEBOOK),
+                format!("工作\n\nid: {}\nparent_id: \ntype_: 2", NOTEBOOK),
+            ),
+            (
+                format!("{}.md", CHILD),
+                format!("项目A\n\nid: {}\nparent_id: {}\ntype_: 2", CHILD, NOTEBOOK),
+            ),
+            (
+                format!("{}.md", NOTE),
+                format!(
+                    "周会纪要\n\n## 议题\n\n![截图](:/{img})\n\n见 [另一篇](:/{todo})\n\n<img src=\":/{missing}\">\n\nid: {note}\nparent_id: {child}\ncreated_time: 2024-01-01T00:00:00.000Z\nupdated_time: 2024-01-02T00:00:00.000Z\nuser_created_time: 2023-12-31T08:00:00.000Z\nis_todo: 0\ntodo_due: 0\ntype_: 1",
+                    img = IMAGE,
+                    todo = TODO,
+                    missing = MISSING,
+                    note = NOTE,
+                    child = CHILD
+                ),
+            ),
+            (
+                format!("{}.md", TODO),
+                format!(
+                    "买票\n\nid: {}\nparent_id: {}\ncreated_time: 2024-01-01T00:00:00.000Z\nupdated_time: 2024-01-01T00:00:00.000Z\nis_todo: 1\ntodo_due: 1704096000000\ntodo_completed: 0\ntype_: 1",
+                    TODO, NOTEBOOK
+                ),
+            ),
+            (
+                format!("{}.md", TAG),
+                format!("重要\n\nid: {}\ntype_: 5", TAG),
+            ),
+            (
+                "88888888888888888888888888888888.md".to_string(),
+                format!(
+                    "id: 88888888888888888888888888888888\nnote_id: {}\ntag_id: {}\ntype_: 6",
+                    NOTE, TAG
+                ),
+            ),
+            (
+                format!("{}.md", IMAGE),
+                format!(
+                    "截图.png\n\nid: {}\nmime: image/png\nfile_extension: png\ntype_: 4",
+                    IMAGE
+                ),
+            ),
+            (
+                format!("{}.md", MISSING),
+                format!(
+                    "附件.png\n\nid: {}\nmime: image/png\nfile_extension: png\ntype_: 4",
+                    MISSING
+                ),
+            ),
+        ]
+    }
+
+    fn build_jex() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut files: Vec<(String, Vec<u8>)> = sample_files()
+            .into_iter()
+            .map(|(name, text)| (name, text.into_bytes()))
+            .collect();
+        files.push((format!("resources/{}.png", IMAGE), b"\x89PNG".to_vec()));
+        for (name, bytes) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, bytes.as_slice())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn parses_item_with_metadata() {
+        let item = parse_item("标题\n\n第一行\n\n第二行\n\nid: abc\nbody_html: a\\nb\ntype_: 1\n")
+            .unwrap();
+        assert_eq!(item.title, "标题");
+        assert_eq!(item.body, "第一行\n\n第二行");
+        assert_eq!(item.get("id"), "abc");
+        assert_eq!(item.get("body_html"), "a\nb");
+        assert!(parse_item("没有元数据的文件").is_none());
+    }
+
+    #[test]
+    fn converts_notebooks_tags_todos_and_resources() {
+        let files = read_jex(build_jex().as_slice()).unwrap();
+        let (notes, skipped) = convert_items(&files);
+        assert_eq!(notes.len(), 2);
+
+        let meeting = notes.iter().find(|n| n.title == "周会纪要").unwrap();
+        assert_eq!(meeting.tags, vec!["工作", "项目A", "重要"]);
+        assert_eq!(meeting.created_at, "2023-12-31T08:00:00.000Z");
+        assert!(meeting.content.contains("![截图](data:image/png;base64,"));
+        assert!(meeting.content.contains("见 另一篇"));
+        assert_eq!(meeting.reminder_date, None);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].kind, "resource");
+
+        let todo = notes.iter().find(|n| n.title == "买票").unwrap();
+        assert_eq!(todo.tags, vec!["工作"]);
+        assert_eq!(
+            todo.reminder_date.as_deref(),
+            Some("2024-01-01T08:00:00.000Z")
+        );
+        assert_eq!(todo.reminder_enabled, 1);
+    }
+
+    #[test]
+    fn imports_raw_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in sample_files() {
+            fs::write(dir.path().join(name), text).unwrap();
+        }
+        fs::create_dir(dir.path().join("resources")).unwrap();
+        fs::write(
+            dir.path().join(format!("resources/{}.png", IMAGE)),
+            b"\x89PNG",
+        )
+        .unwrap();
+
+        let mut conn = setup();
+        let preview = import_path(
+            &mut conn,
+            dir.path(),
+            &ImportOptions {
+                dry_run: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(preview.actions.len(), 2);
+        assert!(!preview.committed);
+
+        let report = import_path(&mut conn, dir.path(), &ImportOptions::default()).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 2);
+        assert_eq!(storage::read_notes(&conn).unwrap().len(), 2);
+    }
+}
@@ -1,17 +1,92 @@
+mod archive;
+mod backup;
 mod commands;
+mod config;
+mod conflict;
 mod db;
+mod encryption;
+mod enex;
+mod export;
+mod export_format;
+mod health;
+mod indexeddb;
+mod joplin;
+mod maintenance;
+mod markdown;
 mod models;
+mod notion;
+mod pdf;
+mod schema;
+mod site;
+mod storage;
 
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager,
+    AppHandle, Manager,
 };
-use tauri_plugin_sql::{Builder as SqlBuilder, Migration, MigrationKind};
+use tauri_plugin_sql::Builder as SqlBuilder;
+
+/// 系统托盘图标 ID
+const TRAY_ID: &str = "main";
+/// 托盘菜单中切换笔记库菜单项的 ID 前缀
+const VAULT_MENU_PREFIX: &str = "vault:";
+
+/// 创建系统托盘菜单（包含「切换笔记库」子菜单）
+fn build_tray_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+
+    let vault_list = db::list_vaults(app).unwrap_or_else(|e| {
+        log::warn!("读取笔记库列表失败: {}", e);
+        db::VaultList {
+            vaults: Vec::new(),
+            active_vault: String::new(),
+        }
+    });
+    let vault_items = vault_list
+        .vaults
+        .iter()
+        .map(|vault| {
+            CheckMenuItem::with_id(
+                app,
+                format!("{}{}", VAULT_MENU_PREFIX, vault.id),
+                &vault.name,
+                true,
+                vault.id == vault_list.active_vault,
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let vault_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = vault_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<tauri::Wry>)
+        .collect();
+    let vault_menu = Submenu::with_items(app, "切换笔记库", true, &vault_refs)?;
+
+    Menu::with_items(app, &[&show_item, &vault_menu, &separator, &quit_item])
+}
+
+/// 笔记库列表变化后重建托盘菜单
+pub(crate) fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_tray_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("更新托盘菜单失败: {}", e);
+            }
+        }
+        Err(e) => log::warn!("创建托盘菜单失败: {}", e),
+    }
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(export::ExportState::default())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -19,13 +94,16 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .setup(|app| {
+            // 记录当前笔记库的打开时间
+            if let Err(e) = db::touch_active_vault(app.handle()) {
+                log::warn!("更新笔记库打开时间失败: {}", e);
+            }
+
             // 创建系统托盘菜单
-            let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu = build_tray_menu(app.handle())?;
 
             // 创建系统托盘图标
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .show_menu_on_left_click(false)
@@ -40,7 +118,21 @@ pub fn run() {
                     "quit" => {
                         app.exit(0);
                     }
-                    _ => {}
+                    id => {
+                        // 切换笔记库（关闭旧连接并重新加载前端）
+                        if let Some(vault_id) = id.strip_prefix(VAULT_MENU_PREFIX) {
+                            let app = app.clone();
+                            let vault_id = vault_id.to_string();
+                            tauri::async_runtime::spawn(async move {
+                                if let Err(e) =
+                                    commands::switch_vault(app.clone(), vault_id).await
+                                {
+                                    log::error!("切换笔记库失败: {}", e);
+                                    refresh_tray_menu(&app);
+                                }
+                            });
+                        }
+                    }
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -69,25 +161,19 @@ pub fn run() {
 
             // 获取数据库完整路径（考虑用户自定义配置）
             let db_path = db::get_database_path(app.handle())
-                .map_err(|e| Box::<dyn std::error::Error>::from(e))?;
+                .map_err(Box::<dyn std::error::Error>::from)?;
             let db_url = format!("sqlite:{}", db_path.to_string_lossy());
             
             log::info!("数据库路径: {}", db_url);
 
-            // 创建迁移
-            let migrations = vec![Migration {
-                version: 1,
-                description: "create initial tables",
-                sql: db::get_init_sql(),
-                kind: MigrationKind::Up,
-            }];
+            // 执行数据库结构迁移（migrations 目录中的脚本），前端连接时结构已是最新
+            db::migrate_database(app.handle()).map_err(Box::<dyn std::error::Error>::from)?;
 
             // 注册 SQL 插件
-            app.handle().plugin(
-                SqlBuilder::default()
-                    .add_migrations(&db_url, migrations)
-                    .build(),
-            )?;
+            app.handle().plugin(SqlBuilder::default().build())?;
+
+            // 启动自动备份
+            db::spawn_backup_scheduler(app.handle().clone());
             
             Ok(())
         })
@@ -100,13 +186,59 @@ pub fn run() {
             commands::change_database_location,
             // 导入导出
             commands::export_database_json,
+            commands::export_database_to_file,
+            commands::cancel_export,
+            commands::validate_export_json,
             commands::import_database_json,
             commands::import_from_indexeddb,
+            commands::export_markdown_vault,
+            commands::import_markdown_folder,
+            commands::export_archive,
+            commands::import_archive,
+            commands::import_enex,
+            commands::import_notion_export,
+            commands::import_joplin_export,
+            commands::export_html_site,
+            commands::export_pdf,
             // AI 设置
             commands::get_ai_settings,
             commands::save_ai_settings,
             commands::get_config_path,
+            // 笔记库管理
+            commands::list_vaults,
+            commands::create_vault,
+            commands::rename_vault,
+            commands::remove_vault,
+            commands::set_vault_ai_override,
+            commands::switch_vault,
+            // 自动备份
+            commands::get_backup_settings,
+            commands::save_backup_settings,
+            commands::list_backups,
+            commands::create_backup,
+            commands::restore_database,
+            // 数据库维护
+            commands::check_database,
+            commands::repair_database,
+            commands::get_storage_stats,
+            commands::optimize_database,
+            // 数据库加密
+            commands::get_encryption_status,
+            commands::unlock_database,
+            commands::enable_encryption,
+            commands::change_database_passphrase,
+            commands::disable_encryption,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // 退出时备份，之后清除内存中的数据库密码
+            if let tauri::RunEvent::Exit = event {
+                let settings = db::get_backup_settings(app).unwrap_or_default();
+                if settings.enabled && settings.on_exit {
+                    db::run_auto_backup(app, "退出");
+                }
+                encryption::lock();
+            }
+        });
 }
@@ -0,0 +1,226 @@
+//! 数据库存储统计与维护
+//!
+//! 说明：统计各表行数、笔记与聊天内容的大小、内嵌图片占用的空间和页面使用情况；
+//! 维护依次执行 `VACUUM`、`ANALYZE`、`PRAGMA optimize`，
+//! 每步之后做一次 WAL 检查点，使文件大小的变化能反映该步回收的空间
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use regex::Regex;
+use rusqlite::Connection;
+
+use crate::models::{MaintenanceReport, MaintenanceStep, NoteSize, StorageStats, TableStats};
+use crate::storage;
+
+/// 统计中列出的最大笔记数
+const LARGEST_NOTES: usize = 10;
+
+fn data_url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"data:image/[A-Za-z0-9.+-]+;base64,[A-Za-z0-9+/=]+").unwrap())
+}
+
+fn wal_path(db_path: &Path) -> std::path::PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push("-wal");
+    name.into()
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn pragma_u64(conn: &Connection, name: &str) -> Result<u64, String> {
+    conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0))
+        .map(|n| n as u64)
+        .map_err(|e| format!("读取 {} 失败: {}", name, e))
+}
+
+/// 统计数据库存储情况
+pub fn storage_stats(conn: &Connection, db_path: &Path) -> Result<StorageStats, String> {
+    let mut stats = StorageStats {
+        file_size: file_size(db_path),
+        wal_size: file_size(&wal_path(db_path)),
+        page_size: pragma_u64(conn, "page_size")?,
+        page_count: pragma_u64(conn, "page_count")?,
+        freelist_count: pragma_u64(conn, "freelist_count")?,
+        ..Default::default()
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .map_err(|e| format!("读取表列表失败: {}", e))?;
+    let tables: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("读取表列表失败: {}", e))?;
+    for name in tables {
+        let rows = storage::count_rows(conn, &format!("\"{}\"", name.replace('"', "\"\"")))?;
+        stats.tables.push(TableStats { name, rows });
+    }
+
+    let mut largest: Vec<NoteSize> = Vec::new();
+    let mut stmt = conn
+        .prepare("SELECT id, title, content FROM notes")
+        .map_err(|e| format!("读取笔记失败: {}", e))?;
+    let mut rows = stmt.query([]).map_err(|e| format!("读取笔记失败: {}", e))?;
+    while let Some(row) = rows.next().map_err(|e| format!("读取笔记失败: {}", e))? {
+        let id: i64 = row.get(0).map_err(|e| format!("读取笔记失败: {}", e))?;
+        let title: String = row.get(1).map_err(|e| format!("读取笔记失败: {}", e))?;
+        let content: String = row.get(2).map_err(|e| format!("读取笔记失败: {}", e))?;
+
+        let mut image_bytes = 0u64;
+        for image in data_url_regex().find_iter(&content) {
+            image_bytes += image.len() as u64;
+            stats.image_count += 1;
+        }
+        stats.image_bytes += image_bytes;
+        stats.note_content_bytes += (title.len() + content.len()) as u64;
+        largest.push(NoteSize {
+            id,
+            title,
+            content_bytes: content.len() as u64,
+            image_bytes,
+        });
+    }
+    largest.sort_by_key(|n| std::cmp::Reverse(n.content_bytes));
+    largest.truncate(LARGEST_NOTES);
+    stats.largest_notes = largest;
+
+    stats.chat_content_bytes = conn
+        .query_row(
+            "SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) FROM chat_messages",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|n| n as u64)
+        .map_err(|e| format!("统计聊天消息大小失败: {}", e))?;
+
+    let db_bytes = stats.page_count * stats.page_size;
+    if db_bytes > 0 {
+        stats.image_share = (stats.image_bytes as f64 / db_bytes as f64).min(1.0);
+    }
+    Ok(stats)
+}
+
+/// 执行一个维护步骤并记录前后的文件大小
+fn run_step(
+    conn: &Connection,
+    db_path: &Path,
+    name: &str,
+    sql: &str,
+) -> Result<MaintenanceStep, String> {
+    let total_size = || file_size(db_path) + file_size(&wal_path(db_path));
+    let bytes_before = total_size();
+    let started = Instant::now();
+    conn.execute_batch(sql)
+        .map_err(|e| format!("执行 {} 失败: {}", name, e))?;
+    // WAL 模式下新内容先写入 -wal，检查点后主文件才会收缩
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+        .map_err(|e| format!("WAL 检查点失败: {}", e))?;
+    let bytes_after = total_size();
+
+    Ok(MaintenanceStep {
+        name: name.to_string(),
+        bytes_before,
+        bytes_after,
+        bytes_reclaimed: bytes_before as i64 - bytes_after as i64,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// 依次执行 VACUUM、ANALYZE 和 PRAGMA optimize
+pub fn optimize_database(conn: &Connection, db_path: &Path) -> Result<MaintenanceReport, String> {
+    let mut report = MaintenanceReport::default();
+    for (name, sql) in [
+        ("vacuum", "VACUUM"),
+        ("analyze", "ANALYZE"),
+        ("optimize", "PRAGMA optimize"),
+    ] {
+        let step = run_step(conn, db_path, name, sql)?;
+        report.bytes_reclaimed += step.bytes_reclaimed;
+        report.steps.push(step);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn reports_content_and_image_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        let conn = setup(&db_path);
+        let image = format!("data:image/png;base64,{}", "A".repeat(1000));
+        conn.execute(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES ('图片', ?1, '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z')",
+            [format!("![截图]({})\n\n![截图]({})", image, image)],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES ('文字', '你好', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (2, 'user', '总结', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+
+        let stats = storage_stats(&conn, &db_path).unwrap();
+        let rows: Vec<(&str, u64)> = stats
+            .tables
+            .iter()
+            .map(|t| (t.name.as_str(), t.rows))
+            .collect();
+        assert!(rows.contains(&("notes", 2)));
+        assert!(rows.contains(&("chat_messages", 1)));
+        assert_eq!(stats.image_count, 2);
+        assert_eq!(stats.image_bytes, 2 * image.len() as u64);
+        assert_eq!(stats.chat_content_bytes, "总结".len() as u64);
+        assert_eq!(stats.largest_notes[0].title, "图片");
+        assert_eq!(stats.largest_notes[0].image_bytes, stats.image_bytes);
+        assert!(stats.wal_size > 0);
+        assert!(stats.image_share > 0.0 && stats.image_share <= 1.0);
+    }
+
+    #[test]
+    fn vacuum_reclaims_deleted_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("jdnotes.db");
+        let conn = setup(&db_path);
+        let big = "内容".repeat(50_000);
+        for _ in 0..10 {
+            conn.execute(
+                "INSERT INTO notes (title, content, created_at, updated_at)
+                 VALUES ('大笔记', ?1, '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z')",
+                [&big],
+            )
+            .unwrap();
+        }
+        conn.execute_batch("DELETE FROM notes; PRAGMA wal_checkpoint(TRUNCATE);")
+            .unwrap();
+        assert!(storage_stats(&conn, &db_path).unwrap().freelist_count > 0);
+
+        let report = optimize_database(&conn, &db_path).unwrap();
+        let names: Vec<&str> = report.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["vacuum", "analyze", "optimize"]);
+        assert!(report.steps[0].bytes_reclaimed > 1_000_000);
+        assert_eq!(storage_stats(&conn, &db_path).unwrap().freelist_count, 0);
+    }
+}
@@ -0,0 +1,762 @@
+//! Markdown 文件夹导入导出
+//!
+//! 说明：每篇笔记导出为独立的 `.md` 文件，文件头部为 YAML front matter，
+//! 便于用 git 或其他编辑器（Obsidian、VS Code 等）管理笔记；
+//! 导入时兼容 Obsidian 的 YAML front matter、Logseq 的 `key:: value` 属性和行内 `#标签`
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use rusqlite::Connection;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::conflict;
+use crate::models::{
+    ChatMessage, FileIssue, MarkdownExportOptions, MarkdownExportReport, MarkdownImportEntry,
+    MarkdownImportOptions, MarkdownImportReport, Note,
+};
+use crate::storage::{normalize_timestamp, to_iso};
+
+/// 文件名（不含扩展名）最大字符数
+const MAX_FILE_STEM_CHARS: usize = 100;
+
+/// AI 对话记录文件名（不含 `.md`）的后缀，导入时跳过这类文件
+const CHAT_STEM_SUFFIX: &str = ".chat";
+
+/// Windows 保留的设备名
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 笔记 front matter
+#[derive(Serialize)]
+struct FrontMatter<'a> {
+    title: &'a str,
+    tags: &'a [String],
+    is_favorite: bool,
+    created_at: &'a str,
+    updated_at: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reminder_date: Option<&'a str>,
+}
+
+/// 将标题转换为各平台都可用的文件名（不含扩展名）
+pub fn sanitize_file_name(title: &str) -> String {
+    let replaced: String = title
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_FILE_STEM_CHARS)
+        .collect();
+
+    // Windows 不允许文件名以空格或点结尾
+    let trimmed = replaced.trim().trim_end_matches('.').trim_end();
+    if trimmed.is_empty() {
+        return "无标题".to_string();
+    }
+
+    let upper = trimmed.to_uppercase();
+    let device = upper.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.contains(&device) {
+        return format!("_{}", trimmed);
+    }
+
+    trimmed.to_string()
+}
+
+/// 为重复的文件名追加序号，比较时忽略大小写（Windows / macOS 文件系统不区分大小写）
+pub fn unique_file_stem(stem: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = stem.to_string();
+    let mut counter = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({})", stem, counter);
+        counter += 1;
+    }
+    candidate
+}
+
+/// 生成单篇笔记的 Markdown 文件内容
+pub fn render_note(note: &Note) -> Result<String, String> {
+    let front_matter = FrontMatter {
+        title: &note.title,
+        tags: &note.tags,
+        is_favorite: note.is_favorite != 0,
+        created_at: &note.created_at,
+        updated_at: &note.updated_at,
+        reminder_date: note.reminder_date.as_deref(),
+    };
+    let yaml = serde_yaml::to_string(&front_matter)
+        .map_err(|e| format!("生成 front matter 失败: {}", e))?;
+
+    let mut content = format!("---\n{}---\n\n", yaml);
+    content.push_str(&note.content);
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// 生成笔记 AI 对话记录的 Markdown 内容
+pub fn render_chat(note: &Note, messages: &[&ChatMessage]) -> String {
+    let mut content = format!("# {} · AI 对话\n", note.title);
+    for message in messages {
+        let speaker = if message.role == "user" {
+            "用户"
+        } else {
+            "AI"
+        };
+        content.push_str(&format!(
+            "\n## {} · {}\n\n{}\n",
+            speaker,
+            message.timestamp,
+            message.content.trim_end()
+        ));
+    }
+    content
+}
+
+/// 导出时生成的单个 Markdown 文件
+pub struct RenderedFile {
+    /// 文件名（含扩展名）
+    pub name: String,
+    pub content: String,
+    /// 是否为 AI 对话记录
+    pub is_chat: bool,
+}
+
+/// 为笔记生成导出文件（文件名去重，可选附带 AI 对话记录）
+pub fn render_files(
+    notes: &[Note],
+    messages: &[ChatMessage],
+    options: &MarkdownExportOptions,
+) -> Result<Vec<RenderedFile>, String> {
+    let mut messages_by_note: HashMap<i64, Vec<&ChatMessage>> = HashMap::new();
+    if options.include_chat {
+        for message in messages {
+            messages_by_note
+                .entry(message.note_id)
+                .or_default()
+                .push(message);
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut used = HashSet::new();
+    for note in notes {
+        // 以 .chat 结尾的标题会与对话记录文件重名，且重新导入时会被当作对话记录跳过
+        let mut stem = sanitize_file_name(&note.title);
+        if stem.to_lowercase().ends_with(CHAT_STEM_SUFFIX) {
+            stem.push('_');
+        }
+        let stem = unique_file_stem(&stem, &mut used);
+        files.push(RenderedFile {
+            name: format!("{}.md", stem),
+            content: render_note(note)?,
+            is_chat: false,
+        });
+
+        if let Some(chat) = note.id.and_then(|id| messages_by_note.get(&id)) {
+            let chat_stem = format!("{}{}", stem, CHAT_STEM_SUFFIX);
+            used.insert(chat_stem.to_lowercase());
+            files.push(RenderedFile {
+                name: format!("{}.md", chat_stem),
+                content: render_chat(note, chat),
+                is_chat: true,
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// 将笔记导出到指定目录，每篇笔记一个 `.md` 文件
+/// 同名文件会被覆盖，便于重复导出到同一个 git 仓库
+pub fn export_to_dir(
+    notes: &[Note],
+    messages: &[ChatMessage],
+    output_dir: &Path,
+    options: &MarkdownExportOptions,
+) -> Result<MarkdownExportReport, String> {
+    fs::create_dir_all(output_dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+
+    let mut report = MarkdownExportReport {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+
+    for file in render_files(notes, messages, options)? {
+        let path = output_dir.join(&file.name);
+        fs::write(&path, file.content)
+            .map_err(|e| format!("写入 {} 失败: {}", path.to_string_lossy(), e))?;
+        if file.is_chat {
+            report.chat_files_exported += 1;
+        } else {
+            report.notes_exported += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+// ============= 导入 =============
+
+/// front matter 中可表示创建时间的键
+const CREATED_KEYS: [&str; 4] = ["created_at", "created", "date", "creation_date"];
+
+/// front matter 中可表示更新时间的键
+const UPDATED_KEYS: [&str; 4] = ["updated_at", "updated", "modified", "lastmod"];
+
+/// 单个 Markdown 文件的来源信息
+pub struct MarkdownSource<'a> {
+    /// 相对于导入目录的路径
+    pub rel_path: &'a Path,
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    /// 由子文件夹名称生成的标签
+    pub folder_tags: Vec<String>,
+}
+
+fn inline_tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[\s(（])#([\p{L}\p{N}_/-]+)").unwrap())
+}
+
+fn heading_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^#{1,6}\s+(.+?)\s*#*\s*$").unwrap())
+}
+
+fn inline_code_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"`[^`]*`").unwrap())
+}
+
+fn logseq_property_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^([A-Za-z][\w-]*):: ?(.*)$").unwrap())
+}
+
+/// 拆分 YAML front matter 与正文
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let first_line_end = match text.find('\n') {
+        Some(end) if text[..end].trim_end() == "---" => end + 1,
+        _ => return (None, text),
+    };
+
+    let mut offset = first_line_end;
+    for line in text[first_line_end..].split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let yaml = &text[first_line_end..offset];
+            let body = &text[offset + line.len()..];
+            return (Some(yaml), body.trim_start_matches(['\r', '\n']));
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// 拆分 Logseq 页面开头的 `key:: value` 属性与正文
+fn split_logseq_properties(text: &str) -> (serde_yaml::Mapping, &str) {
+    let mut properties = serde_yaml::Mapping::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let Some(caps) = logseq_property_regex().captures(line.trim_end()) else {
+            break;
+        };
+        properties.insert(
+            serde_yaml::Value::String(caps[1].to_lowercase()),
+            serde_yaml::Value::String(caps[2].trim().to_string()),
+        );
+        offset += line.len();
+    }
+    (properties, text[offset..].trim_start_matches(['\r', '\n']))
+}
+
+/// 规范化单个标签：去掉 `#`、`[[ ]]` 与首尾空白
+fn clean_tag(raw: &str) -> Option<String> {
+    let tag = raw
+        .trim()
+        .trim_start_matches('#')
+        .trim_start_matches("[[")
+        .trim_end_matches("]]")
+        .trim();
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// 读取 front matter 中的标签，兼容列表与逗号/空格分隔的字符串
+fn front_matter_tags(value: &serde_yaml::Value) -> Vec<String> {
+    match value {
+        serde_yaml::Value::Sequence(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                serde_yaml::Value::String(s) => clean_tag(s),
+                serde_yaml::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        serde_yaml::Value::String(s) => s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(clean_tag)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn front_matter_time(front_matter: &serde_yaml::Mapping, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| front_matter.get(*key))
+        .filter_map(|value| serde_json::to_value(value).ok())
+        .find_map(|value| normalize_timestamp(&value))
+}
+
+/// 扫描正文（跳过代码块与行内代码），返回第一个标题和行内标签
+fn scan_body(body: &str) -> (Option<String>, Vec<String>) {
+    let mut heading = None;
+    let mut tags = Vec::new();
+    let mut in_code_block = false;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if heading.is_none() {
+            if let Some(caps) = heading_regex().captures(trimmed) {
+                heading = Some(caps[1].to_string());
+                continue;
+            }
+        }
+        let without_code = inline_code_regex().replace_all(line, "");
+        for caps in inline_tag_regex().captures_iter(&without_code) {
+            let tag = caps[1].trim_end_matches('/');
+            // 与 Obsidian 一致，纯数字不视为标签
+            if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+
+    (heading, tags)
+}
+
+/// 将单个 Markdown 文件内容解析为笔记
+/// 标题优先级：front matter → 第一个标题 → 文件名
+/// 时间优先级：front matter → 文件创建/修改时间
+pub fn parse_markdown(text: &str, source: &MarkdownSource) -> Result<Note, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let (front_matter, body) = match split_front_matter(text) {
+        (Some(yaml), body) => {
+            let mapping = if yaml.trim().is_empty() {
+                serde_yaml::Mapping::new()
+            } else {
+                serde_yaml::from_str::<serde_yaml::Mapping>(yaml)
+                    .map_err(|e| format!("front matter 解析失败: {}", e))?
+            };
+            (mapping, body)
+        }
+        (None, body) => split_logseq_properties(body),
+    };
+
+    let (heading, inline_tags) = scan_body(body);
+
+    let title = front_matter
+        .get("title")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or(heading)
+        .unwrap_or_else(|| {
+            source
+                .rel_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+    let mut tags = Vec::new();
+    for tag in front_matter
+        .get("tags")
+        .or_else(|| front_matter.get("tag"))
+        .map(front_matter_tags)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(inline_tags)
+        .chain(source.folder_tags.iter().cloned())
+    {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let created_at = front_matter_time(&front_matter, &CREATED_KEYS)
+        .unwrap_or_else(|| to_iso(DateTime::<Utc>::from(source.created)));
+    let updated_at = front_matter_time(&front_matter, &UPDATED_KEYS)
+        .unwrap_or_else(|| to_iso(DateTime::<Utc>::from(source.modified)));
+    let reminder_date = front_matter_time(&front_matter, &["reminder_date"]);
+    let is_favorite = front_matter
+        .get("is_favorite")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    Ok(Note {
+        id: None,
+        title,
+        content: body.to_string(),
+        tags,
+        is_favorite: is_favorite as i32,
+        is_deleted: 0,
+        created_at,
+        updated_at,
+        reminder_enabled: reminder_date.is_some() as i32,
+        reminder_date,
+    })
+}
+
+/// 是否为可导入的 Markdown 文件
+pub fn is_markdown_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    // 跳过导出时生成的 AI 对话记录
+    (name.ends_with(".md") || name.ends_with(".markdown"))
+        && !name.ends_with(&format!("{}.md", CHAT_STEM_SUFFIX))
+}
+
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// 递归扫描目录中的 Markdown 文件（跳过 .obsidian、.git 等隐藏目录）
+pub fn scan_dir(
+    root: &Path,
+    options: &MarkdownImportOptions,
+) -> Result<(Vec<Note>, MarkdownImportReport), String> {
+    if !root.is_dir() {
+        return Err(format!("目录不存在: {}", root.to_string_lossy()));
+    }
+
+    let mut report = MarkdownImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    let mut notes = Vec::new();
+
+    let mut entries: Vec<_> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e))
+        .collect();
+    entries.sort_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a.path().cmp(b.path()),
+        _ => std::cmp::Ordering::Equal,
+    });
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.failed.push(FileIssue {
+                    path: e
+                        .path()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() || !is_markdown_file(entry.path()) {
+            continue;
+        }
+
+        let rel_path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let display_path = rel_path.to_string_lossy().replace('\\', "/");
+        let parsed = fs::read_to_string(entry.path())
+            .map_err(|e| format!("读取文件失败: {}", e))
+            .and_then(|text| {
+                let metadata = entry
+                    .metadata()
+                    .map_err(|e| format!("读取文件信息失败: {}", e))?;
+                let modified = metadata.modified().unwrap_or(SystemTime::now());
+                let folder_tags = if options.folder_tags {
+                    rel_path
+                        .parent()
+                        .into_iter()
+                        .flat_map(|p| p.components())
+                        .filter_map(|c| clean_tag(&c.as_os_str().to_string_lossy()))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let source = MarkdownSource {
+                    rel_path,
+                    created: metadata.created().unwrap_or(modified),
+                    modified,
+                    folder_tags,
+                };
+                parse_markdown(&text, &source)
+            });
+
+        match parsed {
+            Ok(note) => {
+                report.notes.push(MarkdownImportEntry {
+                    path: display_path,
+                    title: note.title.clone(),
+                    tags: note.tags.clone(),
+                    created_at: note.created_at.clone(),
+                    updated_at: note.updated_at.clone(),
+                });
+                notes.push(note);
+            }
+            Err(reason) => report.failed.push(FileIssue {
+                path: display_path,
+                reason,
+            }),
+        }
+    }
+
+    Ok((notes, report))
+}
+
+/// 导入 Markdown 文件夹，按冲突策略在同一事务中写入；dry_run 时仅返回预览和计划
+pub fn import_dir(
+    conn: &mut Connection,
+    root: &Path,
+    options: &MarkdownImportOptions,
+) -> Result<MarkdownImportReport, String> {
+    let (notes, mut report) = scan_dir(root, options)?;
+    report.actions = conflict::plan(conn, &notes, &options.conflict)?;
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    let (inserted, updated) = conflict::import_notes(conn, &notes, &report.actions)?;
+    report.notes_inserted = inserted;
+    report.notes_updated = updated;
+    report.committed = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConflictStrategy, NoteAction};
+
+    fn note(id: i64, title: &str) -> Note {
+        Note {
+            id: Some(id),
+            title: title.to_string(),
+            content: "# 标题\n\n正文".to_string(),
+            tags: vec!["工作".to_string()],
+            is_favorite: 1,
+            is_deleted: 0,
+            created_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: "2024-01-02T00:00:00.000Z".to_string(),
+            reminder_date: None,
+            reminder_enabled: 0,
+        }
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize_file_name("a/b\\c:d*e?"), "a_b_c_d_e_");
+        assert_eq!(sanitize_file_name("  结尾的点.. "), "结尾的点");
+        assert_eq!(sanitize_file_name(""), "无标题");
+        assert_eq!(sanitize_file_name("..."), "无标题");
+        assert_eq!(sanitize_file_name("con"), "_con");
+        assert_eq!(sanitize_file_name("LPT1.txt"), "_LPT1.txt");
+        assert_eq!(sanitize_file_name(&"长".repeat(300)).chars().count(), 100);
+    }
+
+    #[test]
+    fn duplicate_names_do_not_collide() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_stem("笔记", &mut used), "笔记");
+        assert_eq!(unique_file_stem("笔记", &mut used), "笔记 (2)");
+        assert_eq!(unique_file_stem("Note", &mut used), "Note");
+        assert_eq!(unique_file_stem("note", &mut used), "note (2)");
+    }
+
+    #[test]
+    fn renders_front_matter() {
+        let mut n = note(1, "周会: 记录");
+        n.reminder_date = Some("2024-01-03T09:00:00.000Z".to_string());
+        let rendered = render_note(&n).unwrap();
+        assert!(rendered.starts_with("---\n"));
+
+        let yaml = rendered.split("---\n").nth(1).unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(value["title"].as_str(), Some("周会: 记录"));
+        assert_eq!(value["tags"][0].as_str(), Some("工作"));
+        assert_eq!(value["is_favorite"].as_bool(), Some(true));
+        assert_eq!(
+            value["reminder_date"].as_str(),
+            Some("2024-01-03T09:00:00.000Z")
+        );
+        assert!(rendered.ends_with("# 标题\n\n正文\n"));
+    }
+
+    #[test]
+    fn exports_notes_and_chat_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = vec![note(1, "同名"), note(2, "同名")];
+        let messages = vec![ChatMessage {
+            id: Some(1),
+            note_id: 2,
+            role: "assistant".to_string(),
+            content: "回答".to_string(),
+            timestamp: "2024-01-02T00:00:00.000Z".to_string(),
+        }];
+
+        let options = MarkdownExportOptions { include_chat: true };
+        let report = export_to_dir(&notes, &messages, dir.path(), &options).unwrap();
+        assert_eq!(report.notes_exported, 2);
+        assert_eq!(report.chat_files_exported, 1);
+        assert!(dir.path().join("同名.md").exists());
+        assert!(dir.path().join("同名 (2).md").exists());
+
+        let chat = fs::read_to_string(dir.path().join("同名 (2).chat.md")).unwrap();
+        assert!(chat.contains("## AI · 2024-01-02T00:00:00.000Z"));
+        assert!(chat.contains("回答"));
+    }
+
+    #[test]
+    fn titles_ending_in_chat_do_not_collide_with_sidecars() {
+        let notes = vec![note(1, "周会"), note(2, "周会.chat"), note(3, "周会.CHAT")];
+        let messages = vec![ChatMessage {
+            id: Some(1),
+            note_id: 1,
+            role: "user".to_string(),
+            content: "问题".to_string(),
+            timestamp: "2024-01-02T00:00:00.000Z".to_string(),
+        }];
+
+        let options = MarkdownExportOptions { include_chat: true };
+        let files = render_files(&notes, &messages, &options).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "周会.md",
+                "周会.chat.md",
+                "周会.chat_.md",
+                "周会.CHAT_ (2).md"
+            ]
+        );
+        let importable: Vec<bool> = files
+            .iter()
+            .map(|f| is_markdown_file(Path::new(&f.name)))
+            .collect();
+        assert_eq!(importable, vec![true, false, true, true]);
+    }
+
+    fn source(path: &str) -> MarkdownSource<'_> {
+        MarkdownSource {
+            rel_path: Path::new(path),
+            created: SystemTime::UNIX_EPOCH,
+            modified: SystemTime::UNIX_EPOCH,
+            folder_tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_obsidian_front_matter_and_inline_tags() {
+        let text = "---\ntitle: 周报\ntags: [工作, \"#复盘\"]\ncreated: 2024-01-01\nupdated_at: 2024-01-02T00:00:00Z\n---\n\n# 标题\n\n内容 #项目/A 与 #2024\n\n```\n#不是标签\n```\n`#也不是`\n";
+        let note = parse_markdown(text, &source("a/b.md")).unwrap();
+        assert_eq!(note.title, "周报");
+        assert_eq!(note.tags, vec!["工作", "复盘", "项目/A"]);
+        assert_eq!(note.created_at, "2024-01-01T00:00:00.000Z");
+        assert_eq!(note.updated_at, "2024-01-02T00:00:00.000Z");
+        assert!(note.content.starts_with("# 标题"));
+    }
+
+    #[test]
+    fn title_falls_back_to_heading_then_file_name() {
+        let note = parse_markdown("正文\n## 二级标题\n", &source("x/文件名.md")).unwrap();
+        assert_eq!(note.title, "二级标题");
+        assert_eq!(note.created_at, "1970-01-01T00:00:00.000Z");
+
+        let note = parse_markdown("只有正文", &source("x/文件名.md")).unwrap();
+        assert_eq!(note.title, "文件名");
+    }
+
+    #[test]
+    fn parses_logseq_properties() {
+        let text = "title:: 日志\ntags:: [[读书]], 笔记\n\n- 第一条\n";
+        let note = parse_markdown(text, &source("pages/log.md")).unwrap();
+        assert_eq!(note.title, "日志");
+        assert_eq!(note.tags, vec!["读书", "笔记"]);
+        assert_eq!(note.content, "- 第一条\n");
+    }
+
+    #[test]
+    fn round_trips_exported_notes() {
+        let mut original = note(1, "往返");
+        original.reminder_date = Some("2024-01-03T09:00:00.000Z".to_string());
+        let parsed = parse_markdown(&render_note(&original).unwrap(), &source("往返.md")).unwrap();
+        assert_eq!(parsed.title, original.title);
+        assert_eq!(parsed.tags, original.tags);
+        assert_eq!(parsed.is_favorite, 1);
+        assert_eq!(parsed.created_at, original.created_at);
+        assert_eq!(parsed.updated_at, original.updated_at);
+        assert_eq!(parsed.reminder_date, original.reminder_date);
+        assert_eq!(parsed.reminder_enabled, 1);
+        assert_eq!(parsed.content.trim_end(), original.content);
+    }
+
+    #[test]
+    fn imports_folder_with_dry_run_and_folder_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("工作/项目")).unwrap();
+        fs::create_dir_all(dir.path().join(".obsidian")).unwrap();
+        fs::write(dir.path().join("根.md"), "# 根笔记").unwrap();
+        fs::write(dir.path().join("工作/项目/任务.md"), "内容").unwrap();
+        fs::write(dir.path().join("工作/项目/任务.chat.md"), "对话").unwrap();
+        fs::write(dir.path().join(".obsidian/workspace.md"), "忽略").unwrap();
+        fs::write(dir.path().join("坏.md"), "---\ntitle: [\n---\n").unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+
+        let mut options = MarkdownImportOptions {
+            folder_tags: true,
+            dry_run: true,
+            ..Default::default()
+        };
+        let preview = import_dir(&mut conn, dir.path(), &options).unwrap();
+        assert_eq!(preview.notes.len(), 2);
+        assert_eq!(preview.failed.len(), 1);
+        assert!(!preview.committed);
+        assert!(crate::storage::read_notes(&conn).unwrap().is_empty());
+
+        options.dry_run = false;
+        let report = import_dir(&mut conn, dir.path(), &options).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 2);
+
+        let notes = crate::storage::read_notes(&conn).unwrap();
+        let task = notes.iter().find(|n| n.title == "任务").unwrap();
+        assert_eq!(task.tags, vec!["工作", "项目"]);
+
+        // 再次导入时按标题 + 创建时间匹配到已有笔记并跳过
+        options.conflict.strategy = ConflictStrategy::Skip;
+        let report = import_dir(&mut conn, dir.path(), &options).unwrap();
+        assert_eq!(report.notes_inserted, 0);
+        assert!(report.actions.iter().all(|a| a.action == NoteAction::Skip));
+    }
+}
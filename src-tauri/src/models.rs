@@ -39,6 +39,528 @@ pub struct ExportData {
     pub chat_messages: Vec<ChatMessage>,
 }
 
+/// 导入笔记与已有笔记冲突时的处理策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// 跳过导入的笔记
+    Skip,
+    /// 用导入的笔记覆盖已有笔记
+    Overwrite,
+    /// 两者都保留（插入为新笔记）
+    #[default]
+    KeepBoth,
+    /// 保留 updated_at 较新的版本
+    Newer,
+}
+
+/// 判断导入笔记与已有笔记是否为同一篇的方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchBy {
+    /// 标题 + 创建时间相同
+    #[default]
+    TitleCreatedAt,
+    /// 正文内容哈希相同
+    ContentHash,
+}
+
+/// 冲突处理选项
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ConflictOptions {
+    #[serde(default)]
+    pub strategy: ConflictStrategy,
+    #[serde(default)]
+    pub match_by: MatchBy,
+}
+
+/// 对单篇导入笔记执行的操作
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteAction {
+    /// 没有匹配的已有笔记，直接插入
+    Insert,
+    /// 已有匹配笔记，跳过
+    Skip,
+    /// 覆盖匹配的已有笔记
+    Overwrite,
+    /// 已有匹配笔记，仍作为新笔记插入
+    KeepBoth,
+}
+
+/// 导入计划中的单篇笔记
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlannedAction {
+    /// 笔记在导入数据中的下标
+    pub index: usize,
+    pub title: String,
+    pub action: NoteAction,
+    /// 匹配到的已有笔记 ID
+    pub existing_id: Option<i64>,
+}
+
+/// JSON 导入选项
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ImportOptions {
+    #[serde(default, flatten)]
+    pub conflict: ConflictOptions,
+    /// 仅返回导入计划，不写入数据库
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// 导入过程中被跳过或失败的记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportIssue {
+    /// 记录类型："note" 或 "chat_message"
+    pub kind: String,
+    /// 记录在导入数据中的下标
+    pub index: usize,
+    /// 记录在导入数据中的原始 ID
+    pub source_id: Option<i64>,
+    pub reason: String,
+}
+
+/// 导出文件校验问题
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationIssue {
+    /// 问题所在位置，如 `notes[3].created_at`
+    pub path: String,
+    pub message: String,
+}
+
+/// 导出文件校验报告
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ValidationReport {
+    /// 文件中声明的格式版本
+    pub source_version: String,
+    /// 升级后的格式版本
+    pub target_version: String,
+    /// 依次执行过的升级步骤，如 `1.0 -> 2.0`
+    pub upgrades: Vec<String>,
+    /// 存在错误时不会导入任何数据
+    pub errors: Vec<ValidationIssue>,
+    pub warnings: Vec<ValidationIssue>,
+}
+
+/// 导入结果报告
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// 是否已提交事务（有任何失败记录时整体回滚）
+    pub committed: bool,
+    pub notes_inserted: usize,
+    pub notes_updated: usize,
+    pub messages_inserted: usize,
+    /// 每篇笔记的处理计划
+    pub actions: Vec<PlannedAction>,
+    pub skipped: Vec<ImportIssue>,
+    pub failed: Vec<ImportIssue>,
+    /// 导入文件的校验结果（仅 JSON 导入）
+    pub validation: Option<ValidationReport>,
+}
+
+/// 导出进度
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ExportProgress {
+    pub done: u64,
+    pub total: u64,
+}
+
+/// `export-progress` 事件的内容（带导出任务 id，前端据此区分同时进行的导出）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportProgressEvent {
+    pub export_id: String,
+    #[serde(flatten)]
+    pub progress: ExportProgress,
+}
+
+/// 导出到文件的结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ExportFileReport {
+    pub path: String,
+    pub notes_exported: u64,
+    pub messages_exported: u64,
+    pub bytes_written: u64,
+}
+
+/// Markdown 文件夹导出选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MarkdownExportOptions {
+    /// 是否为每篇笔记额外导出 AI 对话记录（`<文件名>.chat.md`）
+    #[serde(default)]
+    pub include_chat: bool,
+}
+
+/// Markdown 文件夹导出结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MarkdownExportReport {
+    pub output_dir: String,
+    pub notes_exported: usize,
+    pub chat_files_exported: usize,
+}
+
+/// Markdown 文件夹导入选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MarkdownImportOptions {
+    /// 是否将子文件夹名称作为标签
+    #[serde(default)]
+    pub folder_tags: bool,
+    /// 仅预览，不写入数据库
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default, flatten)]
+    pub conflict: ConflictOptions,
+}
+
+/// 导入时无法读取或解析的文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileIssue {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Markdown 文件解析结果预览
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MarkdownImportEntry {
+    /// 相对于导入目录的路径
+    pub path: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Markdown 文件夹导入结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MarkdownImportReport {
+    pub dry_run: bool,
+    pub committed: bool,
+    pub notes_inserted: usize,
+    pub notes_updated: usize,
+    pub notes: Vec<MarkdownImportEntry>,
+    /// 每篇笔记的处理计划（下标与 notes 对应）
+    pub actions: Vec<PlannedAction>,
+    pub failed: Vec<FileIssue>,
+}
+
+/// ZIP 归档中笔记的存储格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    /// `notes.json`（ExportData 格式，包含聊天记录和废纸篓中的笔记）
+    #[default]
+    Json,
+    /// 每篇笔记一个 `.md` 文件（不含废纸篓中的笔记）
+    Markdown,
+}
+
+/// ZIP 归档导出选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ArchiveExportOptions {
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    /// Markdown 格式时是否额外导出 AI 对话记录
+    #[serde(default)]
+    pub include_chat: bool,
+}
+
+/// ZIP 归档导出结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ArchiveExportReport {
+    pub path: String,
+    pub notes_exported: usize,
+    /// 提取到 `assets/` 的图片数（相同内容只计一次）
+    pub assets_exported: usize,
+    pub bytes_written: u64,
+}
+
+/// 标签筛选方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// 包含任一标签
+    #[default]
+    Any,
+    /// 包含全部标签
+    All,
+}
+
+/// 废纸篓中笔记的筛选方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletedFilter {
+    /// 不导出废纸篓中的笔记
+    #[default]
+    Exclude,
+    /// 同时导出废纸篓中的笔记
+    Include,
+    /// 只导出废纸篓中的笔记
+    Only,
+}
+
+/// 导出笔记的筛选条件（各条件同时满足，未设置的条件不限）
+/// 时间范围为 `[from, to)`，可使用 ISO 8601 时间或 `YYYY-MM-DD` 日期
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExportFilter {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tag_match: TagMatch,
+    #[serde(default)]
+    pub created_from: Option<String>,
+    #[serde(default)]
+    pub created_to: Option<String>,
+    #[serde(default)]
+    pub updated_from: Option<String>,
+    #[serde(default)]
+    pub updated_to: Option<String>,
+    /// 为 true 时只导出收藏的笔记，为 false 时只导出未收藏的笔记
+    #[serde(default)]
+    pub is_favorite: Option<bool>,
+    #[serde(default)]
+    pub deleted: DeletedFilter,
+    /// 仅导出指定 ID 的笔记
+    #[serde(default)]
+    pub note_ids: Vec<i64>,
+}
+
+/// 静态 HTML 网站导出选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HtmlExportOptions {
+    /// 网站标题，默认为「JD Notes」
+    #[serde(default)]
+    pub site_title: Option<String>,
+    /// 保留笔记中的原始 HTML（默认转义为文本，避免发布的页面执行导入内容中的脚本）
+    #[serde(default)]
+    pub allow_raw_html: bool,
+}
+
+/// 静态 HTML 网站导出结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct HtmlExportReport {
+    pub output_dir: String,
+    pub notes_exported: usize,
+    pub tag_pages: usize,
+    pub files_written: usize,
+}
+
+/// PDF 导出选项
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PdfExportOptions {
+    /// 文档标题，默认为单篇笔记的标题或「JD Notes」
+    #[serde(default)]
+    pub title: Option<String>,
+    /// 是否在开头生成目录（笔记标题和一至三级标题）
+    #[serde(default)]
+    pub table_of_contents: bool,
+    /// 是否在页脚显示页码
+    #[serde(default)]
+    pub page_numbers: bool,
+    /// 嵌入的 TrueType 字体（.ttf / .ttc），为空时自动查找系统中文字体
+    #[serde(default)]
+    pub font_path: Option<String>,
+}
+
+/// PDF 导出结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PdfExportReport {
+    pub path: String,
+    pub notes_exported: usize,
+    pub pages: usize,
+    /// 实际使用的字体文件
+    pub font_path: String,
+    /// 字体中缺少、未能显示的字符数
+    pub missing_glyphs: usize,
+    pub bytes_written: u64,
+}
+
+/// 自动备份设置（保存在 config.json 中）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// 备份根目录，为空时使用应用数据目录下的 `backups`；每个笔记库的备份保存在以笔记库 ID 命名的子目录中
+    pub directory: Option<String>,
+    /// 启动时备份
+    pub on_startup: bool,
+    /// 退出时备份
+    pub on_exit: bool,
+    /// 运行期间每隔多少小时备份一次，0 表示不定时备份
+    pub interval_hours: u32,
+    /// 保留最近多少个小时各一份备份
+    pub keep_hourly: usize,
+    /// 保留最近多少天各一份备份
+    pub keep_daily: usize,
+    /// 保留最近多少周各一份备份
+    pub keep_weekly: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: None,
+            on_startup: true,
+            on_exit: true,
+            interval_hours: 6,
+            keep_hourly: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/// 备份文件信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    /// 备份时间（ISO 8601）
+    pub created_at: String,
+    pub size: u64,
+    /// 快照标记（如恢复前的 `pre-restore`），自动和手动备份为空；快照不会被保留策略清理
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// 从备份恢复的结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RestoreReport {
+    pub backup_path: String,
+    /// 恢复前为当前数据库生成的快照（当前数据库不存在时为空）
+    pub snapshot_path: Option<String>,
+    /// 备份中记录的数据库结构版本（0 表示没有迁移记录）
+    pub schema_version: i64,
+    pub notes: u64,
+    pub chat_messages: u64,
+}
+
+/// 数据库检查发现的问题
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthIssue {
+    /// 问题类型：integrity / foreign_key / orphaned_message / invalid_tags / invalid_date
+    pub kind: String,
+    pub table: String,
+    pub row_id: Option<i64>,
+    pub column: Option<String>,
+    /// 出问题的原始值
+    pub value: Option<String>,
+    pub message: String,
+    /// 能否由 repair_database 自动修复
+    pub fixable: bool,
+}
+
+/// 数据库检查报告
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub notes_checked: u64,
+    pub messages_checked: u64,
+    pub issues: Vec<HealthIssue>,
+}
+
+/// 数据库修复结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RepairReport {
+    /// 修复前为数据库生成的备份
+    pub snapshot_path: Option<String>,
+    /// 删除的孤立聊天消息数
+    pub messages_deleted: usize,
+    pub tags_fixed: usize,
+    pub dates_fixed: usize,
+    /// 是否重建了索引
+    pub reindexed: bool,
+    /// 修复后重新检查的结果
+    pub after: HealthReport,
+}
+
+/// 单张表的行数
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: u64,
+}
+
+/// 单篇笔记占用的空间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoteSize {
+    pub id: i64,
+    pub title: String,
+    /// 正文字节数（含内嵌图片）
+    pub content_bytes: u64,
+    /// 其中内嵌图片（data URL）的字节数
+    pub image_bytes: u64,
+}
+
+/// 数据库存储统计
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StorageStats {
+    pub file_size: u64,
+    /// `-wal` 文件大小（未写回主文件的内容）
+    pub wal_size: u64,
+    pub page_size: u64,
+    pub page_count: u64,
+    /// 空闲页数（VACUUM 可回收）
+    pub freelist_count: u64,
+    pub tables: Vec<TableStats>,
+    /// 笔记标题和正文的字节数
+    pub note_content_bytes: u64,
+    /// 聊天消息的字节数
+    pub chat_content_bytes: u64,
+    /// 笔记中内嵌图片（data URL）的字节数和数量
+    pub image_bytes: u64,
+    pub image_count: u64,
+    /// 内嵌图片占数据库文件的比例（0 ~ 1）
+    pub image_share: f64,
+    /// 占用空间最大的笔记（从大到小）
+    pub largest_notes: Vec<NoteSize>,
+}
+
+/// 单个维护步骤的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceStep {
+    /// vacuum / analyze / optimize
+    pub name: String,
+    /// 执行前后数据库文件与 `-wal` 文件的总大小
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// 回收的字节数（ANALYZE 写入统计信息时可能为负数）
+    pub bytes_reclaimed: i64,
+    pub duration_ms: u64,
+}
+
+/// 数据库维护结果
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MaintenanceReport {
+    pub steps: Vec<MaintenanceStep>,
+    pub bytes_reclaimed: i64,
+}
+
+/// 数据库加密状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionStatus {
+    /// 当前数据库是否已加密
+    pub encrypted: bool,
+    /// 是否可以使用（未加密，或已加密且已用密码解锁）
+    pub unlocked: bool,
+}
+
+/// 启用 / 关闭加密或修改密码的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionReport {
+    /// 操作后数据库是否加密
+    pub encrypted: bool,
+    /// 操作前生成并通过校验的备份，已转换为操作后的加密状态（新密码加密或明文），可直接恢复
+    pub backup_path: String,
+    /// 启用加密后仍是明文的备份（加密失败，或旧版本保存在备份根目录中），应手动删除
+    #[serde(default)]
+    pub plaintext_backups: Vec<String>,
+    /// 修改密码或关闭加密后仍使用原密码加密的备份（转换失败），恢复前需用原密码手动解密
+    #[serde(default)]
+    pub stale_backups: Vec<String>,
+}
+
 /// 应用配置项（用于存储数据库路径等配置）
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
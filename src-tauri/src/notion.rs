@@ -0,0 +1,636 @@
+//! Notion「Markdown & CSV」导出导入
+//!
+//! 说明：Notion 导出的 zip 中每个页面是一个 `.md` 文件，子页面放在与页面同名的文件夹中；
+//! 数据库导出为 `.csv`，数据库行对应的页面放在与 CSV 同名的文件夹中。
+//! 文件和文件夹名末尾带有 32 位十六进制的页面 ID，导入时会去掉；
+//! 页面之间的相对链接改写为 `jdnotes://note/<笔记 ID>`（编辑器中点击即可打开对应笔记），
+//! 引用的图片以 data URL 内嵌
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read, Seek};
+use std::sync::OnceLock;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use rusqlite::{params, Connection};
+use zip::ZipArchive;
+
+use crate::archive::{self, SizeLimit};
+use crate::conflict;
+use crate::models::{
+    FileIssue, MarkdownImportEntry, MarkdownImportOptions, MarkdownImportReport, Note, NoteAction,
+};
+use crate::storage::{normalize_timestamp, to_iso};
+
+/// 导入后页面链接的地址前缀（与前端 src/lib/utils.ts 中的 NOTE_LINK_PREFIX 一致）
+pub const NOTE_LINK_PREFIX: &str = "jdnotes://note/";
+
+/// 写入数据库前页面链接的占位前缀，后接页面下标
+const PAGE_LINK_PLACEHOLDER: &str = "jdnotes://notion-page/";
+
+/// 嵌套 zip 的最大展开层数（较大的 Notion 导出会再分卷打包一层）
+const MAX_ZIP_DEPTH: usize = 2;
+
+/// 作为标签的属性值最大字符数，更长的属性视为正文内容，不转换为标签
+const MAX_TAG_CHARS: usize = 50;
+
+/// 表示创建时间的数据库列
+const CREATED_COLUMNS: [&str; 4] = ["created", "created time", "created at", "创建时间"];
+
+/// 表示更新时间的数据库列
+const UPDATED_COLUMNS: [&str; 6] = [
+    "last edited time",
+    "last edited",
+    "updated",
+    "updated at",
+    "上次编辑时间",
+    "更新时间",
+];
+
+/// 值直接作为标签的数据库列（多选值以逗号分隔）
+const TAG_COLUMNS: [&str; 3] = ["tags", "tag", "标签"];
+
+/// Notion CSV 中的时间格式（随导出语言不同）
+const NOTION_TIME_FORMATS: [&str; 3] =
+    ["%B %d, %Y %I:%M %p", "%Y/%m/%d %H:%M", "%Y年%m月%d日 %H:%M"];
+
+fn notion_id_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(.*?)\s+[0-9a-f]{32}$").unwrap())
+}
+
+fn link_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(!?)\[([^\]]*)\]\(([^)\s]+)\)").unwrap())
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"jdnotes://notion-page/(\d+)").unwrap())
+}
+
+/// 去掉名称末尾的 Notion 页面 ID
+pub fn strip_notion_id(name: &str) -> &str {
+    notion_id_regex()
+        .captures(name)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str())
+        .unwrap_or(name)
+}
+
+/// 去掉路径中每一级名称的页面 ID，如 `项目 <ID>/任务 <ID>.md` → `项目/任务.md`
+pub fn clean_path(path: &str) -> String {
+    path.split('/')
+        .map(|part| match part.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() && !ext.contains(' ') => {
+                format!("{}.{}", strip_notion_id(stem), ext)
+            }
+            _ => strip_notion_id(part).to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 解析相对于 dir 的路径（处理 `.` 与 `..`）
+fn resolve_path(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in relative.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// 读取归档中的全部文件，并展开嵌套的 zip
+fn read_entries<R: Read + Seek>(
+    reader: R,
+    depth: usize,
+    limit: &mut SizeLimit,
+    entries: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), String> {
+    let mut archive =
+        ZipArchive::new(reader).map_err(|e| format!("读取 Notion 导出文件失败: {}", e))?;
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index(i)
+            .map_err(|e| format!("读取 Notion 导出文件失败: {}", e))?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = file.name().replace('\\', "/");
+        let bytes = limit.read(&name, file.size(), file)?;
+        if name.to_lowercase().ends_with(".zip") && depth < MAX_ZIP_DEPTH {
+            read_entries(Cursor::new(bytes), depth + 1, limit, entries)?;
+        } else {
+            entries.insert(name, bytes);
+        }
+    }
+    Ok(())
+}
+
+/// 解析 Notion 导出的时间，日期范围（`开始 → 结束`）取开始时间
+fn parse_notion_time(raw: &str) -> Option<String> {
+    let raw = raw.split(" → ").next().unwrap_or(raw).trim();
+    normalize_timestamp(&serde_json::json!(raw))
+        .or_else(|| {
+            NOTION_TIME_FORMATS
+                .iter()
+                .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+                .map(|dt| to_iso(dt.and_utc()))
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%B %d, %Y")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|dt| to_iso(dt.and_utc()))
+        })
+}
+
+/// 解析中的页面
+#[derive(Default)]
+struct Page {
+    /// 归档内的原始路径（含页面 ID）
+    path: String,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// 数据库 CSV 中的一行
+#[derive(Default)]
+struct Row {
+    title: String,
+    tags: Vec<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// 解析数据库 CSV，返回表头和各行（第一列为页面标题）
+fn parse_database(bytes: &[u8]) -> Result<(Vec<String>, Vec<Row>), String> {
+    let text = std::str::from_utf8(bytes).map_err(|_| "不是有效的 UTF-8".to_string())?;
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("CSV 解析失败: {}", e))?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("CSV 解析失败: {}", e))?;
+        let mut row = Row {
+            title: record.get(0).unwrap_or_default().trim().to_string(),
+            ..Default::default()
+        };
+        for (header, value) in headers.iter().zip(record.iter()).skip(1) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let column = header.to_lowercase();
+            if CREATED_COLUMNS.contains(&column.as_str()) {
+                row.created_at = parse_notion_time(value);
+            } else if UPDATED_COLUMNS.contains(&column.as_str()) {
+                row.updated_at = parse_notion_time(value);
+            } else if TAG_COLUMNS.contains(&column.as_str()) {
+                row.tags.extend(
+                    value
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty()),
+                );
+            } else if value.chars().count() <= MAX_TAG_CHARS {
+                row.tags.push(format!("{}: {}", header, value));
+            }
+        }
+        rows.push(row);
+    }
+    Ok((headers, rows))
+}
+
+/// 拆分页面开头的 `# 标题` 与正文
+fn split_title(text: &str) -> (Option<String>, &str) {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    match text.strip_prefix("# ") {
+        Some(rest) => {
+            let (title, body) = rest.split_once('\n').unwrap_or((rest, ""));
+            (
+                Some(title.trim().to_string()),
+                body.trim_start_matches(['\r', '\n']),
+            )
+        }
+        None => (None, text),
+    }
+}
+
+/// 去掉数据库行页面开头与 CSV 重复的 `属性: 值` 行
+fn strip_property_lines<'a>(body: &'a str, columns: &[String]) -> &'a str {
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let line_trimmed = line.trim_end();
+        let is_property = columns.iter().any(|column| {
+            line_trimmed
+                .strip_prefix(column.as_str())
+                .is_some_and(|rest| rest.starts_with(':'))
+        });
+        if !is_property {
+            break;
+        }
+        offset += line.len();
+    }
+    body[offset..].trim_start_matches(['\r', '\n'])
+}
+
+/// 改写页面中的相对链接：指向页面的改为占位符，指向图片的内嵌为 data URL，
+/// 指向数据库或缺失文件的链接只保留文字
+fn rewrite_links(
+    page: &Page,
+    page_index: &HashMap<String, usize>,
+    entries: &BTreeMap<String, Vec<u8>>,
+) -> String {
+    let dir = parent_dir(&page.path);
+    link_regex()
+        .replace_all(&page.body, |caps: &Captures| {
+            let (bang, text, target) = (&caps[1], &caps[2], &caps[3]);
+            if target.contains("://") || target.starts_with(['#', '/']) || target.contains(':') {
+                return caps[0].to_string();
+            }
+            let resolved = resolve_path(dir, &percent_decode_str(target).decode_utf8_lossy());
+            if let Some(index) = page_index.get(&resolved) {
+                return format!("{}[{}]({}{})", bang, text, PAGE_LINK_PLACEHOLDER, index);
+            }
+            let ext = resolved.rsplit('.').next().unwrap_or_default();
+            match entries.get(&resolved) {
+                Some(bytes) if archive::is_image_ext(ext) => {
+                    format!("{}[{}]({})", bang, text, archive::to_data_url(ext, bytes))
+                }
+                _ if bang.is_empty() => text.to_string(),
+                _ => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// 将占位符替换为导入后笔记的链接
+fn resolve_links(content: &str, ids: &[Option<i64>]) -> String {
+    placeholder_regex()
+        .replace_all(content, |caps: &Captures| {
+            let id = caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| ids.get(index).copied().flatten());
+            match id {
+                Some(id) => format!("{}{}", NOTE_LINK_PREFIX, id),
+                None => "#".to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn push_unique(tags: &mut Vec<String>, tag: String) {
+    if !tag.is_empty() && !tags.contains(&tag) {
+        tags.push(tag);
+    }
+}
+
+/// 解析 Notion 导出，返回待导入的笔记（页面链接为占位符）和预览报告
+pub fn scan_export<R: Read + Seek>(
+    reader: R,
+    options: &MarkdownImportOptions,
+) -> Result<(Vec<Note>, MarkdownImportReport), String> {
+    let mut entries = BTreeMap::new();
+    read_entries(reader, 0, &mut SizeLimit::default(), &mut entries)?;
+
+    let mut report = MarkdownImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    let mut pages = Vec::new();
+    for (path, bytes) in entries
+        .iter()
+        .filter(|(path, _)| path.to_lowercase().ends_with(".md"))
+    {
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            report.failed.push(FileIssue {
+                path: clean_path(path),
+                reason: "不是有效的 UTF-8".to_string(),
+            });
+            continue;
+        };
+        let (title, body) = split_title(text);
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let stem = file_name.strip_suffix(".md").unwrap_or(file_name);
+        pages.push(Page {
+            path: path.clone(),
+            title: title.unwrap_or_else(|| strip_notion_id(stem).to_string()),
+            body: body.to_string(),
+            ..Default::default()
+        });
+    }
+
+    // 数据库行页面位于与 CSV 同名（去掉 .csv 与 _all 后缀）的文件夹中，按「文件夹 + 标题」匹配
+    let mut by_folder_title: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (index, page) in pages.iter().enumerate().rev() {
+        by_folder_title
+            .entry((parent_dir(&page.path).to_string(), page.title.clone()))
+            .or_default()
+            .push(index);
+    }
+
+    // 新版 Notion 同时导出 `<名称>.csv` 和包含全部行的 `<名称>_all.csv`，优先使用后者
+    let mut databases: BTreeMap<String, &String> = BTreeMap::new();
+    for path in entries
+        .keys()
+        .filter(|p| p.to_lowercase().ends_with(".csv"))
+    {
+        let stem = &path[..path.len() - 4];
+        match stem.strip_suffix("_all") {
+            Some(folder) => {
+                databases.insert(folder.to_string(), path);
+            }
+            None => {
+                databases.entry(stem.to_string()).or_insert(path);
+            }
+        }
+    }
+
+    for (folder, csv_path) in databases {
+        let (columns, rows) = match parse_database(&entries[csv_path]) {
+            Ok(parsed) => parsed,
+            Err(reason) => {
+                report.failed.push(FileIssue {
+                    path: clean_path(csv_path),
+                    reason,
+                });
+                continue;
+            }
+        };
+        for row in rows {
+            let matched = by_folder_title
+                .get_mut(&(folder.clone(), row.title.clone()))
+                .and_then(|indexes| indexes.pop());
+            let page = match matched {
+                Some(index) => {
+                    let page = &mut pages[index];
+                    page.body = strip_property_lines(&page.body, &columns).to_string();
+                    page
+                }
+                None => {
+                    pages.push(Page {
+                        path: format!("{}/{}.md", folder, row.title),
+                        title: row.title.clone(),
+                        ..Default::default()
+                    });
+                    pages.last_mut().unwrap()
+                }
+            };
+            for tag in row.tags {
+                push_unique(&mut page.tags, tag);
+            }
+            page.created_at = row.created_at.or(page.created_at.take());
+            page.updated_at = row.updated_at.or(page.updated_at.take());
+        }
+    }
+
+    let page_index: HashMap<String, usize> = pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| (page.path.clone(), index))
+        .collect();
+
+    let now = to_iso(Utc::now());
+    let mut notes = Vec::with_capacity(pages.len());
+    for page in &pages {
+        let cleaned = clean_path(&page.path);
+        let mut tags = page.tags.clone();
+        if options.folder_tags {
+            for folder in parent_dir(&cleaned).split('/') {
+                push_unique(&mut tags, folder.trim().to_string());
+            }
+        }
+        let created_at = page.created_at.clone().unwrap_or_else(|| now.clone());
+        let updated_at = page
+            .updated_at
+            .clone()
+            .unwrap_or_else(|| created_at.clone());
+
+        report.notes.push(MarkdownImportEntry {
+            path: cleaned,
+            title: page.title.clone(),
+            tags: tags.clone(),
+            created_at: created_at.clone(),
+            updated_at: updated_at.clone(),
+        });
+        notes.push(Note {
+            id: None,
+            title: page.title.clone(),
+            content: rewrite_links(page, &page_index, &entries),
+            tags,
+            is_favorite: 0,
+            is_deleted: 0,
+            created_at,
+            updated_at,
+            reminder_date: None,
+            reminder_enabled: 0,
+        });
+    }
+
+    Ok((notes, report))
+}
+
+/// 导入 Notion 导出的 zip，所有页面在同一事务中按冲突策略写入；
+/// 全部写入后再把页面之间的链接改写为对应笔记的 ID。dry_run 时仅返回预览和计划
+pub fn import_export<R: Read + Seek>(
+    conn: &mut Connection,
+    reader: R,
+    options: &MarkdownImportOptions,
+) -> Result<MarkdownImportReport, String> {
+    let (notes, mut report) = scan_export(reader, options)?;
+    report.actions = conflict::plan(conn, &notes, &options.conflict)?;
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    let mut ids = Vec::with_capacity(notes.len());
+    for (note, action) in notes.iter().zip(&report.actions) {
+        let written = conflict::apply(&tx, note, action)
+            .map_err(|e| format!("写入笔记「{}」失败: {}", note.title, e))?;
+        match (written, action.action) {
+            (None, _) => {}
+            (Some(_), NoteAction::Overwrite) => report.notes_updated += 1,
+            (Some(_), _) => report.notes_inserted += 1,
+        }
+        ids.push(written.or(action.existing_id));
+    }
+
+    for ((note, action), id) in notes.iter().zip(&report.actions).zip(&ids) {
+        let Some(id) = id else {
+            continue;
+        };
+        if action.action == NoteAction::Skip || !placeholder_regex().is_match(&note.content) {
+            continue;
+        }
+        tx.execute(
+            "UPDATE notes SET content = ?1 WHERE id = ?2",
+            params![resolve_links(&note.content, &ids), id],
+        )
+        .map_err(|e| format!("改写笔记「{}」中的链接失败: {}", note.title, e))?;
+    }
+
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    report.committed = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const ID_A: &str = "0123456789abcdef0123456789abcdef";
+    const ID_B: &str = "fedcba9876543210fedcba9876543210";
+    const ID_DB: &str = "11111111111111111111111111111111";
+    const ID_ROW: &str = "22222222222222222222222222222222";
+
+    fn build_zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut zip = ZipWriter::new(&mut buffer);
+        for (name, bytes) in files {
+            zip.start_file(name.as_str(), SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+        buffer.into_inner()
+    }
+
+    fn sample_export() -> Vec<u8> {
+        let inner = build_zip(&[
+            (
+                format!("首页 {}.md", ID_A),
+                format!(
+                    "# 首页\n\n见 [子页面](%E9%A6%96%E9%A1%B5%20{a}/%E5%AD%90%E9%A1%B5%E9%9D%A2%20{b}.md) 与 [任务表](%E4%BB%BB%E5%8A%A1%20{db}.csv)\n\n![](%E9%A6%96%E9%A1%B5%20{a}/pic.png)\n",
+                    a = ID_A,
+                    b = ID_B,
+                    db = ID_DB
+                )
+                .into_bytes(),
+            ),
+            (
+                format!("首页 {}/子页面 {}.md", ID_A, ID_B),
+                format!("# 子页面\n\n返回 [首页](../%E9%A6%96%E9%A1%B5%20{}.md)\n", ID_A).into_bytes(),
+            ),
+            (format!("首页 {}/pic.png", ID_A), b"\x89PNG".to_vec()),
+            (
+                format!("任务 {}.csv", ID_DB),
+                "\u{feff}Name,Status,Tags,Created\n写周报,Done,\"工作, 周报\",\"January 2, 2024 8:30 AM\"\n只有行,Todo,,\n"
+                    .as_bytes()
+                    .to_vec(),
+            ),
+            (
+                format!("任务 {}/写周报 {}.md", ID_DB, ID_ROW),
+                "# 写周报\n\nStatus: Done\nTags: 工作, 周报\n\n正文内容\n".as_bytes().to_vec(),
+            ),
+            ("坏 33333333333333333333333333333333.md".to_string(), vec![0xff, 0xfe]),
+        ]);
+        // Notion 大型导出会把分卷再打包一层
+        build_zip(&[("Export-Part-1.zip".to_string(), inner)])
+    }
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn strips_notion_ids() {
+        assert_eq!(strip_notion_id(&format!("页面 {}", ID_A)), "页面");
+        assert_eq!(strip_notion_id("普通名称"), "普通名称");
+        assert_eq!(
+            clean_path(&format!("项目 {}/任务 {}.md", ID_A, ID_B)),
+            "项目/任务.md"
+        );
+        assert_eq!(resolve_path("a/b", "../c/./d.md"), "a/c/d.md");
+    }
+
+    #[test]
+    fn scans_pages_and_database_rows() {
+        let options = MarkdownImportOptions {
+            folder_tags: true,
+            ..Default::default()
+        };
+        let (notes, report) = scan_export(Cursor::new(sample_export()), &options).unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, "坏.md");
+
+        let paths: Vec<_> = report.notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "任务/写周报.md",
+                "首页.md",
+                "首页/子页面.md",
+                "任务/只有行.md"
+            ]
+        );
+
+        let row = &notes[0];
+        assert_eq!(row.content, "正文内容\n");
+        assert_eq!(row.tags, vec!["Status: Done", "工作", "周报", "任务"]);
+        assert_eq!(row.created_at, "2024-01-02T08:30:00.000Z");
+
+        let home = &notes[1];
+        assert!(home.content.contains("[子页面](jdnotes://notion-page/2)"));
+        assert!(home.content.contains("与 任务表"));
+        assert!(home.content.contains("![](data:image/png;base64,"));
+        assert_eq!(notes[3].content, "");
+    }
+
+    #[test]
+    fn imports_and_rebuilds_links() {
+        let mut conn = setup();
+        let dry_run = MarkdownImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let preview = import_export(&mut conn, Cursor::new(sample_export()), &dry_run).unwrap();
+        assert_eq!(preview.actions.len(), 4);
+        assert!(crate::storage::read_notes(&conn).unwrap().is_empty());
+
+        let report = import_export(
+            &mut conn,
+            Cursor::new(sample_export()),
+            &MarkdownImportOptions::default(),
+        )
+        .unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 4);
+
+        let notes = crate::storage::read_notes(&conn).unwrap();
+        assert!(notes[1].content.contains("[子页面](jdnotes://note/3)"));
+        assert!(notes[2].content.contains("[首页](jdnotes://note/2)"));
+    }
+}
@@ -0,0 +1,1201 @@
+//! PDF 导出
+//!
+//! 说明：直接在后端把笔记的 Markdown 排版为 PDF，不依赖 WebView 的打印对话框。
+//! 支持标题、段落、列表、引用、代码块、表格、内嵌图片，可选目录和页码；
+//! 多篇笔记（如同一标签下的全部笔记）合并为一个 PDF，每篇从新的一页开始。
+//! 中文需要嵌入 TrueType 字体，未指定时自动查找包含笔记中全部中文字符的系统字体，
+//! 只有西文的笔记还会尝试 Arial、DejaVu Sans 等西文字体，找不到时在错误中列出缺少的字符；
+//! 字体集合（.ttc）中的字体会先拆分为单个字体再嵌入。
+//! printpdf 只能嵌入 TrueType 轮廓的字体，CFF 轮廓的字体（如 Noto Sans CJK / 思源黑体）无法使用
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use printpdf::image_crate::{self, DynamicImage, Rgb as RgbPixel, RgbImage};
+use printpdf::path::PaintMode;
+use printpdf::{
+    Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerIndex, PdfLayerReference, PdfPageIndex, Point, Rect, Rgb, TextRenderingMode,
+};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use ttf_parser::Face;
+
+use crate::export;
+use crate::models::{Note, PdfExportOptions, PdfExportReport};
+
+/// A4 纸张尺寸（毫米）
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// 1 磅对应的毫米数
+const PT_TO_MM: f32 = 0.352_778;
+
+const TITLE_SIZE: f32 = 22.0;
+const BODY_SIZE: f32 = 11.0;
+const SMALL_SIZE: f32 = 9.0;
+const CODE_SIZE: f32 = 9.5;
+
+/// 列表每级缩进（毫米）
+const LIST_INDENT: f32 = 6.0;
+
+/// 图片按 96 DPI 计算原始尺寸
+const IMAGE_DPI: f32 = 96.0;
+
+/// 目录收录的最大标题级别
+const TOC_MAX_LEVEL: u8 = 3;
+
+/// 自动查找的系统中文字体（TrueType 轮廓，按优先级排列）
+const SYSTEM_FONTS: [&str; 16] = [
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\Deng.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-zenhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-zenhei/wqy-zenhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/arphic/uming.ttc",
+    "/usr/share/fonts/truetype/arphic-gbsn00lp/gbsn00lp.ttf",
+];
+
+/// 笔记中没有中日韩文字时额外尝试的西文字体（TrueType 轮廓）
+const LATIN_FONTS: [&str; 9] = [
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+];
+
+/// 错误信息中最多列出的缺少字符数
+const MAX_LISTED_CHARS: usize = 20;
+
+/// 笔记正文中的排版块
+#[derive(Debug, PartialEq)]
+enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    /// 列表项：层级、项目符号（续段为空）、文字
+    ListItem(usize, String, String),
+    Quote(String),
+    Code(Vec<String>),
+    /// 表格，第一行为表头
+    Table(Vec<Vec<String>>),
+    Image {
+        src: String,
+        alt: String,
+    },
+    Rule,
+}
+
+/// Markdown 事件到排版块的转换状态
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<Block>,
+    text: String,
+    /// 各层列表的下一个序号（无序列表为 None）
+    lists: Vec<Option<u64>>,
+    /// 当前所在列表项的层级和尚未输出的项目符号
+    items: Vec<(usize, String)>,
+    quote_depth: usize,
+    table: Option<Vec<Vec<String>>>,
+    image: Option<String>,
+}
+
+impl BlockBuilder {
+    fn flush_text(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let block = match self.items.last_mut() {
+            Some((depth, marker)) => {
+                Block::ListItem(*depth, std::mem::take(marker), text.to_string())
+            }
+            None if self.quote_depth > 0 => Block::Quote(text.to_string()),
+            None => Block::Paragraph(text.to_string()),
+        };
+        self.blocks.push(block);
+    }
+
+    fn push_event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Heading { .. }) | Event::Start(Tag::Item) => {
+                self.flush_text();
+                if let Event::Start(Tag::Item) = event {
+                    let depth = self.lists.len().saturating_sub(1);
+                    let marker = match self.lists.last_mut() {
+                        Some(Some(next)) => {
+                            *next += 1;
+                            format!("{}.", *next - 1)
+                        }
+                        _ => "•".to_string(),
+                    };
+                    self.items.push((depth, marker));
+                }
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let text = std::mem::take(&mut self.text);
+                self.blocks
+                    .push(Block::Heading(level as u8, text.trim().to_string()));
+            }
+            Event::End(TagEnd::Paragraph) => self.flush_text(),
+            Event::Start(Tag::List(start)) => {
+                self.flush_text();
+                self.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::End(TagEnd::Item) => {
+                self.flush_text();
+                self.items.pop();
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some((_, marker)) = self.items.last_mut() {
+                    *marker = if checked { "[x]" } else { "[ ]" }.to_string();
+                }
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                self.flush_text();
+                self.quote_depth += 1;
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                self.flush_text();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.flush_text();
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let code = std::mem::take(&mut self.text);
+                let lines = code
+                    .trim_end_matches('\n')
+                    .lines()
+                    .map(|line| line.replace('\t', "    "))
+                    .collect();
+                self.blocks.push(Block::Code(lines));
+            }
+            Event::Start(Tag::Table(_)) => {
+                self.flush_text();
+                self.table = Some(Vec::new());
+            }
+            Event::Start(Tag::TableHead) | Event::Start(Tag::TableRow) => {
+                if let Some(table) = &mut self.table {
+                    table.push(Vec::new());
+                }
+            }
+            Event::End(TagEnd::TableCell) => {
+                let cell = std::mem::take(&mut self.text);
+                if let Some(row) = self.table.as_mut().and_then(|t| t.last_mut()) {
+                    row.push(cell.trim().to_string());
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                if let Some(table) = self.table.take() {
+                    self.blocks.push(Block::Table(table));
+                }
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                self.flush_text();
+                self.image = Some(dest_url.to_string());
+            }
+            Event::End(TagEnd::Image) => {
+                let alt = std::mem::take(&mut self.text);
+                if let Some(src) = self.image.take() {
+                    self.blocks.push(Block::Image {
+                        src,
+                        alt: alt.trim().to_string(),
+                    });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => self.text.push_str(&text),
+            Event::SoftBreak => self.text.push(' '),
+            Event::HardBreak => self.text.push('\n'),
+            Event::FootnoteReference(name) => self.text.push_str(&format!("[{}]", name)),
+            Event::Rule => {
+                self.flush_text();
+                self.blocks.push(Block::Rule);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 将笔记 Markdown 解析为排版块
+fn parse_blocks(content: &str) -> Vec<Block> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut builder = BlockBuilder::default();
+    for event in Parser::new_ext(content, options) {
+        builder.push_event(event);
+    }
+    builder.flush_text();
+    builder.blocks
+}
+
+/// 是否为可在任意位置换行的全角字符（中日韩文字和标点）
+fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{2E80}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}')
+}
+
+/// 按宽度折行：西文在空格处换行，中日韩文字可在任意字符间换行，超长单词强制断开
+fn wrap_text(text: &str, max_width: f32, char_width: &dyn Fn(char) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut width = 0.0;
+        let mut last_break = None;
+        for c in paragraph.chars() {
+            if is_wide(c) && !line.is_empty() {
+                last_break = Some(line.len());
+            }
+            line.push(c);
+            width += char_width(c);
+            if c == ' ' || is_wide(c) {
+                last_break = Some(line.len());
+            }
+            if width > max_width && line.chars().count() > 1 {
+                let at = match last_break {
+                    Some(at) if at > 0 && at < line.len() => at,
+                    _ => line.len() - c.len_utf8(),
+                };
+                let rest = line.split_off(at);
+                lines.push(line.trim_end().to_string());
+                line = rest.trim_start().to_string();
+                width = line.chars().map(char_width).sum();
+                last_break = None;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// 解码 data URL 图片，透明部分合成到白色背景上
+fn decode_image(src: &str) -> Option<DynamicImage> {
+    let (meta, data) = src.strip_prefix("data:")?.split_once(',')?;
+    if !meta.ends_with(";base64") {
+        return None;
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .ok()?;
+    let rgba = image_crate::load_from_memory(&bytes).ok()?.to_rgba8();
+    let mut rgb = RgbImage::new(rgba.width(), rgba.height());
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        rgb.put_pixel(x, y, RgbPixel([blend(r), blend(g), blend(b)]));
+    }
+    Some(DynamicImage::ImageRgb8(rgb))
+}
+
+fn gray(level: f32) -> Color {
+    Color::Rgb(Rgb::new(level, level, level, None))
+}
+
+/// 文字行高（毫米）
+fn line_height(size: f32) -> f32 {
+    size * 1.5 * PT_TO_MM
+}
+
+/// 目录条目
+struct TocEntry {
+    level: u8,
+    title: String,
+    page: usize,
+}
+
+/// 排版状态
+struct PdfWriter<'a> {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    face: Face<'a>,
+    pages: Vec<(PdfPageIndex, PdfLayerIndex)>,
+    /// 下一行顶部的纵坐标（毫米，自页面底部起算）
+    y: f32,
+    missing_glyphs: usize,
+}
+
+impl<'a> PdfWriter<'a> {
+    fn layer(&self) -> PdfLayerReference {
+        let (page, layer) = *self.pages.last().expect("至少有一页");
+        self.doc.get_page(page).get_layer(layer)
+    }
+
+    fn char_width(&self, c: char, size: f32) -> f32 {
+        let units = self.face.units_per_em().max(1) as f32;
+        self.face
+            .glyph_index(c)
+            .and_then(|glyph| self.face.glyph_hor_advance(glyph))
+            .map(|advance| advance as f32 / units * size * PT_TO_MM)
+            .unwrap_or(0.0)
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.char_width(c, size)).sum()
+    }
+
+    fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<String> {
+        wrap_text(text, max_width, &|c| self.char_width(c, size))
+    }
+
+    fn new_page(&mut self) {
+        let page = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "正文");
+        self.pages.push(page);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn at_page_top(&self) -> bool {
+        self.y >= PAGE_HEIGHT - MARGIN
+    }
+
+    /// 当前页剩余空间不足时换页
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.at_page_top() {
+            self.new_page();
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        if !self.at_page_top() {
+            self.y -= height;
+        }
+    }
+
+    /// 在指定位置写一行文字（不移动光标）
+    fn text_at(&mut self, text: &str, size: f32, x: f32, baseline: f32, color: Color) {
+        self.missing_glyphs += text
+            .chars()
+            .filter(|c| !c.is_whitespace() && self.face.glyph_index(*c).is_none())
+            .count();
+        let layer = self.layer();
+        layer.set_fill_color(color);
+        layer.use_text(text, size, Mm(x), Mm(baseline), &self.font);
+    }
+
+    /// 写一行文字并下移光标
+    fn line(&mut self, text: &str, size: f32, x: f32, color: Color) {
+        let height = line_height(size);
+        self.ensure_space(height);
+        let baseline = self.y - size * 1.15 * PT_TO_MM;
+        self.text_at(text, size, x, baseline, color);
+        self.y -= height;
+    }
+
+    /// 折行后逐行写入
+    fn paragraph(&mut self, text: &str, size: f32, x: f32, width: f32, color: Color) {
+        for line in self.wrap(text, size, width) {
+            self.line(&line, size, x, color.clone());
+        }
+    }
+
+    fn bold(&self, enabled: bool) {
+        let layer = self.layer();
+        if enabled {
+            layer.set_outline_color(gray(0.0));
+            layer.set_outline_thickness(0.4);
+            layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+        } else {
+            layer.set_text_rendering_mode(TextRenderingMode::Fill);
+        }
+    }
+
+    fn rect(&self, left: f32, bottom: f32, right: f32, top: f32, mode: PaintMode) {
+        let layer = self.layer();
+        layer.add_rect(Rect::new(Mm(left), Mm(bottom), Mm(right), Mm(top)).with_mode(mode));
+    }
+
+    fn rule(&mut self, x1: f32, x2: f32, y: f32) {
+        let layer = self.layer();
+        layer.set_outline_color(gray(0.75));
+        layer.set_outline_thickness(0.5);
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(x1), Mm(y)), false),
+                (Point::new(Mm(x2), Mm(y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn heading(&mut self, level: u8, text: &str) {
+        let size = match level {
+            1 => 18.0,
+            2 => 15.0,
+            3 => 13.0,
+            _ => 12.0,
+        };
+        self.space(line_height(size) * 0.6);
+        // 标题不与下一段分开：至少留出标题和一行正文的空间
+        self.ensure_space(line_height(size) + line_height(BODY_SIZE));
+        self.bold(true);
+        self.paragraph(text, size, MARGIN, CONTENT_WIDTH, gray(0.0));
+        self.bold(false);
+        self.space(line_height(size) * 0.2);
+    }
+
+    fn code(&mut self, lines: &[String]) {
+        let height = line_height(CODE_SIZE);
+        self.space(1.0);
+        for line in lines {
+            for part in self.wrap(line, CODE_SIZE, CONTENT_WIDTH - 6.0) {
+                self.ensure_space(height);
+                let layer = self.layer();
+                layer.set_fill_color(gray(0.95));
+                self.rect(
+                    MARGIN,
+                    self.y - height,
+                    PAGE_WIDTH - MARGIN,
+                    self.y,
+                    PaintMode::Fill,
+                );
+                let baseline = self.y - CODE_SIZE * 1.15 * PT_TO_MM;
+                self.text_at(&part, CODE_SIZE, MARGIN + 3.0, baseline, gray(0.15));
+                self.y -= height;
+            }
+        }
+        self.space(3.0);
+    }
+
+    fn table(&mut self, rows: &[Vec<String>]) {
+        let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let padding = 1.5;
+        let column_width = CONTENT_WIDTH / columns as f32;
+        let height = line_height(SMALL_SIZE);
+        self.space(1.0);
+        for (index, row) in rows.iter().enumerate() {
+            let cells: Vec<Vec<String>> = (0..columns)
+                .map(|i| {
+                    let text = row.get(i).map(String::as_str).unwrap_or_default();
+                    self.wrap(text, SMALL_SIZE, column_width - 2.0 * padding)
+                })
+                .collect();
+            let row_lines = cells.iter().map(Vec::len).max().unwrap_or(1);
+            let row_height = row_lines as f32 * height + 2.0 * padding;
+            self.ensure_space(row_height);
+
+            let top = self.y;
+            let bottom = top - row_height;
+            if index == 0 {
+                self.layer().set_fill_color(gray(0.92));
+                self.rect(MARGIN, bottom, PAGE_WIDTH - MARGIN, top, PaintMode::Fill);
+            }
+            for (i, lines) in cells.iter().enumerate() {
+                let left = MARGIN + i as f32 * column_width;
+                for (n, line) in lines.iter().enumerate() {
+                    let baseline = top - padding - n as f32 * height - SMALL_SIZE * 1.15 * PT_TO_MM;
+                    self.text_at(line, SMALL_SIZE, left + padding, baseline, gray(0.0));
+                }
+                self.layer().set_outline_color(gray(0.6));
+                self.layer().set_outline_thickness(0.5);
+                self.rect(left, bottom, left + column_width, top, PaintMode::Stroke);
+            }
+            self.y = bottom;
+        }
+        self.space(3.0);
+    }
+
+    fn image(&mut self, src: &str, alt: &str) {
+        let Some(image) = decode_image(src) else {
+            // 远程图片无法离线嵌入，只保留说明文字
+            let label = if alt.is_empty() {
+                "[图片]".to_string()
+            } else {
+                format!("[图片: {}]", alt)
+            };
+            self.paragraph(&label, SMALL_SIZE, MARGIN, CONTENT_WIDTH, gray(0.45));
+            return;
+        };
+
+        let natural_width = image.width() as f32 * 25.4 / IMAGE_DPI;
+        let natural_height = image.height() as f32 * 25.4 / IMAGE_DPI;
+        let max_height = (PAGE_HEIGHT - 2.0 * MARGIN) * 0.8;
+        let scale = (CONTENT_WIDTH / natural_width)
+            .min(max_height / natural_height)
+            .min(1.0);
+        let height = natural_height * scale;
+
+        self.space(1.0);
+        self.ensure_space(height);
+        Image::from_dynamic_image(&image).add_to_layer(
+            self.layer(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN)),
+                translate_y: Some(Mm(self.y - height)),
+                scale_x: Some(scale),
+                scale_y: Some(scale),
+                dpi: Some(IMAGE_DPI),
+                ..Default::default()
+            },
+        );
+        self.y -= height + 3.0;
+    }
+
+    fn block(&mut self, block: &Block, toc: &mut Vec<TocEntry>) {
+        match block {
+            Block::Heading(level, text) => {
+                self.heading(*level, text);
+                if *level <= TOC_MAX_LEVEL {
+                    toc.push(TocEntry {
+                        level: *level,
+                        title: text.clone(),
+                        page: self.pages.len(),
+                    });
+                }
+            }
+            Block::Paragraph(text) => {
+                self.paragraph(text, BODY_SIZE, MARGIN, CONTENT_WIDTH, gray(0.0));
+                self.space(2.5);
+            }
+            Block::ListItem(depth, marker, text) => {
+                let indent = MARGIN + LIST_INDENT * (*depth as f32 + 1.0);
+                let lines = self.wrap(text, BODY_SIZE, PAGE_WIDTH - MARGIN - indent);
+                for (i, line) in lines.iter().enumerate() {
+                    self.ensure_space(line_height(BODY_SIZE));
+                    if i == 0 && !marker.is_empty() {
+                        let x = indent - self.text_width(marker, BODY_SIZE) - 1.5;
+                        let baseline = self.y - BODY_SIZE * 1.15 * PT_TO_MM;
+                        self.text_at(marker, BODY_SIZE, x, baseline, gray(0.0));
+                    }
+                    self.line(line, BODY_SIZE, indent, gray(0.0));
+                }
+                self.space(1.0);
+            }
+            Block::Quote(text) => {
+                let top = self.y;
+                let page = self.pages.len();
+                self.paragraph(
+                    text,
+                    BODY_SIZE,
+                    MARGIN + 5.0,
+                    CONTENT_WIDTH - 5.0,
+                    gray(0.4),
+                );
+                if page == self.pages.len() {
+                    self.layer().set_fill_color(gray(0.8));
+                    self.rect(MARGIN, self.y, MARGIN + 1.0, top, PaintMode::Fill);
+                }
+                self.space(2.5);
+            }
+            Block::Code(lines) => self.code(lines),
+            Block::Table(rows) => self.table(rows),
+            Block::Image { src, alt } => self.image(src, alt),
+            Block::Rule => {
+                self.ensure_space(4.0);
+                let y = self.y - 2.0;
+                self.rule(MARGIN, PAGE_WIDTH - MARGIN, y);
+                self.y -= 4.0;
+            }
+        }
+    }
+
+    /// 笔记标题、时间和标签
+    fn note_header(&mut self, note: &Note) {
+        self.bold(true);
+        self.paragraph(&note.title, TITLE_SIZE, MARGIN, CONTENT_WIDTH, gray(0.0));
+        self.bold(false);
+        let mut meta = format!(
+            "创建于 {}  ·  更新于 {}",
+            note.created_at.get(..10).unwrap_or(&note.created_at),
+            note.updated_at.get(..10).unwrap_or(&note.updated_at)
+        );
+        if !note.tags.is_empty() {
+            meta.push_str(&format!("  ·  {}", note.tags.join(", ")));
+        }
+        self.paragraph(&meta, SMALL_SIZE, MARGIN, CONTENT_WIDTH, gray(0.45));
+        let y = self.y - 2.0;
+        self.rule(MARGIN, PAGE_WIDTH - MARGIN, y);
+        self.y -= 6.0;
+    }
+}
+
+/// 每页可容纳的目录条目数
+fn toc_entries_per_page() -> usize {
+    let usable = PAGE_HEIGHT - 2.0 * MARGIN - line_height(TITLE_SIZE) - 6.0;
+    (usable / line_height(BODY_SIZE)).floor() as usize
+}
+
+/// 在预留的目录页上写入目录
+fn write_toc(
+    writer: &mut PdfWriter,
+    toc_pages: &[(PdfPageIndex, PdfLayerIndex)],
+    toc: &[TocEntry],
+) {
+    let per_page = toc_entries_per_page();
+    for (chunk_index, chunk) in toc.chunks(per_page.max(1)).enumerate() {
+        let (page, layer) = toc_pages[chunk_index.min(toc_pages.len() - 1)];
+        let layer_ref = writer.doc.get_page(page).get_layer(layer);
+        let mut y = PAGE_HEIGHT - MARGIN;
+        if chunk_index == 0 {
+            layer_ref.set_outline_color(gray(0.0));
+            layer_ref.set_outline_thickness(0.4);
+            layer_ref.set_text_rendering_mode(TextRenderingMode::FillStroke);
+            layer_ref.set_fill_color(gray(0.0));
+            layer_ref.use_text(
+                "目录",
+                TITLE_SIZE,
+                Mm(MARGIN),
+                Mm(y - TITLE_SIZE * 1.15 * PT_TO_MM),
+                &writer.font,
+            );
+            layer_ref.set_text_rendering_mode(TextRenderingMode::Fill);
+        }
+        y -= line_height(TITLE_SIZE) + 6.0;
+
+        for entry in chunk {
+            let indent = MARGIN + entry.level as f32 * LIST_INDENT;
+            let number = entry.page.to_string();
+            let number_width = writer.text_width(&number, BODY_SIZE);
+            let max_width = PAGE_WIDTH - MARGIN - indent - number_width - 4.0;
+            let mut title = writer
+                .wrap(&entry.title, BODY_SIZE, max_width)
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            if title.chars().count() < entry.title.chars().count() {
+                title.push('…');
+            }
+            let baseline = y - BODY_SIZE * 1.15 * PT_TO_MM;
+            let color = if entry.level == 0 {
+                gray(0.0)
+            } else {
+                gray(0.3)
+            };
+            layer_ref.set_fill_color(color);
+            layer_ref.use_text(&title, BODY_SIZE, Mm(indent), Mm(baseline), &writer.font);
+            layer_ref.use_text(
+                &number,
+                BODY_SIZE,
+                Mm(PAGE_WIDTH - MARGIN - number_width),
+                Mm(baseline),
+                &writer.font,
+            );
+            y -= line_height(BODY_SIZE);
+        }
+    }
+}
+
+/// 排版结果
+pub struct RenderedPdf {
+    pub bytes: Vec<u8>,
+    pub pages: usize,
+    pub missing_glyphs: usize,
+}
+
+/// 将笔记排版为 PDF，font_bytes 为要嵌入的 TrueType 字体
+pub fn render_pdf(
+    notes: &[Note],
+    options: &PdfExportOptions,
+    font_bytes: &[u8],
+) -> Result<RenderedPdf, String> {
+    if notes.is_empty() {
+        return Err("没有可导出的笔记".to_string());
+    }
+    let face = Face::parse(font_bytes, 0).map_err(|e| format!("解析字体失败: {}", e))?;
+
+    let title = options
+        .title
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| match notes {
+            [note] => note.title.clone(),
+            _ => "JD Notes".to_string(),
+        });
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "正文");
+    let font = doc
+        .add_external_font(font_bytes)
+        .map_err(|e| format!("嵌入字体失败: {}", e))?;
+
+    let parsed: Vec<Vec<Block>> = notes.iter().map(|n| parse_blocks(&n.content)).collect();
+
+    let mut writer = PdfWriter {
+        doc,
+        font,
+        face,
+        pages: vec![(page, layer)],
+        y: PAGE_HEIGHT - MARGIN,
+        missing_glyphs: 0,
+    };
+
+    // 目录放在开头：条目数在排版前即可确定，先预留目录页，排版完成后再填入页码
+    let mut toc_pages = Vec::new();
+    if options.table_of_contents {
+        let entries: usize = parsed
+            .iter()
+            .map(|blocks| {
+                1 + blocks
+                    .iter()
+                    .filter(|b| matches!(b, Block::Heading(level, _) if *level <= TOC_MAX_LEVEL))
+                    .count()
+            })
+            .sum();
+        let count = entries.div_ceil(toc_entries_per_page().max(1));
+        toc_pages.push((page, layer));
+        for _ in 1..count {
+            writer.new_page();
+            toc_pages.push(*writer.pages.last().unwrap());
+        }
+        writer.new_page();
+    }
+
+    let mut toc = Vec::new();
+    for (note, blocks) in notes.iter().zip(&parsed) {
+        if !writer.at_page_top() {
+            writer.new_page();
+        }
+        let (page, _) = *writer.pages.last().unwrap();
+        writer.doc.add_bookmark(note.title.clone(), page);
+        toc.push(TocEntry {
+            level: 0,
+            title: note.title.clone(),
+            page: writer.pages.len(),
+        });
+        writer.note_header(note);
+        for block in blocks {
+            writer.block(block, &mut toc);
+        }
+    }
+
+    if !toc_pages.is_empty() {
+        write_toc(&mut writer, &toc_pages, &toc);
+    }
+
+    let total = writer.pages.len();
+    if options.page_numbers {
+        for (index, (page, layer)) in writer.pages.iter().enumerate() {
+            let label = format!("{} / {}", index + 1, total);
+            let x = (PAGE_WIDTH - writer.text_width(&label, SMALL_SIZE)) / 2.0;
+            let layer = writer.doc.get_page(*page).get_layer(*layer);
+            layer.set_fill_color(gray(0.45));
+            layer.use_text(label, SMALL_SIZE, Mm(x), Mm(MARGIN / 2.0), &writer.font);
+        }
+    }
+
+    let missing_glyphs = writer.missing_glyphs;
+    let bytes = writer
+        .doc
+        .save_to_bytes()
+        .map_err(|e| format!("生成 PDF 失败: {}", e))?;
+    Ok(RenderedPdf {
+        bytes,
+        pages: total,
+        missing_glyphs,
+    })
+}
+
+/// 要嵌入 PDF 的字体
+pub struct PdfFont {
+    pub path: PathBuf,
+    /// 在字体集合中的序号（单个字体为 0）
+    pub index: u32,
+    /// 可直接嵌入的单个 TrueType 字体
+    pub bytes: Vec<u8>,
+}
+
+/// 从字体集合中拆出单个字体：复制该字体的表目录和各表数据，并重新计算表的偏移
+fn extract_from_collection(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    let u16_at = |pos: usize| Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?));
+    let u32_at = |pos: usize| Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?));
+
+    let offset = u32_at(12 + 4 * index as usize)? as usize;
+    let num_tables = u16_at(offset + 4)? as usize;
+    let mut font = data.get(offset..offset + 12)?.to_vec();
+    let mut tables = Vec::with_capacity(num_tables);
+    let mut table_offset = 12 + 16 * num_tables;
+    for i in 0..num_tables {
+        let record = offset + 12 + 16 * i;
+        let start = u32_at(record + 8)? as usize;
+        let length = u32_at(record + 12)? as usize;
+        font.extend_from_slice(data.get(record..record + 8)?);
+        font.extend_from_slice(&(table_offset as u32).to_be_bytes());
+        font.extend_from_slice(&(length as u32).to_be_bytes());
+        tables.push(data.get(start..start + length)?);
+        table_offset += length.next_multiple_of(4);
+    }
+    for table in tables {
+        font.extend_from_slice(table);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    Some(font)
+}
+
+fn family_name(face: &Face) -> String {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == ttf_parser::name_id::FAMILY)
+        .find_map(|name| name.to_string())
+        .unwrap_or_default()
+}
+
+/// 读取字体文件，字体集合中优先选择简体中文字体
+pub fn load_font(path: &Path) -> Result<PdfFont, String> {
+    let data = fs::read(path).map_err(|e| format!("读取字体文件失败: {}", e))?;
+    let Some(count) = ttf_parser::fonts_in_collection(&data) else {
+        let face = Face::parse(&data, 0).map_err(|e| format!("解析字体失败: {}", e))?;
+        if face.tables().glyf.is_none() {
+            return Err(format!(
+                "{} 不是 TrueType 轮廓的字体，无法嵌入 PDF",
+                path.to_string_lossy()
+            ));
+        }
+        return Ok(PdfFont {
+            path: path.to_path_buf(),
+            index: 0,
+            bytes: data,
+        });
+    };
+
+    let faces: Vec<(u32, String)> = (0..count)
+        .filter_map(|index| {
+            let face = Face::parse(&data, index).ok()?;
+            face.tables().glyf.map(|_| (index, family_name(&face)))
+        })
+        .collect();
+    let index = faces
+        .iter()
+        .find(|(_, name)| name.contains("SC") || name.contains("GB"))
+        .or(faces.first())
+        .map(|(index, _)| *index)
+        .ok_or_else(|| {
+            format!(
+                "{} 中没有 TrueType 轮廓的字体，无法嵌入 PDF",
+                path.to_string_lossy()
+            )
+        })?;
+    let bytes = extract_from_collection(&data, index)
+        .ok_or_else(|| format!("拆分字体集合失败: {}", path.to_string_lossy()))?;
+    Ok(PdfFont {
+        path: path.to_path_buf(),
+        index,
+        bytes,
+    })
+}
+
+/// 笔记中出现的中日韩文字
+fn wide_chars(notes: &[Note]) -> BTreeSet<char> {
+    notes
+        .iter()
+        .flat_map(|note| note.title.chars().chain(note.content.chars()))
+        .filter(|c| is_wide(*c))
+        .collect()
+}
+
+/// 字体中缺少的字符
+fn missing_chars(font: &PdfFont, chars: &BTreeSet<char>) -> Vec<char> {
+    match Face::parse(&font.bytes, 0) {
+        Ok(face) => chars
+            .iter()
+            .copied()
+            .filter(|c| face.glyph_index(*c).is_none())
+            .collect(),
+        Err(_) => chars.iter().copied().collect(),
+    }
+}
+
+/// 自动查找的字体：笔记中只有西文时也尝试西文字体
+fn candidate_fonts(needs_cjk: bool) -> Vec<&'static str> {
+    let mut fonts = SYSTEM_FONTS.to_vec();
+    if !needs_cjk {
+        fonts.extend(LATIN_FONTS);
+    }
+    fonts
+}
+
+/// 依次尝试候选字体，返回第一个包含笔记中全部中日韩文字的字体；
+/// 都不满足时在错误中列出缺少最少的字体所缺的字符
+fn pick_font<'a>(
+    candidates: impl IntoIterator<Item = &'a Path>,
+    chars: &BTreeSet<char>,
+) -> Result<PdfFont, String> {
+    let mut fewest_missing: Option<Vec<char>> = None;
+    for path in candidates.into_iter().filter(|p| p.is_file()) {
+        let font = match load_font(path) {
+            Ok(font) => font,
+            Err(e) => {
+                log::warn!("跳过字体 {}: {}", path.to_string_lossy(), e);
+                continue;
+            }
+        };
+        let missing = missing_chars(&font, chars);
+        if missing.is_empty() {
+            return Ok(font);
+        }
+        log::info!(
+            "字体 {} 缺少笔记中的 {} 个字符，跳过",
+            path.to_string_lossy(),
+            missing.len()
+        );
+        if fewest_missing
+            .as_ref()
+            .map_or(true, |fewest| missing.len() < fewest.len())
+        {
+            fewest_missing = Some(missing);
+        }
+    }
+
+    let hint = "请在导出选项中指定 TrueType 字体文件（.ttf / .ttc）";
+    let missing = match fewest_missing {
+        Some(missing) => missing,
+        None if chars.is_empty() => return Err(format!("未找到可用的字体，{}", hint)),
+        None => chars.iter().copied().collect(),
+    };
+    let mut listed: String = missing.iter().take(MAX_LISTED_CHARS).collect();
+    if missing.len() > MAX_LISTED_CHARS {
+        listed.push_str(&format!(" 等 {} 个字符", missing.len()));
+    }
+    Err(format!(
+        "未找到包含笔记中全部文字的字体，缺少：{}，{}",
+        listed, hint
+    ))
+}
+
+/// 确定要嵌入的字体：优先使用选项中指定的字体，否则查找包含笔记中全部中日韩文字的系统字体，
+/// 笔记中只有西文时也可使用西文字体
+pub fn find_font(options: &PdfExportOptions, notes: &[Note]) -> Result<PdfFont, String> {
+    if let Some(path) = options.font_path.as_deref().filter(|p| !p.is_empty()) {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(format!("字体文件不存在: {}", path.to_string_lossy()));
+        }
+        return load_font(&path);
+    }
+    let chars = wide_chars(notes);
+    let candidates = candidate_fonts(!chars.is_empty());
+    pick_font(candidates.into_iter().map(Path::new), &chars)
+}
+
+/// 将笔记导出为单个 PDF 文件
+pub fn export_pdf(
+    notes: &[Note],
+    path: &Path,
+    options: &PdfExportOptions,
+) -> Result<PdfExportReport, String> {
+    let font = find_font(options, notes)?;
+    let rendered = render_pdf(notes, options, &font.bytes)?;
+    let bytes_written = export::write_atomically(path, |writer| {
+        writer
+            .write_all(&rendered.bytes)
+            .map_err(|e| format!("写入导出文件失败: {}", e))
+    })?;
+
+    Ok(PdfExportReport {
+        path: path.to_string_lossy().to_string(),
+        notes_exported: notes.len(),
+        pages: rendered.pages,
+        font_path: font.path.to_string_lossy().to_string(),
+        missing_glyphs: rendered.missing_glyphs,
+        bytes_written,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str) -> Note {
+        Note {
+            id: Some(1),
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: vec!["工作".to_string()],
+            is_favorite: 0,
+            is_deleted: 0,
+            created_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: "2024-01-02T00:00:00.000Z".to_string(),
+            reminder_date: None,
+            reminder_enabled: 0,
+        }
+    }
+
+    /// 只包含字母 A 的 TrueType 字体（来自 ttf-parser 的测试字体）
+    const TEST_FONT: &[u8] = include_bytes!("../tests/fixtures/demo.ttf");
+
+    /// 用同一个字体构造包含两个字体的字体集合
+    fn test_collection() -> Vec<u8> {
+        let num_tables = u16::from_be_bytes([TEST_FONT[4], TEST_FONT[5]]) as usize;
+        let directory = 12 + 16 * num_tables;
+        let header = 12 + 4 * 2;
+        let mut ttc = b"ttcf\0\x01\0\0\0\0\0\x02".to_vec();
+        for i in 0..2 {
+            ttc.extend_from_slice(&((header + i * directory) as u32).to_be_bytes());
+        }
+        let tables_start = header + 2 * directory;
+        for _ in 0..2 {
+            ttc.extend_from_slice(&TEST_FONT[..12]);
+            for record in TEST_FONT[12..directory].chunks(16) {
+                let offset = u32::from_be_bytes(record[8..12].try_into().unwrap()) as usize;
+                ttc.extend_from_slice(&record[..8]);
+                ttc.extend_from_slice(&((tables_start + offset) as u32).to_be_bytes());
+                ttc.extend_from_slice(&record[12..]);
+            }
+        }
+        ttc.extend_from_slice(TEST_FONT);
+        ttc
+    }
+
+    #[test]
+    fn parses_markdown_into_blocks() {
+        let blocks = parse_blocks(
+            "# 标题\n\n第一段\n继续\n\n- 一\n  - 二\n- [x] 完成\n\n1. 甲\n2. 乙\n\n> 引用\n\n```rust\nfn main() {\n\tlet x = 1;\n}\n```\n\n| 名称 | 值 |\n| --- | --- |\n| a | 1 |\n\n![图](data:image/png;base64,AAAA)\n\n---\n",
+        );
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading(1, "标题".to_string()),
+                Block::Paragraph("第一段 继续".to_string()),
+                Block::ListItem(0, "•".to_string(), "一".to_string()),
+                Block::ListItem(1, "•".to_string(), "二".to_string()),
+                Block::ListItem(0, "[x]".to_string(), "完成".to_string()),
+                Block::ListItem(0, "1.".to_string(), "甲".to_string()),
+                Block::ListItem(0, "2.".to_string(), "乙".to_string()),
+                Block::Quote("引用".to_string()),
+                Block::Code(vec![
+                    "fn main() {".to_string(),
+                    "    let x = 1;".to_string(),
+                    "}".to_string()
+                ]),
+                Block::Table(vec![
+                    vec!["名称".to_string(), "值".to_string()],
+                    vec!["a".to_string(), "1".to_string()]
+                ]),
+                Block::Image {
+                    src: "data:image/png;base64,AAAA".to_string(),
+                    alt: "图".to_string()
+                },
+                Block::Rule,
+            ]
+        );
+    }
+
+    #[test]
+    fn wraps_latin_on_spaces_and_cjk_anywhere() {
+        let width = |_: char| 1.0;
+        assert_eq!(
+            wrap_text("hello world foo", 11.0, &width),
+            vec!["hello world", "foo"]
+        );
+        assert_eq!(
+            wrap_text("中文文字换行", 4.0, &width),
+            vec!["中文文字", "换行"]
+        );
+        assert_eq!(wrap_text("abcdefgh", 3.0, &width), vec!["abc", "def", "gh"]);
+        assert_eq!(wrap_text("a\nb", 10.0, &width), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn rejects_invalid_fonts_and_empty_exports() {
+        let options = PdfExportOptions::default();
+        assert!(render_pdf(&[], &options, b"").is_err());
+        assert!(render_pdf(&[note("a", "b")], &options, b"not a font").is_err());
+    }
+
+    #[test]
+    fn loads_fonts_from_collections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fonts.ttc");
+        fs::write(&path, test_collection()).unwrap();
+
+        let font = load_font(&path).unwrap();
+        assert_eq!(font.index, 0);
+        assert!(ttf_parser::fonts_in_collection(&font.bytes).is_none());
+        let face = Face::parse(&font.bytes, 0).unwrap();
+        assert!(face.glyph_index('A').is_some());
+        assert!(render_pdf(
+            &[note("A", "AAA")],
+            &PdfExportOptions::default(),
+            &font.bytes
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn requires_fonts_covering_chinese_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("latin.ttf");
+        fs::write(&path, TEST_FONT).unwrap();
+        let font = load_font(&path).unwrap();
+        let latin = wide_chars(&[note("A", "AA A")]);
+        assert!(latin.is_empty());
+        assert!(missing_chars(&font, &latin).is_empty());
+        let chinese = wide_chars(&[note("中文", "中文笔记")]);
+        assert_eq!(missing_chars(&font, &chinese), vec!['中', '文', '笔', '记']);
+
+        assert!(pick_font([path.as_path()], &latin).is_ok());
+        let err = pick_font([path.as_path()], &chinese).err().unwrap();
+        assert!(err.contains("缺少：中文笔记"));
+        let none = dir.path().join("none.ttf");
+        assert!(pick_font([none.as_path()], &chinese)
+            .err()
+            .unwrap()
+            .contains("缺少：中文笔记"));
+        assert!(pick_font([none.as_path()], &latin)
+            .err()
+            .unwrap()
+            .contains("未找到可用的字体"));
+
+        // 只有西文时才尝试西文字体
+        assert!(candidate_fonts(false).len() > candidate_fonts(true).len());
+        assert!(candidate_fonts(true)
+            .iter()
+            .all(|font| !LATIN_FONTS.contains(font)));
+
+        let missing = PdfExportOptions {
+            font_path: Some(dir.path().join("missing.ttf").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(find_font(&missing, &[]).is_err());
+    }
+
+    #[test]
+    fn renders_notes_with_toc_and_page_numbers() {
+        let font = TEST_FONT;
+        let long: String = (0..80)
+            .map(|i| format!("Paragraph {} text.\n\n", i))
+            .collect();
+        let notes = vec![
+            note("First", &format!("# Intro\n\n{}## Details\n\n- item", long)),
+            note(
+                "Second",
+                "| a | b |\n| - | - |\n| 1 | 2 |\n\n```\ncode\n```",
+            ),
+        ];
+        let options = PdfExportOptions {
+            table_of_contents: true,
+            page_numbers: true,
+            ..Default::default()
+        };
+        let rendered = render_pdf(&notes, &options, font).unwrap();
+        assert!(rendered.bytes.starts_with(b"%PDF"));
+        // 目录 1 页 + 第一篇至少 2 页 + 第二篇 1 页
+        assert!(rendered.pages >= 4);
+    }
+}
@@ -0,0 +1,284 @@
+//! 数据库结构迁移
+//!
+//! 说明：迁移脚本放在 `src-tauri/migrations/NNN_描述.sql`，由 build.rs 在编译时
+//! 按版本号收集并嵌入程序，版本号必须从 1 开始连续编号，已发布的脚本不能再修改。
+//! 已执行的版本记录在 `schema_version` 表中；每个迁移与其版本记录在同一个事务中
+//! 提交，失败时只回滚当前迁移，数据库停留在上一个版本。
+//! 旧版本由前端 SQL 插件建立的数据库（只有 `_sqlx_migrations` 记录）从其中的版本接续
+
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::encryption;
+use crate::storage::{self, to_iso};
+
+/// 一个结构迁移（对应 migrations 目录中的一个 SQL 文件）
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaMigration {
+    /// 版本号（文件名开头的 NNN）
+    pub version: i64,
+    /// 描述（文件名中版本号之后的部分）
+    pub description: &'static str,
+    /// 迁移脚本（不能包含 BEGIN / COMMIT，事务由迁移框架管理）
+    pub sql: &'static str,
+}
+
+/// 编译时嵌入的全部迁移（按版本号升序）
+pub static MIGRATIONS: &[SchemaMigration] = include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// 当前应用支持的最新结构版本
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [name],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("读取数据库结构失败: {}", e))
+}
+
+/// 读取数据库的结构版本
+///
+/// 优先读取 `schema_version`，其次读取旧版前端 SQL 插件的 `_sqlx_migrations`；
+/// 都没有但已有 notes 表时视为版本 1，空数据库返回 0
+pub fn current_version(conn: &Connection) -> Result<i64, String> {
+    let sql = if table_exists(conn, "schema_version")? {
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version"
+    } else if table_exists(conn, "_sqlx_migrations")? {
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1"
+    } else if table_exists(conn, "notes")? {
+        return Ok(1);
+    } else {
+        return Ok(0);
+    };
+    conn.query_row(sql, [], |row| row.get(0))
+        .map_err(|e| format!("读取数据库结构版本失败: {}", e))
+}
+
+/// 执行所有尚未执行的迁移，返回本次执行的版本号
+pub fn migrate(conn: &mut Connection) -> Result<Vec<i64>, String> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+/// 打开（不存在时创建）数据库文件并执行迁移
+pub fn migrate_file(path: &Path) -> Result<Vec<i64>, String> {
+    let mut conn = Connection::open(path).map_err(|e| format!("打开数据库失败: {}", e))?;
+    conn.busy_timeout(storage::BUSY_TIMEOUT)
+        .map_err(|e| format!("设置数据库超时失败: {}", e))?;
+    encryption::apply_key(&conn, path)?;
+    migrate(&mut conn)
+}
+
+fn apply_migrations(
+    conn: &mut Connection,
+    migrations: &[SchemaMigration],
+) -> Result<Vec<i64>, String> {
+    let current = current_version(conn)?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > latest {
+        return Err(format!(
+            "数据库结构版本为 {}，高于当前应用支持的版本 {}，请先升级应用",
+            current, latest
+        ));
+    }
+
+    // 首次使用迁移框架：建表并补记旧数据库中已经执行过的版本
+    if !table_exists(conn, "schema_version")? {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("开启事务失败: {}", e))?;
+        tx.execute_batch(
+            "CREATE TABLE schema_version (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at TEXT NOT NULL
+            );",
+        )
+        .map_err(|e| format!("创建 schema_version 表失败: {}", e))?;
+        let now = to_iso(chrono::Utc::now());
+        for migration in migrations.iter().filter(|m| m.version <= current) {
+            tx.execute(
+                "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+                params![migration.version, migration.description, now],
+            )
+            .map_err(|e| format!("记录数据库结构版本失败: {}", e))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("创建 schema_version 表失败: {}", e))?;
+    }
+
+    let mut applied = Vec::new();
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let name = format!("{:03} {}", migration.version, migration.description);
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("开启事务失败: {}", e))?;
+        tx.execute_batch(migration.sql)
+            .map_err(|e| format!("执行数据库迁移 {} 失败: {}", name, e))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![
+                migration.version,
+                migration.description,
+                to_iso(chrono::Utc::now())
+            ],
+        )
+        .map_err(|e| format!("记录数据库结构版本失败: {}", e))?;
+        tx.commit()
+            .map_err(|e| format!("提交数据库迁移 {} 失败: {}", name, e))?;
+        log::info!("已执行数据库迁移 {}", name);
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个已发布版本的数据库结构（含示例数据），新增发布版本时在这里追加
+    const RELEASED_SCHEMAS: &[(&str, &str)] =
+        &[("v1", include_str!("../tests/fixtures/schema_v1.sql"))];
+
+    /// 读取表和索引的定义（不含迁移记录表），用于比较两个数据库的结构
+    fn schema_of(conn: &Connection) -> Vec<(String, String)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, sql FROM sqlite_master
+                 WHERE sql IS NOT NULL AND name NOT IN ('_sqlx_migrations', 'sqlite_sequence')
+                 ORDER BY name",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn recorded_versions(conn: &Connection) -> Vec<i64> {
+        let mut stmt = conn
+            .prepare("SELECT version FROM schema_version ORDER BY version")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+        let columns: Vec<String> = stmt
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        columns.iter().any(|c| c == column)
+    }
+
+    #[test]
+    fn migrations_are_numbered_consecutively() {
+        assert!(!MIGRATIONS.is_empty());
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(!migration.sql.trim().is_empty());
+        }
+        assert_eq!(MIGRATIONS[0].description, "initial");
+    }
+
+    #[test]
+    fn applies_every_migration_to_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        let applied = migrate(&mut conn).unwrap();
+        let all: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(applied, all);
+        assert_eq!(recorded_versions(&conn), all);
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(storage::integrity_check(&conn).unwrap().is_empty());
+        assert_eq!(storage::count_rows(&conn, "notes").unwrap(), 0);
+
+        // 再次执行时没有需要执行的迁移
+        assert!(migrate(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn upgrades_every_released_schema() {
+        let mut fresh = Connection::open_in_memory().unwrap();
+        migrate(&mut fresh).unwrap();
+        let expected = schema_of(&fresh);
+
+        for (name, fixture) in RELEASED_SCHEMAS {
+            let mut conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(fixture).unwrap();
+            let notes = storage::count_rows(&conn, "notes").unwrap();
+            let messages = storage::count_rows(&conn, "chat_messages").unwrap();
+            assert!(notes > 0, "{} 缺少示例数据", name);
+
+            migrate(&mut conn).unwrap_or_else(|e| panic!("升级 {} 失败: {}", name, e));
+            assert_eq!(
+                current_version(&conn).unwrap(),
+                latest_version(),
+                "{}",
+                name
+            );
+            assert_eq!(
+                schema_of(&conn),
+                expected,
+                "{} 升级后的结构与新建数据库不一致",
+                name
+            );
+            assert_eq!(storage::count_rows(&conn, "notes").unwrap(), notes);
+            assert_eq!(
+                storage::count_rows(&conn, "chat_messages").unwrap(),
+                messages
+            );
+            assert!(storage::integrity_check(&conn).unwrap().is_empty());
+            assert!(storage::read_notes(&conn).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_to_previous_version() {
+        let initial = MIGRATIONS[0];
+        let broken = SchemaMigration {
+            version: 2,
+            description: "add pinned",
+            sql: "ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+                  INSERT INTO missing_table VALUES (1);",
+        };
+        let mut conn = Connection::open_in_memory().unwrap();
+        let err = apply_migrations(&mut conn, &[initial, broken]).unwrap_err();
+        assert!(err.contains("002 add pinned"));
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(!has_column(&conn, "notes", "pinned"));
+
+        let fixed = SchemaMigration {
+            sql: "ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;",
+            ..broken
+        };
+        assert_eq!(
+            apply_migrations(&mut conn, &[initial, fixed]).unwrap(),
+            vec![2]
+        );
+        assert_eq!(current_version(&conn).unwrap(), 2);
+        assert!(has_column(&conn, "notes", "pinned"));
+    }
+
+    #[test]
+    fn refuses_database_from_newer_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at)
+             VALUES (?1, 'future', '2030-01-01T00:00:00.000Z')",
+            [latest_version() + 1],
+        )
+        .unwrap();
+        assert!(migrate(&mut conn).unwrap_err().contains("请先升级应用"));
+    }
+}
@@ -0,0 +1,554 @@
+//! 静态 HTML 网站导出
+//!
+//! 说明：生成 `index.html`、每篇笔记一个页面（`notes/`）、每个标签一个列表页（`tags/`）
+//! 以及按创建时间排列的归档页，所有页面共用一个 `style.css`。
+//! 页面之间只使用相对链接，图片保持 data URL 内嵌，可直接通过 `file://` 离线浏览。
+//! 笔记可能来自 ENEX、Notion 等导入，其中的原始 HTML 默认转义为文本，
+//! `javascript:` 等可执行脚本的链接和图片地址会被移除（链接只保留文字，图片只保留替代文字）
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use crate::markdown::{sanitize_file_name, unique_file_stem};
+use crate::models::{HtmlExportOptions, HtmlExportReport, Note};
+use crate::notion::NOTE_LINK_PREFIX;
+
+/// 默认网站标题
+const DEFAULT_SITE_TITLE: &str = "JD Notes";
+
+const STYLE_FILE: &str = "style.css";
+
+/// 链接地址中需要转义的字符（文件名可能包含空格、`#`、`%` 等）
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`');
+
+const STYLE: &str = r#":root {
+  color-scheme: light dark;
+  --text: #1f2328;
+  --muted: #656d76;
+  --border: #d0d7de;
+  --accent: #0969da;
+  --code-bg: #f6f8fa;
+}
+@media (prefers-color-scheme: dark) {
+  :root {
+    --text: #e6edf3;
+    --muted: #8d96a0;
+    --border: #30363d;
+    --accent: #4493f8;
+    --code-bg: #161b22;
+  }
+}
+body {
+  margin: 0 auto;
+  max-width: 48rem;
+  padding: 0 1rem 3rem;
+  font: 16px/1.7 -apple-system, BlinkMacSystemFont, "Segoe UI", "PingFang SC", "Microsoft YaHei", sans-serif;
+  color: var(--text);
+}
+a { color: var(--accent); text-decoration: none; }
+a:hover { text-decoration: underline; }
+header {
+  display: flex;
+  flex-wrap: wrap;
+  align-items: baseline;
+  justify-content: space-between;
+  gap: 0.5rem;
+  padding: 1.5rem 0 1rem;
+  border-bottom: 1px solid var(--border);
+  margin-bottom: 1.5rem;
+}
+.site-title { font-size: 1.25rem; font-weight: 600; color: var(--text); }
+nav a { margin-left: 1rem; }
+.meta, time, .count { color: var(--muted); font-size: 0.875rem; }
+ul.notes { list-style: none; padding: 0; }
+ul.notes li { display: flex; justify-content: space-between; gap: 1rem; padding: 0.35rem 0; border-bottom: 1px dashed var(--border); }
+.tags { display: flex; flex-wrap: wrap; gap: 0.5rem; padding: 0; list-style: none; }
+.tags a { padding: 0 0.5rem; border: 1px solid var(--border); border-radius: 999px; font-size: 0.875rem; }
+article img { max-width: 100%; }
+pre, code { background: var(--code-bg); border-radius: 4px; font-family: ui-monospace, SFMono-Regular, Consolas, monospace; }
+code { padding: 0.1em 0.3em; }
+pre { padding: 0.75rem 1rem; overflow-x: auto; }
+pre code { padding: 0; }
+blockquote { margin: 0; padding-left: 1rem; border-left: 4px solid var(--border); color: var(--muted); }
+table { border-collapse: collapse; }
+th, td { border: 1px solid var(--border); padding: 0.3rem 0.6rem; }
+"#;
+
+/// 生成的单个网站文件
+pub struct SiteFile {
+    /// 相对于输出目录的路径
+    pub path: String,
+    pub content: String,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn href(path: &str) -> String {
+    utf8_percent_encode(path, HREF_ENCODE_SET).to_string()
+}
+
+/// ISO 时间的日期部分
+fn date_of(iso: &str) -> &str {
+    iso.get(..10).unwrap_or(iso)
+}
+
+/// 页面公共布局，root 为页面到网站根目录的相对前缀（如 `../`）
+fn layout(site_title: &str, page_title: &str, root: &str, main: &str) -> String {
+    let site = escape_html(site_title);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{page} - {site}</title>
+<link rel="stylesheet" href="{root}{style}">
+</head>
+<body>
+<header>
+<a class="site-title" href="{root}index.html">{site}</a>
+<nav><a href="{root}index.html">全部笔记</a><a href="{root}tags.html">标签</a><a href="{root}archive.html">归档</a></nav>
+</header>
+<main>
+{main}
+</main>
+</body>
+</html>
+"#,
+        page = escape_html(page_title),
+        site = site,
+        root = root,
+        style = STYLE_FILE,
+        main = main,
+    )
+}
+
+/// 链接或图片地址是否可以安全地写入页面：拒绝 `javascript:`、`vbscript:`，
+/// `data:` 只允许用于图片
+fn is_safe_url(url: &str, is_image: bool) -> bool {
+    // 浏览器解析协议时会忽略空白和控制字符，如 `java\tscript:`
+    let normalized: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(32)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let Some((scheme, _)) = normalized.split_once(':') else {
+        return true;
+    };
+    // 冒号出现在路径、查询或片段中时不是协议
+    if scheme.contains(['/', '?', '#']) {
+        return true;
+    }
+    match scheme {
+        "javascript" | "vbscript" => false,
+        "data" => is_image && normalized.starts_with("data:image/"),
+        _ => true,
+    }
+}
+
+/// 将笔记 Markdown 渲染为 HTML；指向已导出笔记的 `jdnotes://note/<ID>` 链接改为页面链接，
+/// 指向未导出笔记的链接只保留文字；allow_raw_html 为 false 时原始 HTML 转义为文本
+fn render_content(
+    content: &str,
+    note_files: &HashMap<i64, String>,
+    allow_raw_html: bool,
+) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    // 记录每层链接、图片是否保留，用于丢弃对应的结束标签
+    let mut kept_links = Vec::new();
+    let mut kept_images = Vec::new();
+    let events = Parser::new_ext(content, options).filter_map(|event| match event {
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = match dest_url.strip_prefix(NOTE_LINK_PREFIX) {
+                Some(note_id) => {
+                    let file = note_id
+                        .parse::<i64>()
+                        .ok()
+                        .and_then(|note_id| note_files.get(&note_id));
+                    match file {
+                        Some(file) => href(file).into(),
+                        None => {
+                            kept_links.push(false);
+                            return None;
+                        }
+                    }
+                }
+                None if is_safe_url(&dest_url, false) => dest_url,
+                None => {
+                    kept_links.push(false);
+                    return None;
+                }
+            };
+            kept_links.push(true);
+            Some(Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }))
+        }
+        Event::End(TagEnd::Link) => kept_links.pop().unwrap_or(true).then_some(event),
+        Event::Start(Tag::Image { ref dest_url, .. }) => {
+            let safe = is_safe_url(dest_url, true);
+            kept_images.push(safe);
+            safe.then_some(event)
+        }
+        Event::End(TagEnd::Image) => kept_images.pop().unwrap_or(true).then_some(event),
+        Event::Html(html) | Event::InlineHtml(html) if !allow_raw_html => Some(Event::Text(html)),
+        event => Some(event),
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+/// 笔记列表（root 为当前页面到网站根目录的相对前缀）
+fn note_list(notes: &[&Note], note_files: &HashMap<i64, String>, root: &str) -> String {
+    let mut list = String::from("<ul class=\"notes\">\n");
+    for note in notes {
+        let Some(file) = note.id.and_then(|id| note_files.get(&id)) else {
+            continue;
+        };
+        list.push_str(&format!(
+            "<li><a href=\"{}notes/{}\">{}</a><time>{}</time></li>\n",
+            root,
+            href(file),
+            escape_html(&note.title),
+            date_of(&note.created_at)
+        ));
+    }
+    list.push_str("</ul>");
+    list
+}
+
+/// 生成网站的全部文件（不写入磁盘）
+pub fn render_site(notes: &[Note], options: &HtmlExportOptions) -> Vec<SiteFile> {
+    let site_title = options
+        .site_title
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or(DEFAULT_SITE_TITLE);
+
+    // 没有 ID 的笔记无法被链接，按下标生成临时 ID 不影响数据库
+    let notes: Vec<Note> = notes
+        .iter()
+        .enumerate()
+        .map(|(index, note)| Note {
+            id: note.id.or(Some(-(index as i64) - 1)),
+            ..note.clone()
+        })
+        .collect();
+
+    let mut used = HashSet::new();
+    let note_files: HashMap<i64, String> = notes
+        .iter()
+        .map(|note| {
+            let stem = unique_file_stem(&sanitize_file_name(&note.title), &mut used);
+            (note.id.unwrap_or_default(), format!("{}.html", stem))
+        })
+        .collect();
+
+    let mut by_tag: BTreeMap<&str, Vec<&Note>> = BTreeMap::new();
+    for note in &notes {
+        for tag in &note.tags {
+            by_tag.entry(tag.as_str()).or_default().push(note);
+        }
+    }
+    let mut used_tags = HashSet::new();
+    let tag_files: HashMap<&str, String> = by_tag
+        .keys()
+        .map(|tag| {
+            let stem = unique_file_stem(&sanitize_file_name(tag), &mut used_tags);
+            (*tag, format!("{}.html", stem))
+        })
+        .collect();
+
+    let mut files = vec![SiteFile {
+        path: STYLE_FILE.to_string(),
+        content: STYLE.to_string(),
+    }];
+
+    for note in &notes {
+        let file = &note_files[&note.id.unwrap_or_default()];
+        let mut main = format!(
+            "<article>\n<h1>{}</h1>\n<p class=\"meta\">创建于 <time>{}</time> · 更新于 <time>{}</time></p>\n",
+            escape_html(&note.title),
+            date_of(&note.created_at),
+            date_of(&note.updated_at)
+        );
+        if !note.tags.is_empty() {
+            main.push_str("<ul class=\"tags\">");
+            for tag in &note.tags {
+                main.push_str(&format!(
+                    "<li><a href=\"../tags/{}\">{}</a></li>",
+                    href(&tag_files[tag.as_str()]),
+                    escape_html(tag)
+                ));
+            }
+            main.push_str("</ul>\n");
+        }
+        main.push_str(&render_content(
+            &note.content,
+            &note_files,
+            options.allow_raw_html,
+        ));
+        main.push_str("</article>");
+        files.push(SiteFile {
+            path: format!("notes/{}", file),
+            content: layout(site_title, &note.title, "../", &main),
+        });
+    }
+
+    let mut recent: Vec<&Note> = notes.iter().collect();
+    recent.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    files.push(SiteFile {
+        path: "index.html".to_string(),
+        content: layout(
+            site_title,
+            "全部笔记",
+            "",
+            &format!("<h1>全部笔记</h1>\n{}", note_list(&recent, &note_files, "")),
+        ),
+    });
+
+    let mut tag_index = String::from("<h1>标签</h1>\n<ul class=\"tags\">\n");
+    for (tag, tagged) in &by_tag {
+        let file = &tag_files[tag];
+        tag_index.push_str(&format!(
+            "<li><a href=\"tags/{}\">{}</a> <span class=\"count\">{}</span></li>\n",
+            href(file),
+            escape_html(tag),
+            tagged.len()
+        ));
+        let mut tagged = tagged.clone();
+        tagged.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        files.push(SiteFile {
+            path: format!("tags/{}", file),
+            content: layout(
+                site_title,
+                tag,
+                "../",
+                &format!(
+                    "<h1>标签：{}</h1>\n{}",
+                    escape_html(tag),
+                    note_list(&tagged, &note_files, "../")
+                ),
+            ),
+        });
+    }
+    tag_index.push_str("</ul>");
+    files.push(SiteFile {
+        path: "tags.html".to_string(),
+        content: layout(site_title, "标签", "", &tag_index),
+    });
+
+    // 归档页按创建时间的年月分组，最新的在前
+    let mut by_month: BTreeMap<&str, Vec<&Note>> = BTreeMap::new();
+    for note in &notes {
+        let month = note.created_at.get(..7).unwrap_or(&note.created_at);
+        by_month.entry(month).or_default().push(note);
+    }
+    let mut archive = String::from("<h1>归档</h1>\n");
+    for (month, mut month_notes) in by_month.into_iter().rev() {
+        month_notes.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        archive.push_str(&format!(
+            "<h2 id=\"{}\">{}</h2>\n{}\n",
+            escape_html(month),
+            escape_html(month),
+            note_list(&month_notes, &note_files, "")
+        ));
+    }
+    files.push(SiteFile {
+        path: "archive.html".to_string(),
+        content: layout(site_title, "归档", "", &archive),
+    });
+
+    files
+}
+
+/// 将笔记导出为静态网站，已存在的同名文件会被覆盖
+pub fn export_site(
+    notes: &[Note],
+    output_dir: &Path,
+    options: &HtmlExportOptions,
+) -> Result<HtmlExportReport, String> {
+    let mut report = HtmlExportReport {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        notes_exported: notes.len(),
+        ..Default::default()
+    };
+
+    for dir in ["notes", "tags"] {
+        fs::create_dir_all(output_dir.join(dir)).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+    for file in render_site(notes, options) {
+        if file.path.starts_with("tags/") {
+            report.tag_pages += 1;
+        }
+        let path = output_dir.join(&file.path);
+        fs::write(&path, file.content)
+            .map_err(|e| format!("写入 {} 失败: {}", path.to_string_lossy(), e))?;
+        report.files_written += 1;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: i64, title: &str, content: &str, tags: &[&str], created_at: &str) -> Note {
+        Note {
+            id: Some(id),
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            is_favorite: 0,
+            is_deleted: 0,
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+            reminder_date: None,
+            reminder_enabled: 0,
+        }
+    }
+
+    fn find<'a>(files: &'a [SiteFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|f| f.path == path)
+            .unwrap_or_else(|| panic!("缺少 {}", path))
+            .content
+    }
+
+    #[test]
+    fn renders_pages_for_notes_tags_and_archive() {
+        let notes = vec![
+            note(
+                1,
+                "周报 #1",
+                "# 本周\n\n见 [计划](jdnotes://note/2) 和 [旧笔记](jdnotes://note/9)",
+                &["工作"],
+                "2024-01-05T08:00:00.000Z",
+            ),
+            note(
+                2,
+                "计划 <草稿>",
+                "- [x] 完成",
+                &["工作", "个人"],
+                "2024-02-01T08:00:00.000Z",
+            ),
+        ];
+        let files = render_site(&notes, &HtmlExportOptions::default());
+
+        let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert!(paths.contains(&"style.css"));
+        assert!(paths.contains(&"notes/周报 #1.html"));
+        assert!(paths.contains(&"tags/工作.html"));
+
+        let first = find(&files, "notes/周报 #1.html");
+        assert!(first.contains("<link rel=\"stylesheet\" href=\"../style.css\">"));
+        assert!(first.contains(&format!(
+            "<a href=\"{}\">计划</a>",
+            href("计划 _草稿_.html")
+        )));
+        assert!(first.contains("和 旧笔记"));
+        assert!(first.contains("href=\"../tags/%E5%B7%A5%E4%BD%9C.html\""));
+
+        let index = find(&files, "index.html");
+        assert!(index.contains("计划 &lt;草稿&gt;"));
+        assert!(index.contains("notes/%E5%91%A8%E6%8A%A5%20%231.html"));
+        assert!(index.find("计划").unwrap() < index.find("周报").unwrap());
+
+        let archive = find(&files, "archive.html");
+        assert!(archive.find("2024-02").unwrap() < archive.find("2024-01").unwrap());
+        assert!(find(&files, "tags.html").contains("<span class=\"count\">2</span>"));
+    }
+
+    #[test]
+    fn escapes_raw_html_and_drops_script_urls() {
+        let files = HashMap::new();
+        let content = "<script>alert(1)</script>\n\n\
+            段落 <img src=x onerror=alert(1)> \
+            [点我](javascript:alert(1)) [大小写](JaVaScRiPt:alert(1)) [正常](https://example.com/a:b) \
+            ![图](data:image/png;base64,AAAA) ![坏图](javascript:alert(1))";
+        let html = render_content(content, &files, false);
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<img src=x"));
+        assert!(!html.to_lowercase().contains("javascript:"));
+        assert!(html.contains("点我"));
+        assert!(html.contains("<a href=\"https://example.com/a:b\">正常</a>"));
+        assert!(html.contains("<img src=\"data:image/png;base64,AAAA\" alt=\"图\""));
+        assert!(html.contains("坏图"));
+
+        assert!(!is_safe_url(" java\tscript:alert(1)", false));
+        assert!(!is_safe_url("data:text/html,<script>", false));
+        assert!(is_safe_url("notes/a.html?x=1:2", false));
+
+        let raw = render_content("<u>下划线</u>", &files, true);
+        assert!(raw.contains("<u>下划线</u>"));
+    }
+
+    #[test]
+    fn writes_site_to_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = vec![note(
+            1,
+            "笔记",
+            "正文",
+            &["标签"],
+            "2024-01-01T00:00:00.000Z",
+        )];
+        let options = HtmlExportOptions {
+            site_title: Some("我的笔记".to_string()),
+            ..Default::default()
+        };
+        let report = export_site(&notes, dir.path(), &options).unwrap();
+        assert_eq!(report.notes_exported, 1);
+        assert_eq!(report.tag_pages, 1);
+        assert_eq!(report.files_written, 6);
+
+        let page = fs::read_to_string(dir.path().join("notes/笔记.html")).unwrap();
+        assert!(page.contains("<title>笔记 - 我的笔记</title>"));
+        assert!(page.contains("<p>正文</p>"));
+        assert!(dir.path().join("style.css").exists());
+    }
+}
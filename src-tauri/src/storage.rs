@@ -0,0 +1,886 @@
+//! 后端直接访问 SQLite 数据库
+//!
+//! 说明：日常 CRUD 仍由前端通过 tauri-plugin-sql 完成
+//! 这里提供导入导出、备份等需要在后端读写数据库的能力，
+//! 即使前端 WebView 异常也能完成数据备份
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use rusqlite::{params, Connection, OpenFlags, Row, Transaction};
+use serde_json::Value;
+
+use crate::conflict;
+use crate::encryption;
+use crate::schema;
+use crate::models::{
+    ChatMessage, DeletedFilter, ExportData, ExportFilter, ImportIssue, ImportOptions, ImportReport,
+    Note, NoteAction, TagMatch,
+};
+
+/// 当前导出格式版本（版本历史与升级链见 export_format.rs）
+pub const EXPORT_VERSION: &str = "2.0";
+
+/// 等待前端连接释放写锁的最长时间
+pub(crate) const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 小于该值的数字时间戳按秒处理，否则按毫秒处理
+const SECONDS_THRESHOLD: f64 = 100_000_000_000.0;
+
+/// 转换为与 JS `Date.toISOString()` 一致的格式
+pub fn to_iso(dt: DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 将时间戳（毫秒/秒）、ISO 字符串或 Date 序列化结果统一为 ISO 8601
+pub fn normalize_timestamp(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64()?;
+            let millis = if n.abs() < SECONDS_THRESHOLD {
+                n * 1000.0
+            } else {
+                n
+            };
+            Utc.timestamp_millis_opt(millis as i64).single().map(to_iso)
+        }
+        Value::String(s) => parse_date_string(s.trim()),
+        // 部分导出工具会把 Date 序列化为 { "$date": ... }
+        Value::Object(map) => map.get("$date").and_then(normalize_timestamp),
+        _ => None,
+    }
+}
+
+/// 解析常见的日期字符串格式
+fn parse_date_string(s: &str) -> Option<String> {
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(n) = s.parse::<f64>() {
+        return normalize_timestamp(&serde_json::json!(n));
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(to_iso(dt.with_timezone(&Utc)));
+    }
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(to_iso(dt.and_utc()));
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| to_iso(dt.and_utc()))
+}
+
+/// 以只读方式打开数据库（用于导出）
+pub fn open_read_only(path: &Path) -> Result<Connection, String> {
+    if !path.exists() {
+        return Err(format!("数据库文件不存在: {}", path.to_string_lossy()));
+    }
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("打开数据库失败: {}", e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("设置数据库超时失败: {}", e))?;
+    encryption::apply_key(&conn, path)?;
+    Ok(conn)
+}
+
+/// 以读写方式打开数据库（用于导入）
+pub fn open_read_write(path: &Path) -> Result<Connection, String> {
+    if !path.exists() {
+        return Err(format!("数据库文件不存在: {}", path.to_string_lossy()));
+    }
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("打开数据库失败: {}", e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("设置数据库超时失败: {}", e))?;
+    encryption::apply_key(&conn, path)?;
+    Ok(conn)
+}
+
+/// 解析 tags 列中的 JSON 数组，格式错误时返回空数组
+pub fn decode_tags(raw: &str) -> Vec<String> {
+    if raw.trim().is_empty() {
+        return Vec::new();
+    }
+    match serde_json::from_str::<Vec<String>>(raw) {
+        Ok(tags) => tags,
+        Err(e) => {
+            log::warn!("标签 JSON 解析失败，已忽略: {} ({})", raw, e);
+            Vec::new()
+        }
+    }
+}
+
+/// 将 notes 表的一行转换为 Note
+fn row_to_note(row: &Row) -> rusqlite::Result<Note> {
+    let tags: String = row.get("tags")?;
+    Ok(Note {
+        id: row.get("id")?,
+        title: row.get("title")?,
+        content: row.get("content")?,
+        tags: decode_tags(&tags),
+        is_favorite: row.get("is_favorite")?,
+        is_deleted: row.get("is_deleted")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        reminder_date: row.get("reminder_date")?,
+        reminder_enabled: row.get("reminder_enabled")?,
+    })
+}
+
+/// 将 chat_messages 表的一行转换为 ChatMessage
+fn row_to_chat_message(row: &Row) -> rusqlite::Result<ChatMessage> {
+    Ok(ChatMessage {
+        id: row.get("id")?,
+        note_id: row.get("note_id")?,
+        role: row.get("role")?,
+        content: row.get("content")?,
+        timestamp: row.get("timestamp")?,
+    })
+}
+
+/// 逐行遍历全部笔记（包括废纸篓中的笔记），不会一次性加载到内存
+pub fn for_each_note<F>(conn: &Connection, mut f: F) -> Result<(), String>
+where
+    F: FnMut(Note) -> Result<(), String>,
+{
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, content, tags, is_favorite, is_deleted, created_at, updated_at, \
+             reminder_date, reminder_enabled FROM notes ORDER BY id",
+        )
+        .map_err(|e| format!("查询笔记失败: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_note)
+        .map_err(|e| format!("查询笔记失败: {}", e))?;
+    for note in rows {
+        f(note.map_err(|e| format!("读取笔记失败: {}", e))?)?;
+    }
+    Ok(())
+}
+
+/// 逐行遍历全部聊天消息
+pub fn for_each_chat_message<F>(conn: &Connection, mut f: F) -> Result<(), String>
+where
+    F: FnMut(ChatMessage) -> Result<(), String>,
+{
+    let mut stmt = conn
+        .prepare("SELECT id, note_id, role, content, timestamp FROM chat_messages ORDER BY id")
+        .map_err(|e| format!("查询聊天消息失败: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_chat_message)
+        .map_err(|e| format!("查询聊天消息失败: {}", e))?;
+    for message in rows {
+        f(message.map_err(|e| format!("读取聊天消息失败: {}", e))?)?;
+    }
+    Ok(())
+}
+
+/// 读取全部笔记（包括废纸篓中的笔记）
+pub fn read_notes(conn: &Connection) -> Result<Vec<Note>, String> {
+    let mut notes = Vec::new();
+    for_each_note(conn, |note| {
+        notes.push(note);
+        Ok(())
+    })?;
+    Ok(notes)
+}
+
+/// 校验筛选条件并把时间范围统一为 ISO 8601，便于与笔记时间直接比较
+pub fn prepare_filter(filter: &ExportFilter) -> Result<ExportFilter, String> {
+    let normalize = |name: &str, bound: &Option<String>| -> Result<Option<String>, String> {
+        match bound.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+            Some(raw) => parse_date_string(raw)
+                .map(Some)
+                .ok_or_else(|| format!("筛选条件 {} 不是有效的时间: {}", name, raw)),
+            None => Ok(None),
+        }
+    };
+    Ok(ExportFilter {
+        created_from: normalize("created_from", &filter.created_from)?,
+        created_to: normalize("created_to", &filter.created_to)?,
+        updated_from: normalize("updated_from", &filter.updated_from)?,
+        updated_to: normalize("updated_to", &filter.updated_to)?,
+        ..filter.clone()
+    })
+}
+
+/// 判断时间是否落在 `[from, to)` 范围内
+fn in_range(time: &str, from: &Option<String>, to: &Option<String>) -> bool {
+    if from.is_none() && to.is_none() {
+        return true;
+    }
+    let time = parse_date_string(time.trim()).unwrap_or_else(|| time.to_string());
+    from.as_ref().map_or(true, |from| time >= *from) && to.as_ref().map_or(true, |to| time < *to)
+}
+
+/// 判断笔记是否满足导出筛选条件（filter 需先经过 prepare_filter 处理）
+pub fn matches_filter(note: &Note, filter: &ExportFilter) -> bool {
+    let deleted_ok = match filter.deleted {
+        DeletedFilter::Exclude => note.is_deleted == 0,
+        DeletedFilter::Include => true,
+        DeletedFilter::Only => note.is_deleted != 0,
+    };
+    let tags_ok = filter.tags.is_empty()
+        || match filter.tag_match {
+            TagMatch::Any => filter.tags.iter().any(|t| note.tags.contains(t)),
+            TagMatch::All => filter.tags.iter().all(|t| note.tags.contains(t)),
+        };
+    deleted_ok
+        && tags_ok
+        && filter
+            .is_favorite
+            .map_or(true, |favorite| (note.is_favorite != 0) == favorite)
+        && (filter.note_ids.is_empty() || note.id.is_some_and(|id| filter.note_ids.contains(&id)))
+        && in_range(&note.created_at, &filter.created_from, &filter.created_to)
+        && in_range(&note.updated_at, &filter.updated_from, &filter.updated_to)
+}
+
+/// 读取满足筛选条件的笔记
+pub fn read_filtered_notes(conn: &Connection, filter: &ExportFilter) -> Result<Vec<Note>, String> {
+    let filter = prepare_filter(filter)?;
+    let mut notes = Vec::new();
+    for_each_note(conn, |note| {
+        if matches_filter(&note, &filter) {
+            notes.push(note);
+        }
+        Ok(())
+    })?;
+    Ok(notes)
+}
+
+/// 读取全部聊天消息
+pub fn read_chat_messages(conn: &Connection) -> Result<Vec<ChatMessage>, String> {
+    let mut messages = Vec::new();
+    for_each_chat_message(conn, |message| {
+        messages.push(message);
+        Ok(())
+    })?;
+    Ok(messages)
+}
+
+/// 统计表中的行数
+pub fn count_rows(conn: &Connection, table: &str) -> Result<u64, String> {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|n| n as u64)
+    .map_err(|e| format!("统计 {} 失败: {}", table, e))
+}
+
+/// 从数据库构建完整的导出数据
+pub fn build_export(conn: &Connection) -> Result<ExportData, String> {
+    Ok(ExportData {
+        version: EXPORT_VERSION.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        notes: read_notes(conn)?,
+        chat_messages: read_chat_messages(conn)?,
+    })
+}
+
+/// 构建导出数据：未提供筛选条件时导出全部数据（包括废纸篓），
+/// 否则只导出满足条件的笔记及其聊天消息
+pub fn build_filtered_export(
+    conn: &Connection,
+    filter: Option<&ExportFilter>,
+) -> Result<ExportData, String> {
+    let Some(filter) = filter else {
+        return build_export(conn);
+    };
+    let notes = read_filtered_notes(conn, filter)?;
+    let ids: HashSet<i64> = notes.iter().filter_map(|note| note.id).collect();
+    let mut chat_messages = Vec::new();
+    for_each_chat_message(conn, |message| {
+        if ids.contains(&message.note_id) {
+            chat_messages.push(message);
+        }
+        Ok(())
+    })?;
+    Ok(ExportData {
+        version: EXPORT_VERSION.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        notes,
+        chat_messages,
+    })
+}
+
+/// 插入一条笔记（忽略 note.id，由数据库分配新 ID），返回新 ID
+pub fn insert_note(tx: &Transaction, note: &Note) -> rusqlite::Result<i64> {
+    let tags = serde_json::to_string(&note.tags).unwrap_or_else(|_| "[]".to_string());
+    tx.execute(
+        "INSERT INTO notes (title, content, tags, is_favorite, is_deleted, created_at, updated_at, \
+         reminder_date, reminder_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            note.title,
+            note.content,
+            tags,
+            note.is_favorite,
+            note.is_deleted,
+            note.created_at,
+            note.updated_at,
+            note.reminder_date,
+            note.reminder_enabled,
+        ],
+    )?;
+    Ok(tx.last_insert_rowid())
+}
+
+/// 插入一条聊天消息，note_id 使用调用方传入的新笔记 ID，返回新 ID
+pub fn insert_chat_message(
+    tx: &Transaction,
+    note_id: i64,
+    message: &ChatMessage,
+) -> rusqlite::Result<i64> {
+    tx.execute(
+        "INSERT INTO chat_messages (note_id, role, content, timestamp) VALUES (?1, ?2, ?3, ?4)",
+        params![note_id, message.role, message.content, message.timestamp],
+    )?;
+    Ok(tx.last_insert_rowid())
+}
+
+/// 构造导入问题记录
+pub fn import_issue(
+    kind: &str,
+    index: usize,
+    source_id: Option<i64>,
+    reason: String,
+) -> ImportIssue {
+    ImportIssue {
+        kind: kind.to_string(),
+        index,
+        source_id,
+        reason,
+    }
+}
+
+/// 在单个事务中导入 ExportData
+/// 1. 按冲突策略插入或覆盖笔记，记录旧 ID 到新 ID 的映射
+/// 2. 按映射改写聊天消息的 note_id，找不到对应笔记或笔记被跳过的消息跳过
+/// 3. 任意记录失败时整体回滚，报告中列出全部失败记录
+///
+/// 覆盖已有笔记时，其原有聊天记录会被导入数据中的聊天记录替换
+pub fn import_export_data(
+    conn: &mut Connection,
+    data: &ExportData,
+    options: &ImportOptions,
+) -> Result<ImportReport, String> {
+    let actions = conflict::plan(conn, &data.notes, &options.conflict)?;
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    if options.dry_run {
+        report.actions = actions;
+        return Ok(report);
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    // 旧笔记 ID -> 新笔记 ID（None 表示该笔记被跳过）
+    let mut id_map: HashMap<i64, Option<i64>> = HashMap::new();
+
+    for (note, action) in data.notes.iter().zip(&actions) {
+        let written = conflict::apply(&tx, note, action).and_then(|written| {
+            if let (Some(id), NoteAction::Overwrite) = (written, action.action) {
+                tx.execute("DELETE FROM chat_messages WHERE note_id = ?1", [id])?;
+            }
+            Ok(written)
+        });
+        match written {
+            Ok(written) => {
+                match (written, action.action) {
+                    (None, _) => {}
+                    (Some(_), NoteAction::Overwrite) => report.notes_updated += 1,
+                    (Some(_), _) => report.notes_inserted += 1,
+                }
+                if let Some(old_id) = note.id {
+                    id_map.insert(old_id, written);
+                }
+            }
+            Err(e) => {
+                report
+                    .failed
+                    .push(import_issue("note", action.index, note.id, e.to_string()))
+            }
+        }
+    }
+
+    for (index, message) in data.chat_messages.iter().enumerate() {
+        let note_id = match id_map.get(&message.note_id) {
+            Some(Some(note_id)) => *note_id,
+            Some(None) => {
+                report.skipped.push(import_issue(
+                    "chat_message",
+                    index,
+                    message.id,
+                    format!("所属笔记已跳过 (note_id = {})", message.note_id),
+                ));
+                continue;
+            }
+            None => {
+                report.skipped.push(import_issue(
+                    "chat_message",
+                    index,
+                    message.id,
+                    format!("找不到对应的笔记 (note_id = {})", message.note_id),
+                ));
+                continue;
+            }
+        };
+        match insert_chat_message(&tx, note_id, message) {
+            Ok(_) => report.messages_inserted += 1,
+            Err(e) => report.failed.push(import_issue(
+                "chat_message",
+                index,
+                message.id,
+                e.to_string(),
+            )),
+        }
+    }
+
+    if report.failed.is_empty() {
+        tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+        report.committed = true;
+    } else {
+        tx.rollback().map_err(|e| format!("回滚事务失败: {}", e))?;
+        report.notes_inserted = 0;
+        report.notes_updated = 0;
+        report.messages_inserted = 0;
+    }
+
+    report.actions = actions;
+    Ok(report)
+}
+
+/// 执行 `PRAGMA integrity_check`，返回发现的问题（为空表示数据库完好）
+pub fn integrity_check(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("数据库完整性检查失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("数据库完整性检查失败: {}", e))?;
+    let mut problems = Vec::new();
+    for row in rows {
+        let line = row.map_err(|e| format!("数据库完整性检查失败: {}", e))?;
+        if line != "ok" {
+            problems.push(line);
+        }
+    }
+    Ok(problems)
+}
+
+/// 在线复制数据库（`VACUUM INTO`），返回副本大小（字节）
+///
+/// 与直接复制文件不同，即使数据库正被前端打开也能得到一致的副本，
+/// 并且包含尚未写回主文件的 `-wal` 内容。副本先写入目标目录中的临时文件，
+/// 通过完整性检查后才替换为 `dest`，失败时不会改动目标位置已有的文件
+pub fn copy_database(src: &Path, dest: &Path) -> Result<u64, String> {
+    let file_name = dest
+        .file_name()
+        .ok_or_else(|| format!("无效的目标路径: {}", dest.to_string_lossy()))?;
+    let temp_path = dest.with_file_name(format!(".{}.copying", file_name.to_string_lossy()));
+    // VACUUM INTO 要求目标文件不存在
+    if temp_path.exists() {
+        std::fs::remove_file(&temp_path).map_err(|e| format!("清理临时文件失败: {}", e))?;
+    }
+
+    let result = (|| {
+        let conn = open_read_only(src)?;
+        conn.execute("VACUUM INTO ?1", [temp_path.to_string_lossy()])
+            .map_err(|e| format!("复制数据库失败: {}", e))?;
+        drop(conn);
+
+        let copy = open_read_only(&temp_path)?;
+        let problems = integrity_check(&copy)?;
+        if !problems.is_empty() {
+            return Err(format!("数据库副本校验失败: {}", problems.join("; ")));
+        }
+        drop(copy);
+
+        std::fs::rename(&temp_path, dest).map_err(|e| format!("保存数据库副本失败: {}", e))?;
+        std::fs::metadata(dest)
+            .map(|m| m.len())
+            .map_err(|e| format!("获取数据库文件信息失败: {}", e))
+    })();
+
+    if result.is_err() && temp_path.exists() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 打开（不存在时创建）数据库，执行结构迁移并做完整性检查
+///
+/// 运行时切换到其他数据库前调用，确认新数据库可用且结构是最新版本；
+/// 任意一步失败都不会影响当前正在使用的数据库
+pub fn prepare_database(path: &Path) -> Result<(), String> {
+    schema::migrate_file(path)?;
+
+    let conn = open_read_only(path)?;
+    let problems = integrity_check(&conn)?;
+    if !problems.is_empty() {
+        return Err(format!("数据库完整性检查失败: {}", problems.join("; ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConflictOptions, ConflictStrategy};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn export_reads_notes_and_messages() {
+        let conn = setup();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, tags, is_favorite, is_deleted, created_at, updated_at, reminder_date, reminder_enabled)
+             VALUES ('会议', '<p>内容</p>', '[\"工作\",\"周会\"]', 1, 0, '2024-01-01T00:00:00.000Z', '2024-01-02T00:00:00.000Z', '2024-01-03T09:00:00.000Z', 1);
+             INSERT INTO notes (title, content, created_at, updated_at, is_deleted)
+             VALUES ('草稿', '', '2024-02-01T00:00:00.000Z', '2024-02-01T00:00:00.000Z', 1);
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (1, 'user', '总结一下', '2024-01-02T00:00:00.000Z');",
+        )
+        .unwrap();
+
+        let data = build_export(&conn).unwrap();
+        assert_eq!(data.version, EXPORT_VERSION);
+        assert_eq!(data.notes.len(), 2);
+        assert_eq!(data.chat_messages.len(), 1);
+
+        let note = &data.notes[0];
+        assert_eq!(note.id, Some(1));
+        assert_eq!(note.tags, vec!["工作".to_string(), "周会".to_string()]);
+        assert_eq!(note.is_favorite, 1);
+        assert_eq!(
+            note.reminder_date.as_deref(),
+            Some("2024-01-03T09:00:00.000Z")
+        );
+        assert_eq!(note.reminder_enabled, 1);
+
+        let deleted = &data.notes[1];
+        assert!(deleted.tags.is_empty());
+        assert_eq!(deleted.is_deleted, 1);
+        assert_eq!(deleted.reminder_date, None);
+
+        assert_eq!(data.chat_messages[0].note_id, 1);
+        assert_eq!(data.chat_messages[0].role, "user");
+    }
+
+    #[test]
+    fn filter_matches_tags_dates_favorites_and_trash() {
+        let conn = setup();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, tags, is_favorite, is_deleted, created_at, updated_at)
+             VALUES ('甲', '', '[\"项目A\",\"会议\"]', 1, 0, '2024-01-10T08:00:00.000Z', '2024-03-01T00:00:00.000Z');
+             INSERT INTO notes (title, content, tags, is_favorite, is_deleted, created_at, updated_at)
+             VALUES ('乙', '', '[\"项目A\"]', 0, 0, '2024-02-10T08:00:00.000Z', '2024-02-10T08:00:00.000Z');
+             INSERT INTO notes (title, content, tags, is_favorite, is_deleted, created_at, updated_at)
+             VALUES ('丙', '', '[\"项目A\",\"会议\"]', 0, 1, '2024-01-20T08:00:00.000Z', '2024-01-20T08:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (2, 'user', '问题', '2024-02-10T08:00:00.000Z');
+             INSERT INTO chat_messages (note_id, role, content, timestamp)
+             VALUES (3, 'user', '问题', '2024-01-20T08:00:00.000Z');",
+        )
+        .unwrap();
+
+        let titles = |filter: ExportFilter| -> Vec<String> {
+            read_filtered_notes(&conn, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|n| n.title)
+                .collect()
+        };
+
+        assert_eq!(titles(ExportFilter::default()), ["甲", "乙"]);
+        assert_eq!(
+            titles(ExportFilter {
+                tags: vec!["项目A".to_string(), "会议".to_string()],
+                tag_match: TagMatch::All,
+                deleted: DeletedFilter::Include,
+                ..Default::default()
+            }),
+            ["甲", "丙"]
+        );
+        assert_eq!(
+            titles(ExportFilter {
+                deleted: DeletedFilter::Only,
+                ..Default::default()
+            }),
+            ["丙"]
+        );
+        assert_eq!(
+            titles(ExportFilter {
+                created_from: Some("2024-01-01".to_string()),
+                created_to: Some("2024-02-01".to_string()),
+                ..Default::default()
+            }),
+            ["甲"]
+        );
+        assert_eq!(
+            titles(ExportFilter {
+                updated_from: Some("2024-02-10T08:00:00Z".to_string()),
+                is_favorite: Some(false),
+                ..Default::default()
+            }),
+            ["乙"]
+        );
+
+        let err = read_filtered_notes(
+            &conn,
+            &ExportFilter {
+                created_to: Some("下周".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.contains("created_to"));
+
+        let data = build_filtered_export(
+            &conn,
+            Some(&ExportFilter {
+                note_ids: vec![2, 3],
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(data.notes.len(), 1);
+        assert_eq!(data.chat_messages.len(), 1);
+        assert_eq!(data.chat_messages[0].note_id, 2);
+        assert_eq!(build_filtered_export(&conn, None).unwrap().notes.len(), 3);
+    }
+
+    #[test]
+    fn export_of_empty_database_is_valid() {
+        let conn = setup();
+        let data = build_export(&conn).unwrap();
+        assert!(data.notes.is_empty());
+        assert!(data.chat_messages.is_empty());
+
+        let json = serde_json::to_string(&data).unwrap();
+        let parsed: ExportData = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.version, EXPORT_VERSION);
+    }
+
+    #[test]
+    fn invalid_tags_json_decodes_to_empty() {
+        assert!(decode_tags("").is_empty());
+        assert!(decode_tags("not json").is_empty());
+        assert!(decode_tags("{\"a\":1}").is_empty());
+        assert_eq!(decode_tags("[\"a\"]"), vec!["a".to_string()]);
+    }
+
+    fn sample_note(id: i64, title: &str) -> Note {
+        Note {
+            id: Some(id),
+            title: title.to_string(),
+            content: format!("<p>{}</p>", title),
+            tags: vec!["导入".to_string()],
+            is_favorite: 0,
+            is_deleted: 0,
+            created_at: "2024-01-01T00:00:00.000Z".to_string(),
+            updated_at: "2024-01-01T00:00:00.000Z".to_string(),
+            reminder_date: None,
+            reminder_enabled: 0,
+        }
+    }
+
+    fn sample_message(note_id: i64, role: &str) -> ChatMessage {
+        ChatMessage {
+            id: None,
+            note_id,
+            role: role.to_string(),
+            content: "hi".to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn import_remaps_message_note_ids() {
+        let mut conn = setup();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at) VALUES ('已有', '', 'x', 'x')",
+        )
+        .unwrap();
+
+        let data = ExportData {
+            version: EXPORT_VERSION.to_string(),
+            exported_at: String::new(),
+            notes: vec![sample_note(1, "a"), sample_note(7, "b")],
+            chat_messages: vec![
+                sample_message(7, "user"),
+                sample_message(1, "assistant"),
+                sample_message(42, "user"),
+            ],
+        };
+
+        let report = import_export_data(&mut conn, &data, &ImportOptions::default()).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 2);
+        assert_eq!(report.messages_inserted, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].index, 2);
+
+        let messages = read_chat_messages(&conn).unwrap();
+        assert_eq!(messages[0].note_id, 3);
+        assert_eq!(messages[1].note_id, 2);
+        assert_eq!(read_notes(&conn).unwrap()[1].tags, vec!["导入".to_string()]);
+    }
+
+    #[test]
+    fn import_rolls_back_on_failure() {
+        let mut conn = setup();
+        let data = ExportData {
+            version: EXPORT_VERSION.to_string(),
+            exported_at: String::new(),
+            notes: vec![sample_note(1, "a")],
+            chat_messages: vec![sample_message(1, "user"), sample_message(1, "system")],
+        };
+
+        let report = import_export_data(&mut conn, &data, &ImportOptions::default()).unwrap();
+        assert!(!report.committed);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].kind, "chat_message");
+        assert_eq!(report.failed[0].index, 1);
+        assert!(read_notes(&conn).unwrap().is_empty());
+        assert!(read_chat_messages(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn normalizes_timestamps() {
+        let expected = Some("2024-01-01T00:00:00.000Z".to_string());
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!(1704067200000_i64)),
+            expected
+        );
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!(1704067200)),
+            expected
+        );
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!("2024-01-01T00:00:00Z")),
+            expected
+        );
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!("2024-01-01T08:00:00+08:00")),
+            expected
+        );
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!("2024-01-01")),
+            expected
+        );
+        assert_eq!(
+            normalize_timestamp(&serde_json::json!({ "$date": "2024-01-01T00:00:00.000Z" })),
+            expected
+        );
+        assert_eq!(normalize_timestamp(&serde_json::json!("昨天")), None);
+        assert_eq!(normalize_timestamp(&serde_json::json!(null)), None);
+    }
+
+    #[test]
+    fn skipped_notes_drop_their_messages() {
+        let mut conn = setup();
+        let data = ExportData {
+            version: EXPORT_VERSION.to_string(),
+            exported_at: String::new(),
+            notes: vec![sample_note(1, "a")],
+            chat_messages: vec![sample_message(1, "user")],
+        };
+        import_export_data(&mut conn, &data, &ImportOptions::default()).unwrap();
+
+        let options = ImportOptions {
+            conflict: ConflictOptions {
+                strategy: ConflictStrategy::Skip,
+                ..Default::default()
+            },
+            dry_run: false,
+        };
+        let report = import_export_data(&mut conn, &data, &options).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.notes_inserted, 0);
+        assert_eq!(report.actions[0].action, NoteAction::Skip);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(read_chat_messages(&conn).unwrap().len(), 1);
+
+        let dry_run = ImportOptions {
+            dry_run: true,
+            ..options
+        };
+        let report = import_export_data(&mut conn, &data, &dry_run).unwrap();
+        assert!(report.dry_run && !report.committed);
+        assert_eq!(report.actions[0].existing_id, Some(1));
+    }
+
+    #[test]
+    fn copy_database_includes_wal_content_and_verifies_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("jdnotes.db");
+        let conn = Connection::open(&src).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.pragma_update(None, "wal_autocheckpoint", 0).unwrap();
+        conn.execute_batch(include_str!("../migrations/001_initial.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (title, content, created_at, updated_at)
+             VALUES ('未写回', '', '2024-01-01T00:00:00.000Z', '2024-01-01T00:00:00.000Z');",
+        )
+        .unwrap();
+        // 源数据库保持打开，新数据仍在 -wal 中
+
+        let dest_dir = dir.path().join("新位置");
+        std::fs::create_dir(&dest_dir).unwrap();
+        let dest = dest_dir.join("jdnotes.db");
+        std::fs::write(&dest, b"old").unwrap();
+        let size = copy_database(&src, &dest).unwrap();
+        assert_eq!(size, std::fs::metadata(&dest).unwrap().len());
+
+        let copy = open_read_only(&dest).unwrap();
+        assert!(integrity_check(&copy).unwrap().is_empty());
+        let notes = read_notes(&copy).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title, "未写回");
+        assert_eq!(std::fs::read_dir(&dest_dir).unwrap().count(), 1);
+
+        assert!(copy_database(&dir.path().join("missing.db"), &dest).is_err());
+        assert_eq!(
+            read_notes(&open_read_only(&dest).unwrap()).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn prepare_database_migrates_new_file_and_rejects_invalid_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jdnotes.db");
+        prepare_database(&path).unwrap();
+        let conn = open_read_only(&path).unwrap();
+        assert_eq!(count_rows(&conn, "notes").unwrap(), 0);
+        assert_eq!(count_rows(&conn, "chat_messages").unwrap(), 0);
+        drop(conn);
+        // 已迁移的数据库可以重复执行
+        prepare_database(&path).unwrap();
+
+        let invalid = dir.path().join("invalid.db");
+        std::fs::write(&invalid, b"not a database file").unwrap();
+        assert!(prepare_database(&invalid).is_err());
+        assert_eq!(std::fs::read(&invalid).unwrap(), b"not a database file");
+    }
+}
@@ -0,0 +1,64 @@
+-- 1.2.0 及更早版本的数据库结构（由前端 SQL 插件执行 001_initial.sql 建立）
+-- 已发布的结构不能修改，仅用于测试从该版本升级
+
+-- 笔记表
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    tags TEXT NOT NULL DEFAULT '[]',  -- JSON 数组
+    is_favorite INTEGER NOT NULL DEFAULT 0,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,  -- ISO 8601 格式
+    updated_at TEXT NOT NULL,
+    reminder_date TEXT,  -- ISO 8601 格式，可为 NULL
+    reminder_enabled INTEGER NOT NULL DEFAULT 0
+);
+
+-- 聊天消息表
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id INTEGER NOT NULL,
+    role TEXT NOT NULL CHECK(role IN ('user', 'assistant')),
+    content TEXT NOT NULL,
+    timestamp TEXT NOT NULL,  -- ISO 8601 格式
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+-- 索引优化
+CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_notes_created_at ON notes(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_notes_is_deleted ON notes(is_deleted);
+CREATE INDEX IF NOT EXISTS idx_notes_is_favorite ON notes(is_favorite);
+CREATE INDEX IF NOT EXISTS idx_notes_reminder ON notes(reminder_enabled, reminder_date);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_note_id ON chat_messages(note_id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_timestamp ON chat_messages(timestamp);
+
+-- 应用配置表（存储数据库路径等）
+CREATE TABLE IF NOT EXISTS app_config (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- tauri-plugin-sql（sqlx）的迁移记录
+CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+    version BIGINT PRIMARY KEY,
+    description TEXT NOT NULL,
+    installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    success BOOLEAN NOT NULL,
+    checksum BLOB NOT NULL,
+    execution_time BIGINT NOT NULL
+);
+INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+VALUES (1, 'create initial tables', 1, X'00', 1000000);
+
+-- 示例数据
+INSERT INTO notes (title, content, tags, is_favorite, created_at, updated_at, reminder_date, reminder_enabled)
+VALUES ('欢迎使用', '# 欢迎使用', '["入门"]', 1, '2024-01-01T00:00:00.000Z', '2024-01-02T00:00:00.000Z', '2024-02-01T09:00:00.000Z', 1);
+INSERT INTO notes (title, content, is_deleted, created_at, updated_at)
+VALUES ('已删除', '', 1, '2024-01-03T00:00:00.000Z', '2024-01-03T00:00:00.000Z');
+INSERT INTO chat_messages (note_id, role, content, timestamp)
+VALUES (1, 'user', '总结这篇笔记', '2024-01-02T00:00:00.000Z');
+INSERT INTO chat_messages (note_id, role, content, timestamp)
+VALUES (1, 'assistant', '这是一篇入门笔记。', '2024-01-02T00:00:01.000Z');
+INSERT INTO app_config (key, value) VALUES ('theme', 'dark');
@@ -284,6 +284,16 @@ function App() {
     setContentToInsert(null)
   }, [])
 
+  // 点击笔记中的笔记链接
+  const handleOpenNoteLink = useCallback(async (id: number) => {
+    const note = await noteOperations.get(id)
+    if (!note) {
+      toast.error('链接的笔记不存在')
+      return
+    }
+    handleSelectNote(note)
+  }, [handleSelectNote])
+
   // 从命令面板选择笔记
   const handleCommandSelectNote = (id: number) => {
     const note = notes.find((n) => n.id === id)
@@ -408,6 +418,7 @@ function App() {
                   onToggleChat={toggleChat}
                   onCreateNote={handleCreateNote}
                   onContentInserted={handleContentInserted}
+                  onOpenNote={handleOpenNoteLink}
                   onSetReminder={handleSetReminder}
                   onClearReminder={handleClearReminder}
                 />
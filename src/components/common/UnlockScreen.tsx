@@ -0,0 +1,53 @@
+import { useState } from 'react'
+import { dbOperations } from '../../lib/db'
+import { Lock } from 'lucide-react'
+
+/**
+ * 加密数据库的解锁界面
+ * 解锁成功后重新加载页面，前端使用后端建立的加密连接
+ */
+export function UnlockScreen() {
+  const [passphrase, setPassphrase] = useState('')
+  const [error, setError] = useState('')
+  const [unlocking, setUnlocking] = useState(false)
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault()
+    if (!passphrase || unlocking) return
+    setUnlocking(true)
+    setError('')
+    try {
+      await dbOperations.unlock(passphrase)
+      window.location.reload()
+    } catch (err) {
+      setError(String(err))
+      setUnlocking(false)
+    }
+  }
+
+  return (
+    <div className="flex items-center justify-center h-screen bg-[#F9FBFC] dark:bg-[#0B0D11]">
+      <form onSubmit={handleSubmit} className="w-72 flex flex-col items-center">
+        <Lock className="h-12 w-12 mb-4 text-slate-400 dark:text-slate-500" strokeWidth={1} />
+        <p className="text-[14px] text-slate-600 dark:text-slate-300">数据库已加密</p>
+        <p className="text-[12px] mt-1 mb-4 text-slate-400 dark:text-slate-500">请输入密码解锁</p>
+        <input
+          type="password"
+          value={passphrase}
+          onChange={(e) => setPassphrase(e.target.value)}
+          autoFocus
+          placeholder="密码"
+          className="w-full px-3 py-2 text-sm text-gray-900 dark:text-gray-100 bg-gray-50 dark:bg-gray-800 border border-gray-200 dark:border-gray-700 rounded-lg focus:ring-1 focus:ring-[#5E6AD2] focus:border-transparent outline-none transition-all"
+        />
+        {error && <p className="w-full mt-2 text-[12px] text-red-500">{error}</p>}
+        <button
+          type="submit"
+          disabled={!passphrase || unlocking}
+          className="w-full mt-4 px-4 py-2 bg-[#5E6AD2] text-white text-[13px] rounded-lg hover:bg-[#4F5ABF] transition-colors btn-press disabled:opacity-50"
+        >
+          {unlocking ? '正在解锁...' : '解锁'}
+        </button>
+      </form>
+    </div>
+  )
+}
@@ -4,4 +4,5 @@ export { SidebarItem } from './SidebarItem'
 export { NoteCard } from './NoteCard'
 export { EmptyState, NoNotesState } from './EmptyState'
 export { Select } from './Select'
+export { UnlockScreen } from './UnlockScreen'
 export type { SelectOption } from './Select'
@@ -12,7 +12,7 @@ import { AIReviewToolbar } from '../ai/AIReviewToolbar'
 import { SlashCommand } from './SlashCommand'
 import { useEditorAI, useSlashCommand } from '../../hooks'
 import { useAutoTitle } from '../../hooks/useAutoTitle'
-import { formatDateTime, formatTime, isSameDay } from '../../lib/utils'
+import { formatDateTime, formatTime, isSameDay, parseNoteLink } from '../../lib/utils'
 import { EditorHeader } from './EditorHeader'
 
 interface EditorProps {
@@ -27,6 +27,7 @@ interface EditorProps {
   onTagsChange?: (tags: string[]) => void
   contentToInsert?: string | null // 要插入的内容
   onContentInserted?: () => void // 插入完成后的回调
+  onOpenNote?: (id: number) => void // 点击笔记链接（jdnotes://note/<id>）时打开对应笔记
 }
 
 export function Editor({
@@ -41,6 +42,7 @@ export function Editor({
   onTagsChange,
   contentToInsert,
   onContentInserted,
+  onOpenNote,
 }: EditorProps) {
   const editorContainerRef = useRef<HTMLDivElement>(null)
   const diffRef = useRef<HTMLDivElement>(null)
@@ -49,6 +51,10 @@ export function Editor({
   const contentRef = useRef(content)
   contentRef.current = content
 
+  // 编辑器只创建一次，点击链接时通过 ref 调用最新的回调
+  const onOpenNoteRef = useRef(onOpenNote)
+  onOpenNoteRef.current = onOpenNote
+
   // 用于跟踪用户输入产生的最新内容，避免不必要的 setContent 调用
   const lastEmittedContentRef = useRef<string | null>(null)
 
@@ -62,6 +68,10 @@ export function Editor({
           levels: [1, 2, 3],
         },
         codeBlock: false,
+        link: {
+          // 允许笔记之间的链接，否则解析时会被当作不安全的地址移除
+          protocols: ['jdnotes'],
+        },
       }),
       CodeBlock.extend({
         addAttributes() {
@@ -119,6 +129,16 @@ export function Editor({
         class:
           'prose prose-slate dark:prose-invert prose-lg max-w-none focus:outline-none min-h-[300px]',
       },
+      // 阅读模式下点击笔记链接打开对应笔记，编辑模式下需按住 Ctrl / Cmd 点击
+      handleClick: (view, _pos, event) => {
+        const link = (event.target as HTMLElement).closest('a')
+        const id = parseNoteLink(link?.getAttribute('href'))
+        if (id === null) return false
+        if (view.editable && !(event.ctrlKey || event.metaKey)) return false
+        event.preventDefault()
+        onOpenNoteRef.current?.(id)
+        return true
+      },
     },
     onCreate: ({ editor }) => {
       const latestContent = contentRef.current
@@ -24,6 +24,7 @@ interface MainContentProps {
   onToggleChat: () => void
   onCreateNote: () => void
   onContentInserted: () => void
+  onOpenNote?: (id: number) => void
   onSetReminder?: (noteId: number, reminderDate: Date) => void
   onClearReminder?: (noteId: number) => void
 }
@@ -44,6 +45,7 @@ export function MainContent({
   onToggleChat,
   onCreateNote,
   onContentInserted,
+  onOpenNote,
   onSetReminder,
   onClearReminder,
 }: MainContentProps) {
@@ -210,6 +212,7 @@ export function MainContent({
               onTagsChange={onTagsChange}
               contentToInsert={contentToInsert}
               onContentInserted={onContentInserted}
+              onOpenNote={onOpenNote}
             />
           </motion.div>
         ) : (
@@ -94,7 +94,12 @@ async function getDatabase(): Promise<Database> {
   
   // 获取数据库 URL
   const dbUrl = await invoke<string>('get_database_url')
-  database = await Database.load(dbUrl)
+  const status = await invoke<EncryptionStatus>('get_encryption_status')
+  if (!status.unlocked) {
+    throw new Error('数据库已加密，请先输入密码解锁')
+  }
+  // 加密数据库的连接由后端解锁时建立（SQL 插件无法设置密钥）
+  database = status.encrypted ? Database.get(dbUrl) : await Database.load(dbUrl)
   return database
 }
 
@@ -558,6 +563,20 @@ export const chatOperations = {
 
 // ============= 数据库管理功能 =============
 
+// 数据库加密状态
+export interface EncryptionStatus {
+  encrypted: boolean
+  unlocked: boolean
+}
+
+// 启用 / 关闭加密或修改密码的结果
+export interface EncryptionReport {
+  encrypted: boolean
+  backup_path: string // 操作前的备份，已转换为操作后的加密状态，可直接恢复
+  plaintext_backups: string[] // 启用加密后仍是明文的备份，应手动删除
+  stale_backups: string[] // 修改密码或关闭加密后仍使用原密码加密的备份
+}
+
 export const dbOperations = {
   // 获取数据库路径
   async getPath(): Promise<string> {
@@ -585,6 +604,31 @@ export const dbOperations = {
     return await invoke<string>('change_database_location', { newDir })
   },
 
+  // 获取数据库加密状态
+  async getEncryptionStatus(): Promise<EncryptionStatus> {
+    return await invoke<EncryptionStatus>('get_encryption_status')
+  },
+
+  // 用密码解锁数据库
+  async unlock(passphrase: string): Promise<void> {
+    await invoke('unlock_database', { passphrase })
+  },
+
+  // 启用数据库加密（完成后应用自动重新加载）
+  async enableEncryption(passphrase: string): Promise<EncryptionReport> {
+    return await invoke<EncryptionReport>('enable_encryption', { passphrase })
+  },
+
+  // 修改数据库密码
+  async changePassphrase(oldPassphrase: string, newPassphrase: string): Promise<EncryptionReport> {
+    return await invoke<EncryptionReport>('change_database_passphrase', { oldPassphrase, newPassphrase })
+  },
+
+  // 关闭数据库加密
+  async disableEncryption(passphrase: string): Promise<EncryptionReport> {
+    return await invoke<EncryptionReport>('disable_encryption', { passphrase })
+  },
+
   // 导出数据为 JSON
   async exportJSON(): Promise<string> {
     const db = await getDatabase()
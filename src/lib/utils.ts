@@ -13,6 +13,16 @@ export function extractPreview(markdown: string): string {
   return text.slice(0, 80) + (text.length > 80 ? '...' : '')
 }
 
+// 笔记之间链接的地址前缀（如 Notion 导入时改写的页面链接）
+export const NOTE_LINK_PREFIX = 'jdnotes://note/'
+
+// 解析笔记链接，返回笔记 ID，不是笔记链接时返回 null
+export function parseNoteLink(href: string | null | undefined): number | null {
+  if (!href?.startsWith(NOTE_LINK_PREFIX)) return null
+  const id = Number.parseInt(href.slice(NOTE_LINK_PREFIX.length), 10)
+  return Number.isNaN(id) ? null : id
+}
+
 // 格式化日期（相对时间）
 export function formatDate(date: Date): string {
   const now = new Date()
@@ -2,9 +2,17 @@ import { StrictMode } from 'react'
 import { createRoot } from 'react-dom/client'
 import './index.css'
 import App from './App.tsx'
+import { UnlockScreen } from './components/common'
+import { dbOperations } from './lib/db'
 
-createRoot(document.getElementById('root')!).render(
-  <StrictMode>
-    <App />
-  </StrictMode>,
-)
+// 加密数据库未解锁时先显示解锁界面
+dbOperations
+  .getEncryptionStatus()
+  .catch(() => null)
+  .then((status) => {
+    createRoot(document.getElementById('root')!).render(
+      <StrictMode>
+        {status && !status.unlocked ? <UnlockScreen /> : <App />}
+      </StrictMode>,
+    )
+  })